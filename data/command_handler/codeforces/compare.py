@@ -0,0 +1,143 @@
+import sys
+import json
+from typing import List, Dict, Any
+from io import BytesIO
+import matplotlib.pyplot as plt
+
+# Colors for each compared user, in order
+USER_COLORS = ["#4169E1", "#FF6347", "#3CB371", "#DAA520"]
+
+
+def plot_summary(ax, users: List[Dict[str, Any]]):
+    """Draws the summary table: rating, max rating, solved and unique counts."""
+    ax.axis("off")
+    ax.set_title("Overview")
+
+    def fmt(value):
+        return "-" if value is None else str(value)
+
+    rows = [
+        ["Rating"] + [fmt(u["rating"]) for u in users],
+        ["Max rating"] + [fmt(u["max_rating"]) for u in users],
+        ["Solved"] + [str(u["solved"]) for u in users],
+        ["Only solved by"] + [str(u["unique"]) for u in users],
+        ["Hardest unique"]
+        + ["\n".join(u["unique_hardest"][:3]) or "-" for u in users],
+    ]
+    table = ax.table(
+        cellText=rows,
+        colLabels=[""] + [u["handle"] for u in users],
+        loc="center",
+        cellLoc="center",
+    )
+    table.auto_set_font_size(False)
+    table.set_fontsize(8)
+    table.scale(1, 1.6)
+    # The last row holds several lines of text
+    for col in range(len(users) + 1):
+        table[len(rows), col].set_height(table[len(rows), col].get_height() * 2.5)
+    for i in range(len(users)):
+        table[0, i + 1].get_text().set_color(USER_COLORS[i])
+
+
+def plot_grouped_bars(ax, labels: List[str], counts: List[List[int]], handles: List[str], horizontal: bool):
+    """Draws one bar per user for each label."""
+    n = len(handles)
+    width = 0.8 / n
+    positions = range(len(labels))
+
+    for i, handle in enumerate(handles):
+        offsets = [p - 0.4 + width * (i + 0.5) for p in positions]
+        values = [c[i] for c in counts]
+        if horizontal:
+            ax.barh(offsets, values, height=width, color=USER_COLORS[i], label=handle)
+        else:
+            ax.bar(offsets, values, width=width, color=USER_COLORS[i], label=handle)
+
+    if horizontal:
+        ax.set_yticks(list(positions))
+        ax.set_yticklabels(labels, fontsize=7)
+        ax.invert_yaxis()
+    else:
+        ax.set_xticks(list(positions))
+        ax.set_xticklabels(labels, fontsize=6, rotation=60)
+    ax.legend(fontsize=7)
+
+
+def plot_contests(ax, contests: List[Dict[str, Any]], handles: List[str]):
+    """Draws the table of contests everyone took part in."""
+    ax.axis("off")
+    ax.set_title("Common rated contests (rank)")
+
+    if not contests:
+        ax.text(0.5, 0.5, "No common contests", ha="center", va="center")
+        return
+
+    rows = []
+    for contest in contests:
+        name = contest["name"]
+        if len(name) > 45:
+            name = name[:42] + "..."
+        ranks = contest["ranks"]
+        best = min(ranks)
+        rows.append([name] + [f"{r} *" if r == best else str(r) for r in ranks])
+
+    table = ax.table(
+        cellText=rows,
+        colLabels=["Contest"] + handles,
+        loc="center",
+        cellLoc="center",
+    )
+    table.auto_set_font_size(False)
+    table.set_fontsize(7)
+    table.scale(1, 1.3)
+    table.auto_set_column_width(list(range(len(handles) + 1)))
+
+
+def plot_comparison(data: Dict[str, Any]) -> bytes:
+    users = data["users"]
+    handles = [u["handle"] for u in users]
+
+    fig, axes = plt.subplots(2, 2, dpi=200, figsize=(14, 10))
+
+    plot_summary(axes[0][0], users)
+
+    buckets = data["buckets"]
+    axes[0][1].set_title("Solved problems by rating")
+    plot_grouped_bars(
+        axes[0][1],
+        [str(b[0]) for b in buckets],
+        [b[1] for b in buckets],
+        handles,
+        horizontal=False,
+    )
+
+    tags = data["tags"]
+    axes[1][0].set_title("Solved problems by tag")
+    plot_grouped_bars(
+        axes[1][0],
+        [t[0] for t in tags],
+        [t[1] for t in tags],
+        handles,
+        horizontal=True,
+    )
+
+    plot_contests(axes[1][1], data["contests"], handles)
+
+    fig.suptitle(" vs ".join(handles))
+    fig.tight_layout()
+
+    with BytesIO() as buffer:
+        fig.savefig(buffer, format="png")
+        return buffer.getvalue()
+
+
+def main():
+    """Reads the comparison prepared by the bot from stdin and writes a PNG to stdout."""
+    data = json.load(sys.stdin)
+    image_bytes = plot_comparison(data)
+    sys.stdout.buffer.write(image_bytes)
+
+
+if __name__ == "__main__":
+    main()
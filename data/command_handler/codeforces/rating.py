@@ -1,4 +1,5 @@
 import sys
+import json
 from typing import List, Dict, Any, Tuple
 import requests
 from datetime import datetime
@@ -18,6 +19,20 @@ RATING_COLORS = [
     (6000, "#FF0000", "Red"),
 ]
 
+# Line colors for each user when several users are drawn together
+LINE_COLORS = [
+    "#4169E1",
+    "#000000",
+    "#FF1493",
+    "#8B4513",
+    "#2F4F4F",
+    "#FFD700",
+    "#00FF7F",
+    "#9400D3",
+    "#FF4500",
+    "#1E90FF",
+]
+
 
 def fetch_rating_history(cf_id: str) -> List[Dict[str, Any]]:
     """Fetches user rating history from the Codeforces API."""
@@ -52,30 +67,35 @@ def process_rating_data(
     return dates, ratings
 
 
-def plot_rating_history(cf_id: str, dates: List[datetime], ratings: List[int]) -> bytes:
-    """Generates a rating history plot and returns it as bytes."""
+def plot_rating_history(series: List[Tuple[str, List[datetime], List[int]]]) -> bytes:
+    """Generates a rating history plot of one or more users and returns it as bytes."""
     fig, ax = plt.subplots(dpi=300, figsize=(10, 5))
 
     # Plot data
-    ax.plot(
-        dates,
-        ratings,
-        "o-",
-        color="#4169E1",
-        alpha=0.8,
-        linewidth=1,
-        label="rating",
-        markersize=2,
-    )
+    for i, (cf_id, dates, ratings) in enumerate(series):
+        ax.plot(
+            dates,
+            ratings,
+            "o-",
+            color=LINE_COLORS[i % len(LINE_COLORS)],
+            alpha=0.8,
+            linewidth=1,
+            label=cf_id,
+            markersize=2,
+        )
 
     # Set titles and labels
-    ax.set_title(f"{cf_id}'s rating change")
+    if len(series) == 1:
+        ax.set_title(f"{series[0][0]}'s rating change")
+    else:
+        ax.set_title("Rating change")
     ax.set_xlabel("Time")
     ax.set_ylabel("Rating")
 
     # Set y-axis limits
-    min_rating, max_rating = min(ratings), max(ratings)
-    gap = (max_rating - min_rating) * 0.1
+    all_ratings = [r for _, _, ratings in series for r in ratings]
+    min_rating, max_rating = min(all_ratings), max(all_ratings)
+    gap = max((max_rating - min_rating) * 0.1, 50)
     y_min = min_rating - gap
     y_max = max_rating + gap
     ax.set_ylim(y_min, y_max)
@@ -92,7 +112,7 @@ def plot_rating_history(cf_id: str, dates: List[datetime], ratings: List[int]) -
     ax.xaxis.set_major_formatter(mdates.DateFormatter("%y-%m-%d"))
     plt.setp(ax.get_xticklabels(), rotation=20, ha="right")
 
-    ax.legend()
+    ax.legend(fontsize=6 if len(series) > 5 else None)
     fig.tight_layout()
 
     # Save to buffer
@@ -101,15 +121,39 @@ def plot_rating_history(cf_id: str, dates: List[datetime], ratings: List[int]) -
         return buffer.getvalue()
 
 
-def main():
-    """Main function to run the script."""
-    if len(sys.argv) < 2:
+def read_series_from_stdin() -> List[Tuple[str, List[datetime], List[int]]]:
+    """Reads [{"handle": ..., "points": [[timestamp, rating], ...]}, ...] prepared by the bot."""
+    series = []
+    for user in json.load(sys.stdin):
+        points = user["points"]
+        if not points:
+            continue
+        dates = [datetime.fromtimestamp(t) for t, _ in points]
+        ratings = [r for _, r in points]
+        series.append((user["handle"], dates, ratings))
+
+    if not series:
+        print("没有参赛记录", file=sys.stderr)
         sys.exit(1)
+    return series
+
 
-    cf_id = sys.argv[1]
-    history = fetch_rating_history(cf_id)
-    dates, ratings = process_rating_data(history)
-    image_bytes = plot_rating_history(cf_id, dates, ratings)
+def main():
+    """Main function to run the script.
+
+    With handles as arguments the histories are fetched from Codeforces,
+    otherwise they are read from stdin.
+    """
+    if len(sys.argv) >= 2:
+        series = []
+        for cf_id in sys.argv[1:]:
+            history = fetch_rating_history(cf_id)
+            dates, ratings = process_rating_data(history)
+            series.append((cf_id, dates, ratings))
+    else:
+        series = read_series_from_stdin()
+
+    image_bytes = plot_rating_history(series)
     sys.stdout.buffer.write(image_bytes)
 
 
@@ -0,0 +1,90 @@
+import sys
+import json
+from typing import Dict, Any
+from io import BytesIO
+import matplotlib.pyplot as plt
+
+STATE_COLORS = {
+    "solved": "#C8F7C5",
+    "first": "#5CD65C",
+    "rejected": "#F7C5C5",
+    "none": "#FFFFFF",
+}
+
+
+def plot_standings(data: Dict[str, Any]) -> bytes:
+    """Draws the standings of the given users as a table."""
+    problems = data["problems"]
+    icpc = data["icpc"]
+
+    columns = ["#", "Who", "Points"]
+    if icpc:
+        columns.append("Penalty")
+    columns += problems + ["Hacks", "Δ"]
+
+    cells = []
+    colors = []
+    for row in data["rows"]:
+        delta = row["delta"]
+        line = [
+            "-" if row["rank"] is None else str(row["rank"]),
+            row["who"],
+            row["points"],
+        ]
+        if icpc:
+            line.append(str(row["penalty"]))
+        line += [result["text"] for result in row["results"]]
+        line += [row["hacks"], "" if delta is None else f"{delta:+d}"]
+        cells.append(line)
+
+        line_colors = ["#FFFFFF"] * (4 if icpc else 3)
+        line_colors += [STATE_COLORS[result["state"]] for result in row["results"]]
+        line_colors += ["#FFFFFF", "#FFFFFF"]
+        colors.append(line_colors)
+
+    height = 1.2 + 0.45 * len(cells)
+    width = 4 + 0.7 * len(columns)
+    fig, ax = plt.subplots(dpi=200, figsize=(width, height))
+    ax.axis("off")
+
+    title = data["contest"]
+    if data["truncated"]:
+        title += f" (top {len(cells)})"
+    ax.set_title(title)
+
+    table = ax.table(
+        cellText=cells,
+        cellColours=colors,
+        colLabels=columns,
+        loc="center",
+        cellLoc="center",
+    )
+    table.auto_set_font_size(False)
+    table.set_fontsize(7)
+    table.scale(1, 2)
+    table.auto_set_column_width([1])
+
+    # Color the rating change
+    delta_col = len(columns) - 1
+    for i, row in enumerate(data["rows"]):
+        delta = row["delta"]
+        if delta is not None:
+            color = "#008000" if delta >= 0 else "#FF0000"
+            table[i + 1, delta_col].get_text().set_color(color)
+
+    fig.tight_layout()
+
+    with BytesIO() as buffer:
+        fig.savefig(buffer, format="png")
+        return buffer.getvalue()
+
+
+def main():
+    """Reads the standings prepared by the bot from stdin and writes a PNG to stdout."""
+    data = json.load(sys.stdin)
+    image_bytes = plot_standings(data)
+    sys.stdout.buffer.write(image_bytes)
+
+
+if __name__ == "__main__":
+    main()
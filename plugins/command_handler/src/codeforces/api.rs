@@ -0,0 +1,467 @@
+//! Codeforces API 客户端
+//!
+//! 所有对 CF API 的请求都应该经过这里：请求统一经过 `codeforces` 限速器，
+//! 返回值按接口缓存一段时间，失败时保留 API 返回的 `comment`。
+
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use kovi::chrono::Utc;
+use kovi::serde_json::{self, Value};
+use moka::future::Cache;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha512};
+
+use crate::CONFIG;
+
+use crate::duel::submission::Submission;
+use crate::utils::fetch_cf_api;
+
+const API_URL: &str = "https://codeforces.com/api";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum CfApiError {
+    #[error("找不到 CF 用户 {0}")]
+    HandleNotFound(String),
+    #[error("CF API 请求过于频繁，请稍后再试")]
+    RateLimited,
+    #[error("CF API 暂时不可用: {0}")]
+    Unavailable(String),
+    #[error("CF API 返回错误: {0}")]
+    Failed(String),
+    #[error("无法解析 CF API 的返回: {0}")]
+    InvalidResponse(String),
+}
+
+impl CfApiError {
+    /// 根据 API 返回的 comment 判断错误类型
+    fn from_comment(comment: String) -> Self {
+        if comment.contains("Call limit exceeded") {
+            return Self::RateLimited;
+        }
+        // 例如 "handles: User with handle tourist1 not found"
+        if let Some(rest) = comment.split("User with handle ").nth(1)
+            && let Some(handle) = rest.strip_suffix(" not found")
+        {
+            return Self::HandleNotFound(handle.to_string());
+        }
+        if comment.contains("temporarily unavailable") {
+            return Self::Unavailable(comment);
+        }
+        Self::Failed(comment)
+    }
+
+    /// CF 拒绝了对这场比赛的请求，例如比赛不存在或者榜单不公开，重试也没有用
+    ///
+    /// 这类错误的 comment 以出错的参数名开头，例如 "contestId: Contest with id 1 not found"
+    pub fn is_contest_rejected(&self) -> bool {
+        matches!(self, Self::Failed(comment) if comment.starts_with("contestId:"))
+    }
+}
+
+impl From<reqwest::Error> for CfApiError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Unavailable(e.to_string())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Response {
+    status: String,
+    comment: Option<String>,
+    result: Option<Value>,
+}
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap()
+});
+
+/// 按照 CF 的要求为请求签名，返回加上 apiKey、time 和 apiSig 之后的参数
+///
+/// apiSig 为 rand 加上 `rand/method?参数#secret` 的 SHA-512 的小写十六进制，
+/// 参数包括 apiKey 和 time，按名字排序，名字相同时按值排序
+fn sign<'a>(
+    method: &str,
+    params: &[(&'a str, String)],
+    key: &str,
+    secret: &str,
+    time: i64,
+    rand: &str,
+) -> Vec<(&'a str, String)> {
+    let mut params = params.to_vec();
+    params.push(("apiKey", key.to_string()));
+    params.push(("time", time.to_string()));
+    params.sort();
+
+    let query = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    let hash = Sha512::digest(format!("{}/{}?{}#{}", rand, method, query, secret));
+
+    params.push(("apiSig", format!("{}{}", rand, hex::encode(hash))));
+    params
+}
+
+/// 配置了 key 和 secret 时为请求签名
+fn with_credentials<'a>(method: &str, params: &[(&'a str, String)]) -> Vec<(&'a str, String)> {
+    let credentials = CONFIG
+        .get()
+        .and_then(|config| Some((config.cf_api_key.as_ref()?, config.cf_api_secret.as_ref()?)));
+
+    match credentials {
+        Some((key, secret)) => {
+            let rand = format!("{:06}", rand::rng().random_range(0..1_000_000));
+            sign(method, params, key, secret, Utc::now().timestamp(), &rand)
+        }
+        None => params.to_vec(),
+    }
+}
+
+async fn call<T: DeserializeOwned>(
+    method: &str,
+    params: &[(&str, String)],
+) -> Result<T, CfApiError> {
+    let params = with_credentials(method, params);
+    let request = CLIENT.get(format!("{}/{}", API_URL, method)).query(&params);
+    let res = fetch_cf_api(async move { request.send().await }).await?;
+
+    let status = res.status();
+    let text = res.text().await?;
+
+    // CF 出错时也会返回 JSON，只有限流和维护时会返回网页
+    let body = match serde_json::from_str::<Response>(&text) {
+        Ok(body) => body,
+        Err(_) if status == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            return Err(CfApiError::RateLimited);
+        }
+        Err(_) if !status.is_success() => return Err(CfApiError::Unavailable(status.to_string())),
+        Err(e) => return Err(CfApiError::InvalidResponse(e.to_string())),
+    };
+
+    if body.status != "OK" {
+        return Err(CfApiError::from_comment(
+            body.comment.unwrap_or_else(|| status.to_string()),
+        ));
+    }
+
+    let result = body
+        .result
+        .ok_or_else(|| CfApiError::InvalidResponse("缺少 result".to_string()))?;
+    serde_json::from_value(result).map_err(|e| CfApiError::InvalidResponse(e.to_string()))
+}
+
+/// 一个 API 接口及其缓存，缓存的键为请求参数
+struct Endpoint<T> {
+    method: &'static str,
+    cache: Cache<String, Arc<T>>,
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> Endpoint<T> {
+    fn new(method: &'static str, ttl: Duration, capacity: u64) -> Self {
+        Self {
+            method,
+            cache: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    fn key(params: &[(&str, String)]) -> String {
+        params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// 优先使用缓存，同时发起的相同请求只会请求一次
+    async fn get(&self, params: &[(&str, String)]) -> Result<Arc<T>, CfApiError> {
+        self.cache
+            .try_get_with(Self::key(params), async {
+                call(self.method, params).await.map(Arc::new)
+            })
+            .await
+            .map_err(|e| e.as_ref().clone())
+    }
+
+    /// 跳过缓存直接请求，并用结果更新缓存
+    async fn get_fresh(&self, params: &[(&str, String)]) -> Result<Arc<T>, CfApiError> {
+        let value = Arc::new(call::<T>(self.method, params).await?);
+        self.cache.insert(Self::key(params), value.clone()).await;
+        Ok(value)
+    }
+
+    /// 丢弃缓存的结果，下次请求时重新获取
+    async fn invalidate(&self, params: &[(&str, String)]) {
+        self.cache.invalidate(&Self::key(params)).await;
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub handle: String,
+    pub rating: Option<i64>,
+    pub max_rating: Option<i64>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingChange {
+    pub contest_id: i64,
+    pub contest_name: String,
+    pub handle: String,
+    pub rank: i64,
+    pub rating_update_time_seconds: i64,
+    pub old_rating: i64,
+    pub new_rating: i64,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Contest {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub phase: String,
+    pub duration_seconds: i64,
+    pub start_time_seconds: Option<i64>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Standings {
+    pub contest: Contest,
+    pub problems: Vec<ContestProblem>,
+    pub rows: Vec<RanklistRow>,
+}
+
+/// 榜单中的题目，和题库中的题目不同，带有这场比赛中的分数
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ContestProblem {
+    pub index: String,
+    #[serde(default)]
+    pub name: String,
+    /// CF 赛制的题目满分，ICPC 赛制没有
+    pub points: Option<f64>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RanklistRow {
+    pub party: Party,
+    pub rank: i64,
+    pub points: f64,
+    pub penalty: i64,
+    #[serde(default)]
+    pub successful_hack_count: i64,
+    #[serde(default)]
+    pub unsuccessful_hack_count: i64,
+    pub problem_results: Vec<ProblemResult>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Party {
+    pub members: Vec<Member>,
+    pub participant_type: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Member {
+    pub handle: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemResult {
+    pub points: f64,
+    pub rejected_attempt_count: i64,
+    pub best_submission_time_seconds: Option<i64>,
+}
+
+/// problemset.problems 的原始返回，单道题目解析失败时跳过而不是整体失败
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Problemset {
+    pub problems: Vec<Value>,
+    #[serde(default)]
+    pub problem_statistics: Vec<Value>,
+}
+
+static USER_INFO: LazyLock<Endpoint<Vec<User>>> =
+    LazyLock::new(|| Endpoint::new("user.info", Duration::from_secs(10 * 60), 200));
+static USER_STATUS: LazyLock<Endpoint<Vec<Submission>>> =
+    LazyLock::new(|| Endpoint::new("user.status", Duration::from_secs(60), 50));
+static USER_RATING: LazyLock<Endpoint<Vec<RatingChange>>> =
+    LazyLock::new(|| Endpoint::new("user.rating", Duration::from_secs(10 * 60), 100));
+static USER_RATED_LIST: LazyLock<Endpoint<Vec<User>>> =
+    LazyLock::new(|| Endpoint::new("user.ratedList", Duration::from_secs(10 * 60), 5));
+static CONTEST_LIST: LazyLock<Endpoint<Vec<Contest>>> =
+    LazyLock::new(|| Endpoint::new("contest.list", Duration::from_secs(10 * 60), 2));
+static CONTEST_STANDINGS: LazyLock<Endpoint<Standings>> =
+    LazyLock::new(|| Endpoint::new("contest.standings", Duration::from_secs(60), 20));
+static CONTEST_RATING_CHANGES: LazyLock<Endpoint<Vec<RatingChange>>> =
+    LazyLock::new(|| Endpoint::new("contest.ratingChanges", Duration::from_secs(10 * 60), 20));
+static PROBLEMSET: LazyLock<Endpoint<Problemset>> =
+    LazyLock::new(|| Endpoint::new("problemset.problems", Duration::from_secs(60), 1));
+
+/// user.info，返回顺序和 `handles` 一致
+pub async fn user_info(handles: &[&str]) -> Result<Arc<Vec<User>>, CfApiError> {
+    USER_INFO.get(&[("handles", handles.join(";"))]).await
+}
+
+/// user.status，`from` 从 1 开始，都不指定时返回全部提交
+pub async fn user_status(
+    handle: &str,
+    from: Option<usize>,
+    count: Option<usize>,
+) -> Result<Arc<Vec<Submission>>, CfApiError> {
+    USER_STATUS.get(&status_params(handle, from, count)).await
+}
+
+/// user.status，不经过缓存，用于把提交同步到本地，避免把过期的分页写入同步点
+pub async fn user_status_uncached(
+    handle: &str,
+    from: Option<usize>,
+    count: Option<usize>,
+) -> Result<Vec<Submission>, CfApiError> {
+    call(USER_STATUS.method, &status_params(handle, from, count)).await
+}
+
+/// 用户最近一次提交，不使用缓存
+pub async fn last_submission(handle: &str) -> Result<Option<Submission>, CfApiError> {
+    let submissions = USER_STATUS
+        .get_fresh(&status_params(handle, None, Some(1)))
+        .await?;
+    Ok(submissions.first().cloned())
+}
+
+fn status_params(handle: &str, from: Option<usize>, count: Option<usize>) -> Vec<(&str, String)> {
+    let mut params = vec![("handle", handle.to_string())];
+    if let Some(from) = from {
+        params.push(("from", from.to_string()));
+    }
+    if let Some(count) = count {
+        params.push(("count", count.to_string()));
+    }
+    params
+}
+
+/// user.rating，用户的 rating 变化历史
+pub async fn user_rating(handle: &str) -> Result<Arc<Vec<RatingChange>>, CfApiError> {
+    USER_RATING.get(&[("handle", handle.to_string())]).await
+}
+
+/// user.ratedList，参加了这场比赛的有 rating 的用户，rating 为当前值
+pub async fn rated_list(contest_id: i64) -> Result<Arc<Vec<User>>, CfApiError> {
+    USER_RATED_LIST
+        .get(&[
+            ("activeOnly", "false".to_string()),
+            ("includeRetired", "true".to_string()),
+            ("contestId", contest_id.to_string()),
+        ])
+        .await
+}
+
+/// contest.list
+pub async fn contest_list(gym: bool) -> Result<Arc<Vec<Contest>>, CfApiError> {
+    CONTEST_LIST.get(&[("gym", gym.to_string())]).await
+}
+
+/// contest.standings，`handles` 为空时返回完整榜单，`count` 限制返回的行数
+///
+/// 私有的 gym 和 mashup 需要配置 key 和 secret
+pub async fn contest_standings(
+    contest_id: i64,
+    handles: &[&str],
+    show_unofficial: bool,
+    count: Option<usize>,
+) -> Result<Arc<Standings>, CfApiError> {
+    let mut params = vec![
+        ("contestId", contest_id.to_string()),
+        ("showUnofficial", show_unofficial.to_string()),
+    ];
+    if !handles.is_empty() {
+        params.push(("handles", handles.join(";")));
+    }
+    if let Some(count) = count {
+        params.push(("count", count.to_string()));
+    }
+    CONTEST_STANDINGS.get(&params).await
+}
+
+/// contest.ratingChanges，比赛还没有更新 rating 时为空
+///
+/// 空的结果不会被缓存，rating 一更新就能查到
+pub async fn contest_rating_changes(contest_id: i64) -> Result<Arc<Vec<RatingChange>>, CfApiError> {
+    let params = [("contestId", contest_id.to_string())];
+    let changes = CONTEST_RATING_CHANGES.get(&params).await?;
+    if changes.is_empty() {
+        CONTEST_RATING_CHANGES.invalidate(&params).await;
+    }
+    Ok(changes)
+}
+
+/// problemset.problems
+pub async fn problemset() -> Result<Arc<Problemset>, CfApiError> {
+    PROBLEMSET.get(&[]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_from_comment() {
+        assert!(matches!(
+            CfApiError::from_comment("handles: User with handle tourist1 not found".to_string()),
+            CfApiError::HandleNotFound(handle) if handle == "tourist1"
+        ));
+        assert!(matches!(
+            CfApiError::from_comment("Call limit exceeded".to_string()),
+            CfApiError::RateLimited
+        ));
+        let error =
+            CfApiError::from_comment("contestId: Contest with id 99999 not found".to_string());
+        assert!(matches!(error, CfApiError::Failed(_)));
+        assert!(error.is_contest_rejected());
+        assert!(!CfApiError::Failed("Internal Server Error".to_string()).is_contest_rejected());
+        assert!(!CfApiError::RateLimited.is_contest_rejected());
+    }
+
+    #[test]
+    fn test_sign() {
+        // 参数取自 CF 文档中的例子，期望的签名是按文档描述的规则在本地用 sha512sum 算出的
+        let params = sign(
+            "contest.hacks",
+            &[("contestId", "566".to_string())],
+            "xxx",
+            "yyy",
+            1_000_000_000,
+            "123456",
+        );
+        let query = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        // 123456/contest.hacks?apiKey=xxx&contestId=566&time=1000000000#yyy 的 SHA-512
+        assert_eq!(
+            query,
+            "apiKey=xxx&contestId=566&time=1000000000&apiSig=123456\
+             6539c3d4a0f9353495f3e46e8ad6ec2e404891e38ecef28c33af07114c907ddf\
+             a5f5d3ee47aa834965320122c32d664912360043951a86a419cdd3a4ed777048"
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! 群友做题喜报
+//!
+//! 定时增量同步订阅群中绑定用户的提交，遇到以下情况时在群里播报：
+//! 通过了比自己 rating 高出很多的题、赛后补完了参加过的整场比赛、通过题数达到里程碑。
+//! 每个账号检查到的位置记录在数据库中，第一次检查时只记录位置，不播报以前的提交。
+//! 退订后重新订阅、退群后重新加群的账号上一轮没有检查过，同样从当前位置重新开始。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use ::utils::api_limit::limit_api_call;
+use kovi::{
+    MsgEvent,
+    chrono::Utc,
+    log::{error, info},
+    tokio::sync::Mutex,
+};
+
+use crate::{
+    BOT, CONFIG,
+    codeforces::api,
+    duel::{
+        problem::get_problems,
+        submission::{Submission, sync_submissions},
+    },
+    sql::{self, brag::CommitBragExt, utils::Commit},
+    subscription,
+    utils::group_member_ids,
+};
+
+pub const BRAG_TOPIC: &str = "cf_brag";
+
+/// 喜报单独限速，每隔这么久最多同步一个账号，给其他指令留出 CF API 的额度
+const SYNC_GAP: Duration = Duration::from_secs(10);
+
+/// 通过题数的里程碑
+const MILESTONES: &[usize] = &[
+    50, 100, 200, 300, 500, 750, 1000, 1500, 2000, 2500, 3000, 4000, 5000,
+];
+
+/// 每个群一次最多播报几条，很久没检查时避免刷屏
+const MAX_BRAGS: usize = 10;
+
+#[derive(Debug, PartialEq)]
+enum Brag {
+    /// 通过了比自己 rating 高出 `gap` 的题
+    HardSolve {
+        contest_id: i64,
+        index: String,
+        name: String,
+        rating: i64,
+        gap: i64,
+    },
+    /// 赛后补完了参加过的比赛的全部题目
+    ContestCleared { contest_id: i64, problems: usize },
+    /// 通过题数达到里程碑
+    Milestone(usize),
+}
+
+impl Brag {
+    fn describe(&self, handle: &str) -> String {
+        match self {
+            Brag::HardSolve {
+                contest_id,
+                index,
+                name,
+                rating,
+                gap,
+            } => format!(
+                "{} 通过了 {}{} {}（{}，比自己的 rating 高 {}）",
+                handle, contest_id, index, name, rating, gap
+            ),
+            Brag::ContestCleared {
+                contest_id,
+                problems,
+            } => format!(
+                "{} 补完了比赛 {} 的全部 {} 道题",
+                handle, contest_id, problems
+            ),
+            Brag::Milestone(count) => format!("{} 的通过题数达到了 {} 道", handle, count),
+        }
+    }
+}
+
+/// 上次检查时的做题情况
+#[derive(Debug)]
+struct History {
+    /// 通过的题目
+    solved: HashSet<(i64, String)>,
+    /// 赛时或者虚拟参加过的比赛
+    participated: HashSet<i64>,
+}
+
+/// 赛时或者虚拟参赛时的提交，这场比赛之后再通过的题就算补题
+///
+/// 和 [`sql::duel::submission::get_participated_until`] 中的条件一致
+fn took_part(submission: &Submission) -> bool {
+    matches!(
+        submission.author.participant_type.as_str(),
+        "CONTESTANT" | "OUT_OF_COMPETITION" | "VIRTUAL"
+    )
+}
+
+/// 找出 `new` 中值得播报的事
+///
+/// `history` 是上次检查时的做题情况，`new` 按提交 id 升序；`contest_sizes` 是题库中每场比赛的题数
+fn detect(
+    history: History,
+    new: &[Submission],
+    user_rating: Option<i64>,
+    min_gap: i64,
+    contest_sizes: &HashMap<i64, usize>,
+) -> Vec<Brag> {
+    let History {
+        mut solved,
+        mut participated,
+    } = history;
+    let before = solved.len();
+
+    let mut brags = Vec::new();
+    for submission in new {
+        let problem = &submission.problem;
+        if took_part(submission) {
+            participated.insert(problem.contest_id);
+        }
+        if !submission.is_accepted() || !solved.insert(problem.key()) {
+            continue;
+        }
+
+        if let (Some(rating), Some(user_rating)) = (problem.rating, user_rating)
+            && rating - user_rating >= min_gap
+        {
+            brags.push(Brag::HardSolve {
+                contest_id: problem.contest_id,
+                index: problem.index.clone(),
+                name: problem.name.clone(),
+                rating,
+                gap: rating - user_rating,
+            });
+        }
+
+        // 每次新通过一道题才检查，所以补完一场比赛只会播报一次
+        if submission.is_practice()
+            && participated.contains(&problem.contest_id)
+            && let Some(&size) = contest_sizes.get(&problem.contest_id)
+            && solved
+                .iter()
+                .filter(|(contest_id, _)| *contest_id == problem.contest_id)
+                .count()
+                == size
+        {
+            brags.push(Brag::ContestCleared {
+                contest_id: problem.contest_id,
+                problems: size,
+            });
+        }
+    }
+
+    if let Some(&milestone) = MILESTONES
+        .iter()
+        .rev()
+        .find(|&&milestone| before < milestone && solved.len() >= milestone)
+    {
+        brags.push(Brag::Milestone(milestone));
+    }
+    brags
+}
+
+/// 同步一个账号的提交并找出上次检查之后值得播报的事
+///
+/// `since` 之后没有检查过的账号不在上一轮里，不补播之前的提交
+async fn check_handle(
+    handle: &str,
+    since: i64,
+    user_rating: Option<i64>,
+    min_gap: i64,
+    contest_sizes: &HashMap<i64, usize>,
+) -> anyhow::Result<Vec<Brag>> {
+    let now = Utc::now().timestamp();
+    let cursor = sql::brag::get_brag_cursor(handle, since).await?;
+    limit_api_call("codeforces_brag", SYNC_GAP, 1, sync_submissions(handle)).await?;
+
+    let (last_id, brags) = match cursor {
+        Some(cursor) => {
+            let new = sql::duel::submission::get_submissions_after(handle, cursor).await?;
+            // 还在评测的提交和之后的提交都留到下次再检查
+            let judged = new
+                .iter()
+                .position(|s| matches!(s.verdict.as_deref(), None | Some("TESTING")))
+                .unwrap_or(new.len());
+            let new = &new[..judged];
+            match new.last() {
+                Some(last) => {
+                    let history = History {
+                        solved: sql::duel::submission::get_solved_set_until(handle, cursor).await?,
+                        participated: sql::duel::submission::get_participated_until(handle, cursor)
+                            .await?,
+                    };
+                    let brags = detect(history, new, user_rating, min_gap, contest_sizes);
+                    (Some(last.id), brags)
+                }
+                None => (Some(cursor), Vec::new()),
+            }
+        }
+        // 第一次检查或者上一轮没有检查时只记录位置，同步点之前的提交都已经评测完
+        None => (
+            sql::duel::submission::get_sync_point(handle).await?,
+            Vec::new(),
+        ),
+    };
+
+    if let Some(last_id) = last_id {
+        Commit::start()
+            .await?
+            .set_brag_cursor(handle, last_id, now)
+            .await?
+            .commit()
+            .await?;
+    }
+    Ok(brags)
+}
+
+/// 检查订阅群中绑定用户的新提交，有值得播报的就发到群里
+pub async fn check_feed() {
+    // 成员多时一轮检查可能比定时间隔还长，上一轮没结束就跳过
+    static RUNNING: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    let Ok(_running) = RUNNING.try_lock() else {
+        return;
+    };
+
+    // 上一轮开始之后检查过的账号才接着播报，一轮耗时多久都不影响
+    let round_started_at = Utc::now().timestamp();
+    let since = match sql::brag::get_brag_round_started_at().await {
+        Ok(since) => since.unwrap_or(i64::MIN),
+        Err(e) => {
+            error!("获取做题喜报的检查进度失败: {}", e);
+            return;
+        }
+    };
+
+    let groups = match sql::subscription::get_subscribed_groups(BRAG_TOPIC).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("获取做题喜报订阅失败: {}", e);
+            return;
+        }
+    };
+
+    if groups.is_empty() {
+        return;
+    }
+
+    let users = match sql::duel::user::get_bound_users().await {
+        Ok(users) => users,
+        Err(e) => {
+            error!("获取绑定用户失败: {}", e);
+            return;
+        }
+    };
+
+    // 只检查在订阅群里的账号，同一个账号只同步一次
+    let mut handles: HashMap<String, (String, HashSet<i64>)> = HashMap::new();
+    for &group_id in groups.iter() {
+        let members = match group_member_ids(group_id).await {
+            Ok(members) => members,
+            Err(e) => {
+                error!("获取群 {} 成员失败: {}", group_id, e);
+                continue;
+            }
+        };
+        for user in users.iter().filter(|user| members.contains(&user.qq)) {
+            if let Some(cf_id) = &user.cf_id {
+                handles
+                    .entry(cf_id.to_lowercase())
+                    .or_insert_with(|| (cf_id.clone(), HashSet::new()))
+                    .1
+                    .insert(group_id);
+            }
+        }
+    }
+    if handles.is_empty() {
+        return;
+    }
+
+    // 有人改名时整批查询会失败，这时这批人只是不播报难题
+    let mut ratings = HashMap::new();
+    let all = handles
+        .values()
+        .map(|(handle, _)| handle.as_str())
+        .collect::<Vec<_>>();
+    for chunk in all.chunks(100) {
+        match api::user_info(chunk).await {
+            Ok(infos) => ratings.extend(
+                infos
+                    .iter()
+                    .filter_map(|info| Some((info.handle.to_lowercase(), info.rating?))),
+            ),
+            Err(e) => error!("获取用户 rating 失败: {}", e),
+        }
+    }
+
+    let mut contest_sizes: HashMap<i64, usize> = HashMap::new();
+    match get_problems().await {
+        Ok(problems) => {
+            for problem in problems.iter() {
+                *contest_sizes.entry(problem.contest_id).or_default() += 1;
+            }
+        }
+        Err(e) => error!("获取题库失败: {}", e),
+    }
+
+    let min_gap = CONFIG.get().unwrap().brag_rating_gap;
+    let mut lines: HashMap<i64, Vec<String>> = HashMap::new();
+    for (key, (handle, groups)) in handles.iter() {
+        let rating = ratings.get(key).copied();
+        let brags = match check_handle(handle, since, rating, min_gap, &contest_sizes).await {
+            Ok(brags) => brags,
+            Err(e) => {
+                error!("检查 {} 的提交失败: {}", handle, e);
+                // 这一轮检查过，下一轮接着从原来的位置播报
+                touch_cursor(handle, since).await;
+                continue;
+            }
+        };
+        for brag in brags {
+            let line = brag.describe(handle);
+            for &group_id in groups {
+                lines.entry(group_id).or_default().push(line.clone());
+            }
+        }
+    }
+
+    let result = async {
+        Commit::start()
+            .await?
+            .set_brag_round_started_at(round_started_at)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        error!("记录做题喜报的检查进度失败: {}", e);
+    }
+
+    let bot = BOT.get().unwrap();
+
+    for (group_id, lines) in lines {
+        let mut msg = format!(
+            "做题喜报：\n{}",
+            lines[..lines.len().min(MAX_BRAGS)].join("\n")
+        );
+        if lines.len() > MAX_BRAGS {
+            msg.push_str(&format!("\n...等 {} 条", lines.len()));
+        }
+        info!("send brag feed to group: {}", group_id);
+        bot.send_group_msg(group_id, msg);
+    }
+}
+
+async fn touch_cursor(handle: &str, since: i64) {
+    let result = async {
+        Commit::start()
+            .await?
+            .touch_brag_cursor(handle, since, Utc::now().timestamp())
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        error!("更新 {} 的喜报检查时间失败: {}", handle, e);
+    }
+}
+
+/// 订阅或取消订阅做题喜报
+pub async fn subscribe(event: &MsgEvent, subscribe: bool) {
+    subscription::toggle(event, BRAG_TOPIC, subscribe, "做题喜报").await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duel::submission::SubmissionBuilder;
+
+    fn submission(id: i64, problem: (i64, &str, i64), verdict: &str, kind: &str) -> Submission {
+        let (contest_id, index, rating) = problem;
+        SubmissionBuilder::new(id)
+            .problem(contest_id, index)
+            .rating(rating)
+            .verdict(verdict)
+            .participant_type(kind)
+            .build()
+    }
+
+    fn history(old: &[Submission]) -> History {
+        History {
+            solved: old
+                .iter()
+                .filter(|s| s.is_accepted())
+                .map(|s| s.problem.key())
+                .collect(),
+            participated: old
+                .iter()
+                .filter(|s| took_part(s))
+                .map(|s| s.problem.contest_id)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let sizes = HashMap::from([(1, 2), (2, 2)]);
+        let old = vec![
+            submission(1, (1, "A", 800), "OK", "CONTESTANT"),
+            submission(2, (1, "B", 1600), "WRONG_ANSWER", "CONTESTANT"),
+            submission(3, (2, "A", 800), "OK", "PRACTICE"),
+        ];
+        let new = vec![
+            submission(4, (2, "B", 1900), "WRONG_ANSWER", "PRACTICE"),
+            // 补完了参加过的比赛 1
+            submission(5, (1, "B", 1600), "OK", "PRACTICE"),
+            // 比 rating 高 400，但比赛 2 没有参加过
+            submission(6, (2, "B", 1900), "OK", "PRACTICE"),
+            // 重复通过不算
+            submission(7, (2, "B", 1900), "OK", "PRACTICE"),
+        ];
+
+        assert_eq!(
+            detect(history(&old), &new, Some(1500), 300, &sizes),
+            vec![
+                Brag::ContestCleared {
+                    contest_id: 1,
+                    problems: 2
+                },
+                Brag::HardSolve {
+                    contest_id: 2,
+                    index: "B".to_string(),
+                    name: String::new(),
+                    rating: 1900,
+                    gap: 400
+                },
+            ]
+        );
+
+        // 没有 rating 时不播报难题
+        assert!(detect(history(&old[..2]), &old[2..], None, 300, &sizes).is_empty());
+    }
+
+    #[test]
+    fn test_milestone() {
+        let solves = (0..101)
+            .map(|i| submission(i, (i, "A", 800), "OK", "PRACTICE"))
+            .collect::<Vec<_>>();
+        let sizes = HashMap::new();
+
+        // 从 49 题一次过到 101 题，只播报最高的里程碑
+        assert_eq!(
+            detect(
+                history(&solves[..49]),
+                &solves[49..],
+                Some(1500),
+                300,
+                &sizes
+            ),
+            vec![Brag::Milestone(100)]
+        );
+        assert!(
+            detect(
+                history(&solves[..100]),
+                &solves[100..],
+                Some(1500),
+                300,
+                &sizes
+            )
+            .is_empty()
+        );
+    }
+}
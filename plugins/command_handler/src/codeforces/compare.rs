@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use kovi::{Message, MsgEvent};
+
+use super::{get_cf_id, render};
+use crate::{
+    codeforces::api,
+    duel::{problem::Problem, submission::get_recent_submissions},
+    utils::user_id_or_text,
+};
+
+/// 一次最多比较的人数
+const MAX_COMPARE_USERS: usize = 4;
+/// 标签图中展示的标签数
+const TOP_TAGS: usize = 12;
+/// 展示的共同比赛数
+const COMMON_CONTESTS: usize = 10;
+/// 每人展示的独有题目数
+const UNIQUE_PROBLEMS: usize = 5;
+
+const MIN_BUCKET: i64 = 800;
+const MAX_BUCKET: i64 = 3500;
+
+#[derive(serde::Serialize)]
+struct UserSummary {
+    handle: String,
+    rating: Option<i64>,
+    max_rating: Option<i64>,
+    solved: usize,
+    /// 只有这个人通过的题目数
+    unique: usize,
+    /// 只有这个人通过的题目中最难的几道
+    unique_hardest: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct CommonContest {
+    name: String,
+    /// 与 users 顺序一致
+    ranks: Vec<i64>,
+}
+
+#[derive(serde::Serialize)]
+struct Comparison {
+    users: Vec<UserSummary>,
+    /// rating 段 -> 每人通过的题目数
+    buckets: Vec<(i64, Vec<usize>)>,
+    /// 标签 -> 每人通过的题目数
+    tags: Vec<(String, Vec<usize>)>,
+    contests: Vec<CommonContest>,
+}
+
+/// 对比多个用户的 CF 数据，生成一张图片
+///
+/// 用法：/cf compare <@用户|handle> <@用户|handle> ...
+pub async fn compare(event: &MsgEvent, args: &[String]) {
+    let args = args.get(2..).unwrap_or_default();
+    if args.len() < 2 || args.len() > MAX_COMPARE_USERS {
+        event.reply(format!(
+            "参数非法：需要 2 到 {} 个用户（@ 或 CF 账号）",
+            MAX_COMPARE_USERS
+        ));
+        return;
+    }
+
+    let mut handles = Vec::new();
+    for arg in args {
+        let user = match user_id_or_text(arg) {
+            Ok(user) => user,
+            Err(_) => {
+                event.reply("参数非法");
+                return;
+            }
+        };
+        match get_cf_id(&user).await {
+            Ok(cf_id) => handles.push(cf_id),
+            Err(e) => {
+                event.reply(format!("{}: {}", arg, e));
+                return;
+            }
+        }
+    }
+
+    event.reply("正在对比用户数据");
+
+    let comparison = match build_comparison(&handles).await {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            event.reply(format!("查询失败: {}", e));
+            return;
+        }
+    };
+
+    match render("compare.py", &comparison).await {
+        Ok(image) => event.reply(Message::new().add_image(&image)),
+        Err(e) => event.reply(format!("生成图片失败: {}", e)),
+    }
+}
+
+async fn build_comparison(handles: &[String]) -> anyhow::Result<Comparison> {
+    let handle_refs = handles.iter().map(String::as_str).collect::<Vec<_>>();
+    let infos = api::user_info(&handle_refs).await?;
+
+    // 每个人通过的题目
+    let mut solved = Vec::new();
+    for handle in handles {
+        let submissions = get_recent_submissions(handle).await?;
+        let problems = submissions
+            .into_iter()
+            .filter(|submission| submission.is_accepted())
+            .map(|submission| (submission.problem.key(), submission.problem))
+            .collect::<HashMap<_, _>>();
+        solved.push(problems);
+    }
+
+    let buckets = (MIN_BUCKET..=MAX_BUCKET)
+        .step_by(100)
+        .map(|bucket| {
+            let counts = solved
+                .iter()
+                .map(|problems| {
+                    problems
+                        .values()
+                        .filter(|problem| problem.rating.is_some_and(|r| r / 100 * 100 == bucket))
+                        .count()
+                })
+                .collect();
+            (bucket, counts)
+        })
+        .collect();
+
+    let mut tag_counts: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, problems) in solved.iter().enumerate() {
+        for tag in problems.values().flat_map(|problem| problem.tags.iter()) {
+            tag_counts
+                .entry(tag)
+                .or_insert_with(|| vec![0; handles.len()])[i] += 1;
+        }
+    }
+    let mut tags = tag_counts
+        .into_iter()
+        .map(|(tag, counts)| (tag.to_string(), counts))
+        .collect::<Vec<_>>();
+    tags.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.iter().sum::<usize>()));
+    tags.truncate(TOP_TAGS);
+
+    let users = handles
+        .iter()
+        .enumerate()
+        .map(|(i, handle)| {
+            let others = solved
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .flat_map(|(_, problems)| problems.keys())
+                .collect::<HashSet<_>>();
+            let mut unique = solved[i]
+                .iter()
+                .filter(|(key, _)| !others.contains(key))
+                .map(|(_, problem)| problem)
+                .collect::<Vec<_>>();
+            unique.sort_by_key(|problem| std::cmp::Reverse(problem.rating));
+
+            let info = infos.get(i);
+            UserSummary {
+                handle: info.map_or_else(|| handle.clone(), |info| info.handle.clone()),
+                rating: info.and_then(|info| info.rating),
+                max_rating: info.and_then(|info| info.max_rating),
+                solved: solved[i].len(),
+                unique: unique.len(),
+                unique_hardest: unique
+                    .iter()
+                    .take(UNIQUE_PROBLEMS)
+                    .map(|problem| problem_label(problem))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(Comparison {
+        users,
+        buckets,
+        tags,
+        contests: common_contests(handles).await?,
+    })
+}
+
+/// 所有人都参加过的 rated 比赛，最近的在前
+async fn common_contests(handles: &[String]) -> anyhow::Result<Vec<CommonContest>> {
+    let mut histories = Vec::new();
+    for handle in handles {
+        let history = api::user_rating(handle).await?;
+        histories.push(
+            history
+                .iter()
+                .map(|change| (change.contest_id, change.clone()))
+                .collect::<HashMap<_, _>>(),
+        );
+    }
+
+    let mut contests = histories[0]
+        .values()
+        .filter(|change| {
+            histories
+                .iter()
+                .all(|history| history.contains_key(&change.contest_id))
+        })
+        .map(|change| {
+            let ranks = histories
+                .iter()
+                .map(|history| history[&change.contest_id].rank)
+                .collect();
+            (
+                change.rating_update_time_seconds,
+                CommonContest {
+                    name: change.contest_name.clone(),
+                    ranks,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    contests.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+    Ok(contests
+        .into_iter()
+        .take(COMMON_CONTESTS)
+        .map(|(_, contest)| contest)
+        .collect())
+}
+
+fn problem_label(problem: &Problem) -> String {
+    match problem.rating {
+        Some(rating) => format!("{}{} ({})", problem.contest_id, problem.index, rating),
+        None => format!("{}{}", problem.contest_id, problem.index),
+    }
+}
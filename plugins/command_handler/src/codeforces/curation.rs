@@ -0,0 +1,252 @@
+use kovi::MsgEvent;
+
+use crate::{
+    codeforces::api,
+    duel::{
+        curation::{get_blacklist, invalidate_blacklist, parse_rule},
+        problem::parse_problem_id,
+    },
+    sql::{self, duel::curation::CommitCurationExt, utils::Commit},
+    utils::is_bot_admin,
+};
+
+/// 添加黑名单规则（仅 bot 管理员）
+///
+/// 用法：/cf blacklist add <筛选表达式> [# 原因]
+pub async fn blacklist_add(event: &MsgEvent, args: &[String]) {
+    if !is_bot_admin(event.user_id) {
+        event.reply("只有管理员可以修改题目黑名单");
+        return;
+    }
+
+    let args = args.get(3..).unwrap_or_default();
+    let (rule, reason) = match args.iter().position(|arg| arg == "#") {
+        Some(pos) => (&args[..pos], args[pos + 1..].join(" ")),
+        None => (args, String::new()),
+    };
+
+    if let Err(e) = parse_rule(rule) {
+        event.reply(format!("规则非法: {}", e));
+        return;
+    }
+
+    let rule = rule.join(" ");
+    let res = async {
+        Commit::start()
+            .await?
+            .add_blacklist(&rule, &reason)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(_) => {
+            invalidate_blacklist().await;
+            event.reply(format!("已添加黑名单规则：{}", rule));
+        }
+        Err(e) => event.reply(format!("添加黑名单规则失败: {}", e)),
+    }
+}
+
+/// 按编号删除黑名单规则（仅 bot 管理员）
+pub async fn blacklist_remove(event: &MsgEvent, args: &[String]) {
+    if !is_bot_admin(event.user_id) {
+        event.reply("只有管理员可以修改题目黑名单");
+        return;
+    }
+
+    let Some(id) = args.get(3).and_then(|arg| arg.parse::<i64>().ok()) else {
+        event.reply("参数非法：需要规则编号，可以用 /cf blacklist list 查看");
+        return;
+    };
+
+    let res = async {
+        Commit::start()
+            .await?
+            .remove_blacklist(id)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(_) => {
+            invalidate_blacklist().await;
+            event.reply(format!("已删除黑名单规则 {}", id));
+        }
+        Err(e) => event.reply(format!("删除黑名单规则失败: {}", e)),
+    }
+}
+
+pub async fn blacklist_list(event: &MsgEvent) {
+    let blacklist = match get_blacklist().await {
+        Ok(blacklist) => blacklist,
+        Err(e) => {
+            event.reply(format!("获取题目黑名单失败: {}", e));
+            return;
+        }
+    };
+
+    if blacklist.rules.is_empty() {
+        event.reply("题目黑名单为空");
+        return;
+    }
+
+    let msg = blacklist
+        .rules
+        .iter()
+        .fold("题目黑名单：\n".to_string(), |mut acc, rule| {
+            if rule.reason.is_empty() {
+                acc.push_str(&format!("{}. {}\n", rule.id, rule.rule));
+            } else {
+                acc.push_str(&format!("{}. {}（{}）\n", rule.id, rule.rule, rule.reason));
+            }
+            acc
+        });
+
+    event.reply(msg.trim_end());
+}
+
+/// 向题单中添加或移除题目（仅 bot 管理员）
+///
+/// 用法：/cf pool add|remove <题单名> <题号>...
+/// 移除时不带题号则删除整个题单
+pub async fn pool_edit(event: &MsgEvent, args: &[String], add: bool) {
+    if !is_bot_admin(event.user_id) {
+        event.reply("只有管理员可以修改题单");
+        return;
+    }
+
+    // 筛选条件中的题单名不区分大小写
+    let Some(name) = args.get(3).map(|name| name.to_lowercase()) else {
+        event.reply("参数非法：需要题单名");
+        return;
+    };
+
+    let mut problems = Vec::new();
+    for arg in args.get(4..).unwrap_or_default() {
+        match parse_problem_id(arg) {
+            Some(id) => problems.push(id),
+            None => {
+                event.reply(format!("参数非法：{} 不是一个题号，例如 1900D", arg));
+                return;
+            }
+        }
+    }
+
+    if add && problems.is_empty() {
+        event.reply("参数非法：需要至少一个题号");
+        return;
+    }
+
+    let res = async {
+        let mut commit = Commit::start().await?;
+        if add {
+            commit.add_to_pool(&name, &problems).await?;
+        } else if problems.is_empty() {
+            commit.remove_pool(&name).await?;
+        } else {
+            commit.remove_from_pool(&name, &problems).await?;
+        }
+        commit.commit().await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(_) if add => event.reply(format!("已向题单 {} 添加 {} 道题目", name, problems.len())),
+        Ok(_) if problems.is_empty() => event.reply(format!("已删除题单 {}", name)),
+        Ok(_) => event.reply(format!("已从题单 {} 移除 {} 道题目", name, problems.len())),
+        Err(e) => event.reply(format!("修改题单失败: {}", e)),
+    }
+}
+
+/// 把一场比赛（包括私有的 gym 和 mashup）的全部题目加入题单（仅 bot 管理员）
+///
+/// 用法：/cf pool import <题单名> <比赛 id>
+pub async fn pool_import(event: &MsgEvent, args: &[String]) {
+    if !is_bot_admin(event.user_id) {
+        event.reply("只有管理员可以修改题单");
+        return;
+    }
+
+    let (Some(name), Some(contest_id)) = (
+        args.get(3).map(|name| name.to_lowercase()),
+        args.get(4).and_then(|arg| arg.parse::<i64>().ok()),
+    ) else {
+        event.reply("参数非法：/cf pool import <题单名> <比赛 id>");
+        return;
+    };
+
+    let standings = match api::contest_standings(contest_id, &[], false, Some(1)).await {
+        Ok(standings) => standings,
+        Err(e) => {
+            event.reply(format!("获取比赛题目失败: {}", e));
+            return;
+        }
+    };
+
+    let problems = standings
+        .problems
+        .iter()
+        .map(|problem| (contest_id, problem.index.clone()))
+        .collect::<Vec<_>>();
+
+    let res = async {
+        Commit::start()
+            .await?
+            .add_to_pool(&name, &problems)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(_) => event.reply(format!(
+            "已将 {} 的 {} 道题目加入题单 {}",
+            standings.contest.name,
+            problems.len(),
+            name
+        )),
+        Err(e) => event.reply(format!("修改题单失败: {}", e)),
+    }
+}
+
+/// 列出所有题单，或者某个题单中的题目
+pub async fn pool_list(event: &MsgEvent, args: &[String]) {
+    let msg = match args.get(3).map(|name| name.to_lowercase()) {
+        Some(name) => match sql::duel::curation::get_pool(&name).await {
+            Ok(pool) if pool.is_empty() => format!("题单 {} 不存在", name),
+            Ok(pool) => {
+                let mut problems = pool
+                    .into_iter()
+                    .map(|(contest_id, index)| format!("{}{}", contest_id, index))
+                    .collect::<Vec<_>>();
+                problems.sort();
+                format!("题单 {}：\n{}", name, problems.join(" "))
+            }
+            Err(e) => format!("获取题单失败: {}", e),
+        },
+        None => match sql::duel::curation::get_pools().await {
+            Ok(pools) if pools.is_empty() => "还没有题单".to_string(),
+            Ok(pools) => pools
+                .into_iter()
+                .fold("题单列表：\n".to_string(), |mut acc, (name, count)| {
+                    acc.push_str(&format!("{}: {} 道题\n", name, count));
+                    acc
+                })
+                .trim_end()
+                .to_string(),
+            Err(e) => format!("获取题单失败: {}", e),
+        },
+    };
+
+    event.reply(msg);
+}
@@ -0,0 +1,383 @@
+//! 全群的薄弱标签分析
+//!
+//! 汇总本群所有绑定成员最近的提交，按标签统计通过题数和通过率，
+//! 并和题库中群平均水平附近的题目的标签分布比较，找出大家练得少的标签。
+
+use std::collections::{HashMap, HashSet};
+
+use kovi::{MsgEvent, chrono::Utc};
+use rand::seq::IndexedRandom;
+
+use super::{api, bound_handles};
+use crate::duel::{
+    curation::get_blacklist,
+    problem::{Problem, get_problems, problem_url},
+    submission::{Submission, get_recent_submissions},
+};
+
+/// 只统计最近这么多天的提交
+const ANALYZE_DAYS: i64 = 180;
+/// 和群水平比较时使用的题库难度范围（群 rating 上下这么多）
+const LEVEL_BAND: i64 = 200;
+/// 在题库的标签中占比低于这个值的标签不参与比较
+const MIN_TAG_SHARE: f64 = 0.01;
+/// 列出的薄弱标签数
+const WEAK_TAGS: usize = 8;
+/// 建议的题目数，每个薄弱标签一道
+const SUGGEST_COUNT: usize = 5;
+
+#[derive(Debug, Default, PartialEq)]
+struct TagStats {
+    /// 通过的题目数，同一道题多人通过算多次
+    solved: usize,
+    /// 提交过的题目数
+    attempted: usize,
+    /// 通过过这个标签的题目的人数
+    members: usize,
+}
+
+impl TagStats {
+    fn success_rate(&self) -> f64 {
+        self.solved as f64 / self.attempted.max(1) as f64
+    }
+}
+
+/// 按标签汇总每个人 `since` 之后的提交，每人每道题只算一次
+fn aggregate(members: &[Vec<Submission>], since: i64) -> HashMap<String, TagStats> {
+    let mut stats: HashMap<String, TagStats> = HashMap::new();
+    for submissions in members {
+        let mut problems: HashMap<(i64, String), (&Problem, bool)> = HashMap::new();
+        for submission in submissions
+            .iter()
+            .filter(|s| s.creation_time_seconds >= since)
+        {
+            let entry = problems
+                .entry(submission.problem.key())
+                .or_insert((&submission.problem, false));
+            entry.1 |= submission.is_accepted();
+        }
+
+        let mut solved_tags = HashSet::new();
+        for (problem, solved) in problems.into_values() {
+            for tag in problem.tags.iter().filter(|tag| !tag.starts_with('*')) {
+                let tag_stats = stats.entry(tag.clone()).or_default();
+                tag_stats.attempted += 1;
+                if solved {
+                    tag_stats.solved += 1;
+                    solved_tags.insert(tag);
+                }
+            }
+        }
+        for tag in solved_tags {
+            stats.entry(tag.clone()).or_default().members += 1;
+        }
+    }
+    stats
+}
+
+/// 题库中难度在 `[lo, hi]` 的题目里各标签的占比，按标签出现的次数计算，和 [`TagStats::solved`] 一致
+fn tag_shares(problems: &[std::sync::Arc<Problem>], lo: i64, hi: i64) -> HashMap<String, f64> {
+    let in_band = problems
+        .iter()
+        .filter(|p| p.rating.is_some_and(|rating| (lo..=hi).contains(&rating)))
+        .filter(|p| !p.tags.iter().any(|tag| tag == "*special"))
+        .collect::<Vec<_>>();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for problem in in_band.iter() {
+        for tag in problem.tags.iter().filter(|tag| !tag.starts_with('*')) {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+    }
+    let total = counts.values().sum::<usize>().max(1);
+    counts
+        .into_iter()
+        .map(|(tag, count)| (tag, count as f64 / total as f64))
+        .collect()
+}
+
+/// 一个标签的练习程度：本群通过的题目的标签中这个标签的占比除以题库中的占比，1 表示和题库一致
+///
+/// `total_solved` 为所有标签的 [`TagStats::solved`] 之和
+fn practice_index(stats: Option<&TagStats>, total_solved: usize, expected_share: f64) -> f64 {
+    let solved = stats.map_or(0, |stats| stats.solved);
+    solved as f64 / total_solved.max(1) as f64 / expected_share
+}
+
+fn median(values: &mut [i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+/// 分析本群的薄弱标签
+///
+/// 用法：/cf group analyze
+pub async fn analyze(event: &MsgEvent) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let handles = match bound_handles(Some(group_id)).await {
+        Ok(handles) => handles,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    event.reply(format!("正在统计 {} 名绑定成员的做题记录", handles.len()));
+
+    // 有人改名时整批查询会失败，这时没有 rating 的人不计入群水平
+    let mut ratings = Vec::new();
+    for chunk in handles.chunks(100) {
+        let chunk = chunk.iter().map(String::as_str).collect::<Vec<_>>();
+        if let Ok(users) = api::user_info(&chunk).await {
+            ratings.extend(users.iter().filter_map(|user| user.rating));
+        }
+    }
+    let level = median(&mut ratings).unwrap_or(1200);
+
+    let mut members = Vec::new();
+    let mut failed = Vec::new();
+    for handle in handles.iter() {
+        match get_recent_submissions(handle).await {
+            Ok(submissions) => members.push(submissions),
+            Err(_) => failed.push(handle.as_str()),
+        }
+    }
+    if members.is_empty() {
+        event.reply("获取做题记录失败");
+        return;
+    }
+
+    let (problems, blacklist) = match (get_problems().await, get_blacklist().await) {
+        (Ok(problems), Ok(blacklist)) => (problems, blacklist),
+        (Err(e), _) | (_, Err(e)) => {
+            event.reply(format!("获取题库失败: {}", e));
+            return;
+        }
+    };
+
+    let since = Utc::now().timestamp() - ANALYZE_DAYS * 24 * 60 * 60;
+    let stats = aggregate(&members, since);
+    let total_solved = stats.values().map(|stats| stats.solved).sum::<usize>();
+    let solves = members
+        .iter()
+        .map(|submissions| {
+            submissions
+                .iter()
+                .filter(|s| s.creation_time_seconds >= since && s.is_accepted())
+                .map(|s| s.problem.key())
+                .collect::<HashSet<_>>()
+                .len()
+        })
+        .sum::<usize>();
+    let shares = tag_shares(&problems, level - LEVEL_BAND, level + LEVEL_BAND);
+
+    // 按练习程度从低到高排列题库中常见的标签
+    let mut tags = shares
+        .iter()
+        .filter(|(tag, share)| **share >= MIN_TAG_SHARE && !tag.starts_with('*'))
+        .map(|(tag, share)| {
+            let index = practice_index(stats.get(tag), total_solved, *share);
+            (tag.as_str(), *share, index)
+        })
+        .collect::<Vec<_>>();
+    tags.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(b.0)));
+
+    let mut msg = format!(
+        "本群 {} 名成员最近 {} 天的做题情况，群 rating 中位数 {}，共通过 {} 题（多人通过的题算多次）",
+        members.len(),
+        ANALYZE_DAYS,
+        level,
+        solves
+    );
+    if !failed.is_empty() {
+        msg.push_str(&format!("（{} 的记录获取失败）", failed.join(", ")));
+    }
+
+    let untouched = tags
+        .iter()
+        .filter(|(tag, _, _)| stats.get(*tag).is_none_or(|stats| stats.solved == 0))
+        .map(|(tag, _, _)| *tag)
+        .collect::<Vec<_>>();
+    if !untouched.is_empty() {
+        msg.push_str(&format!("\n\n没有人练习的标签：{}", untouched.join(", ")));
+    }
+
+    msg.push_str(&format!(
+        "\n\n练得最少的标签（和 {}-{} 的题库比较）：",
+        level - LEVEL_BAND,
+        level + LEVEL_BAND
+    ));
+    for (tag, share, index) in tags.iter().take(WEAK_TAGS) {
+        let tag_stats = stats.get(*tag);
+        msg.push_str(&format!(
+            "\n{}：通过 {} 题，{} 人通过，通过率 {:.0}%，题库标签占比 {:.0}%，练习程度 {:.2}",
+            tag,
+            tag_stats.map_or(0, |s| s.solved),
+            tag_stats.map_or(0, |s| s.members),
+            tag_stats.map_or(0.0, |s| s.success_rate()) * 100.0,
+            share * 100.0,
+            index
+        ));
+    }
+
+    let strong = tags
+        .iter()
+        .rev()
+        .take(3)
+        .map(|(tag, _, index)| format!("{} ({:.2})", tag, index))
+        .collect::<Vec<_>>();
+    msg.push_str(&format!("\n练得最多的标签：{}", strong.join(", ")));
+
+    // 从薄弱标签中各挑一道群里没人通过过、难度稍高于群水平的题
+    let solved = members
+        .iter()
+        .flatten()
+        .filter(|s| s.is_accepted())
+        .map(|s| s.problem.key())
+        .collect::<HashSet<_>>();
+    let mut rng = rand::rng();
+    let mut suggested: Vec<(&str, &std::sync::Arc<Problem>)> = Vec::new();
+    for (tag, _, _) in tags.iter() {
+        if suggested.len() >= SUGGEST_COUNT {
+            break;
+        }
+        let candidates = problems
+            .iter()
+            .filter(|p| {
+                p.rating
+                    .is_some_and(|rating| (level..=level + LEVEL_BAND).contains(&rating))
+            })
+            .filter(|p| p.tags.iter().any(|t| t == tag))
+            .filter(|p| !p.tags.iter().any(|t| t == "*special"))
+            .filter(|p| !solved.contains(&p.key()) && !blacklist.contains(p))
+            .filter(|p| !suggested.iter().any(|(_, s)| s.same_problem(p)))
+            .collect::<Vec<_>>();
+        if let Some(problem) = candidates.choose(&mut rng) {
+            suggested.push((tag, problem));
+        }
+    }
+    if !suggested.is_empty() {
+        msg.push_str("\n\n建议的群训练题：");
+        for (i, (tag, problem)) in suggested.iter().enumerate() {
+            msg.push_str(&format!(
+                "\n{}. {}{} ({}, {}) {}",
+                i + 1,
+                problem.contest_id,
+                problem.index,
+                problem.rating_text(),
+                tag,
+                problem_url(problem.contest_id, &problem.index)
+            ));
+        }
+    }
+
+    event.reply(msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duel::submission::SubmissionBuilder;
+
+    fn submission(contest_id: i64, time: i64, tags: &[&str], verdict: &str) -> Submission {
+        SubmissionBuilder::new(time)
+            .problem(contest_id, "A")
+            .tags(tags)
+            .verdict(verdict)
+            .build()
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let alice = vec![
+            submission(1, 100, &["dp"], "WRONG_ANSWER"),
+            submission(1, 101, &["dp"], "OK"),
+            submission(2, 102, &["dp", "graphs"], "WRONG_ANSWER"),
+            // 太早的提交不计入
+            submission(3, 10, &["math"], "OK"),
+        ];
+        let bob = vec![
+            submission(1, 200, &["dp"], "OK"),
+            submission(4, 201, &["*special"], "OK"),
+        ];
+
+        let stats = aggregate(&[alice, bob], 50);
+        assert_eq!(
+            stats["dp"],
+            TagStats {
+                solved: 2,
+                attempted: 3,
+                members: 2
+            }
+        );
+        assert_eq!(
+            stats["graphs"],
+            TagStats {
+                solved: 0,
+                attempted: 1,
+                members: 0
+            }
+        );
+        assert!(!stats.contains_key("math"));
+        assert!(!stats.contains_key("*special"));
+    }
+
+    #[test]
+    fn test_practice_index() {
+        let stats = TagStats {
+            solved: 10,
+            attempted: 20,
+            members: 3,
+        };
+        // 题库中占 20%，群里只占 10%
+        assert!((practice_index(Some(&stats), 100, 0.2) - 0.5).abs() < 1e-9);
+        assert_eq!(practice_index(None, 100, 0.2), 0.0);
+
+        // 做的题和题库的标签分布一致时每个标签的练习程度都是 1
+        let problems = [
+            (1, vec!["dp", "greedy"]),
+            (2, vec!["dp"]),
+            (3, vec!["math", "*special"]),
+            (4, vec!["math"]),
+        ]
+        .map(|(contest_id, tags)| {
+            std::sync::Arc::new(Problem::new(
+                contest_id,
+                "A".to_string(),
+                Some(1500),
+                tags.iter().map(|tag| tag.to_string()).collect(),
+            ))
+        });
+        let shares = tag_shares(&problems[..], 1400, 1600);
+        // *special 的题目不计入
+        assert!((shares["dp"] - 0.5).abs() < 1e-9);
+        assert!(!shares.contains_key("*special"));
+        assert!((shares.values().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let alice = vec![
+            submission(1, 100, &["dp", "greedy"], "OK"),
+            submission(2, 100, &["dp"], "OK"),
+            submission(4, 100, &["math"], "OK"),
+        ];
+        let bob = vec![submission(4, 100, &["math"], "WRONG_ANSWER")];
+        let group_stats = aggregate(&[alice, bob], 0);
+        let total_solved = group_stats
+            .values()
+            .map(|stats| stats.solved)
+            .sum::<usize>();
+        for tag in ["dp", "greedy", "math"] {
+            let index = practice_index(group_stats.get(tag), total_solved, shares[tag]);
+            assert!((index - 1.0).abs() < 1e-9, "{}: {}", tag, index);
+        }
+        assert!((stats.success_rate() - 0.5).abs() < 1e-9);
+        assert_eq!(median(&mut [1900, 1200, 1500]), Some(1500));
+        assert_eq!(median(&mut []), None);
+    }
+}
@@ -0,0 +1,651 @@
+//! 群赛：管理员从 CF 题库中选几道题，在指定时间段内按 ICPC 赛制排名
+//!
+//! 参赛者直接在 CF 上提交，比赛时间内对这些题目的提交都计入成绩，
+//! 随时可以查看当前榜单，结束后自动公布最终榜单。
+//! 按条件选题时在比赛开始时才选题，排除参赛者已经通过的题目。
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use kovi::{
+    Message, MsgEvent,
+    chrono::{DateTime, Duration, Utc},
+    log::{error, info},
+};
+use rand::seq::IndexedRandom;
+
+use super::{
+    render,
+    standings::{Row, Table, result_cell},
+    virtual_contest::{Rules, VirtualStatus, format_local, next_time, score, user_cf_id},
+};
+use crate::{
+    BOT,
+    codeforces::api::ContestProblem,
+    duel::{
+        filter::ProblemFilter,
+        problem::{get_problems, get_problems_by, parse_problem_id, problem_url},
+        submission::{Submission, get_recent_submissions, get_solved_set},
+    },
+    sql::{self, mashup::CommitMashupExt, utils::Commit},
+    utils::is_group_admin,
+};
+
+/// 按条件选题时默认的题目数
+const DEFAULT_PROBLEM_COUNT: usize = 5;
+const MAX_PROBLEM_COUNT: usize = 26;
+/// 每次错误提交的罚时（分钟）
+const PENALTY_MINUTES: i64 = 20;
+
+#[derive(Clone, Debug)]
+pub struct Mashup {
+    pub id: i64,
+    pub group_id: i64,
+    pub creator: i64,
+    /// 按顺序编号为 A, B, C...，按条件选题时开始前为空
+    pub problems: Vec<(i64, String)>,
+    /// 按条件选题时的筛选条件，比赛开始时才选题
+    pub filter: Option<String>,
+    pub problem_count: usize,
+    pub start_time: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub status: VirtualStatus,
+}
+
+impl Mashup {
+    fn end_time(&self) -> DateTime<Utc> {
+        self.start_time + Duration::seconds(self.duration_seconds)
+    }
+}
+
+fn label(i: usize) -> String {
+    ((b'A' + i as u8) as char).to_string()
+}
+
+/// 创建群赛时的选题方式
+#[derive(Debug, PartialEq)]
+enum ProblemChoice {
+    Ids(Vec<(i64, String)>),
+    Filter { args: Vec<String>, count: usize },
+}
+
+/// 选题参数：全部是题号时直接使用，否则作为筛选条件随机选题
+fn parse_problem_args(args: &[String]) -> Result<ProblemChoice> {
+    let mut count = DEFAULT_PROBLEM_COUNT;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-c" || arg == "--count" {
+            count = iter
+                .next()
+                .and_then(|n| n.parse().ok())
+                .filter(|n| (1..=MAX_PROBLEM_COUNT).contains(n))
+                .ok_or_else(|| anyhow::anyhow!("题目数需要在 1 到 {} 之间", MAX_PROBLEM_COUNT))?;
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+
+    let ids = rest
+        .iter()
+        .map(|arg| parse_problem_id(arg))
+        .collect::<Option<Vec<_>>>();
+    match ids {
+        Some(ids) if !ids.is_empty() => Ok(ProblemChoice::Ids(ids)),
+        _ => Ok(ProblemChoice::Filter { args: rest, count }),
+    }
+}
+
+/// 创建群赛（群管理员）
+///
+/// 用法：/contest host <HH:MM|now> <时长(分钟)> <题号...|筛选条件 [-c 题目数]>
+pub async fn host(event: &MsgEvent, args: &[String]) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    if !is_group_admin(event) {
+        event.reply("只有群管理员可以创建群赛");
+        return;
+    }
+
+    let result = async {
+        if sql::mashup::get_active_mashup(group_id).await?.is_some() {
+            return Err(anyhow::anyhow!("本群已经有一场群赛了"));
+        }
+
+        let (Some(start), Some(duration), Some(problem_args)) = (
+            args.get(2),
+            args.get(3).and_then(|minutes| minutes.parse::<i64>().ok()),
+            args.get(4..).filter(|rest| !rest.is_empty()),
+        ) else {
+            return Err(anyhow::anyhow!(
+                "用法：/contest host <HH:MM|now> <时长(分钟)> <题号...|筛选条件 [-c 题目数]>"
+            ));
+        };
+
+        let start_time = if start == "now" {
+            Utc::now()
+        } else {
+            let time = kovi::chrono::NaiveTime::parse_from_str(start, "%H:%M")
+                .map_err(|_| anyhow::anyhow!("无法识别开始时间 {}", start))?;
+            next_time(time)
+        };
+        if duration <= 0 {
+            return Err(anyhow::anyhow!("时长需要大于 0"));
+        }
+
+        let (problems, filter) = match parse_problem_args(problem_args)? {
+            ProblemChoice::Ids(ids) => {
+                let known = get_problems()
+                    .await?
+                    .iter()
+                    .map(|problem| problem.key())
+                    .collect::<HashSet<_>>();
+                if let Some((contest_id, index)) = ids.iter().find(|key| !known.contains(*key)) {
+                    return Err(anyhow::anyhow!("题库中没有 {}{}", contest_id, index));
+                }
+                (ids, None)
+            }
+            ProblemChoice::Filter { args, count } => {
+                // 先检查一遍条件，开始时还会排除参赛者通过的题目
+                let candidates = get_problems_by(&ProblemFilter::parse(&args)?, event.user_id)
+                    .await?
+                    .len();
+                if candidates < count {
+                    return Err(anyhow::anyhow!(
+                        "符合条件的题目只有 {} 道，不够 {} 道",
+                        candidates,
+                        count
+                    ));
+                }
+                (Vec::new(), Some((args.join(" "), count)))
+            }
+        };
+        if problems.len() > MAX_PROBLEM_COUNT {
+            return Err(anyhow::anyhow!("最多 {} 道题", MAX_PROBLEM_COUNT));
+        }
+
+        let mashup = Mashup {
+            id: 0,
+            group_id,
+            creator: event.user_id,
+            problem_count: filter.as_ref().map_or(problems.len(), |(_, count)| *count),
+            problems,
+            filter: filter.map(|(filter, _)| filter),
+            start_time,
+            duration_seconds: duration * 60,
+            status: VirtualStatus::Pending,
+        };
+        Commit::start()
+            .await?
+            .add_mashup(&mashup)
+            .await?
+            .commit()
+            .await?;
+
+        Ok(format!(
+            "已创建群赛，{} 开始，时长 {} 分钟，共 {} 题\n使用 /contest join 报名，开始时公布题目",
+            format_local(mashup.start_time),
+            duration,
+            mashup.problem_count
+        ))
+    }
+    .await;
+
+    match result {
+        Ok(msg) => event.reply(msg),
+        Err(e) => event.reply(format!("创建群赛失败: {}", e)),
+    }
+}
+
+/// 报名或者退出本群的群赛，比赛开始后也可以报名
+pub async fn join(event: &MsgEvent, join: bool) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let mashup = sql::mashup::get_active_mashup(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有群赛"))?;
+
+        let mut commit = Commit::start().await?;
+        if join {
+            let cf_id = user_cf_id(event.user_id).await?;
+            commit.join_mashup(mashup.id, event.user_id, &cf_id).await?;
+        } else {
+            commit.leave_mashup(mashup.id, event.user_id).await?;
+        }
+        commit.commit().await?;
+        anyhow::Ok(mashup)
+    }
+    .await;
+
+    match result {
+        Ok(mashup) if join => event.reply(format!(
+            "报名成功，群赛 {} 开始，{} 结束",
+            format_local(mashup.start_time),
+            format_local(mashup.end_time())
+        )),
+        Ok(_) => event.reply("已退出群赛"),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+/// 取消本群的群赛（群管理员）
+pub async fn cancel(event: &MsgEvent) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    if !is_group_admin(event) {
+        event.reply("只有群管理员可以取消群赛");
+        return;
+    }
+
+    let result = async {
+        let mut mashup = sql::mashup::get_active_mashup(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有群赛"))?;
+        mashup.status = VirtualStatus::Cancelled;
+        save(&mashup).await
+    }
+    .await;
+
+    match result {
+        Ok(_) => event.reply("已取消群赛"),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+/// 查看本群群赛的当前榜单
+pub async fn board(event: &MsgEvent) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let mashup = sql::mashup::get_active_mashup(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有群赛"))?;
+        let participants = sql::mashup::get_mashup_participants(mashup.id).await?;
+
+        if mashup.status == VirtualStatus::Pending {
+            return anyhow::Ok(Message::from(format!(
+                "群赛 {} 开始，共 {} 题，已报名：{}",
+                format_local(mashup.start_time),
+                mashup.problem_count,
+                participants
+                    .iter()
+                    .map(|(_, cf_id)| cf_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let table = scoreboard(&mashup, &participants).await?;
+        let image = render("standings.py", &table).await?;
+        Ok(Message::new()
+            .add_image(&image)
+            .add_text(format!("{} 结束", format_local(mashup.end_time()))))
+    }
+    .await;
+
+    match result {
+        Ok(msg) => event.reply(msg),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+async fn save(mashup: &Mashup) -> Result<()> {
+    Commit::start()
+        .await?
+        .update_mashup(mashup)
+        .await?
+        .commit()
+        .await?;
+    Ok(())
+}
+
+/// 定时检查：到时间的群赛公布题目，时间结束的群赛公布最终榜单
+pub async fn tick() {
+    let mashups = match sql::mashup::get_active_mashups().await {
+        Ok(mashups) => mashups,
+        Err(e) => {
+            error!("获取群赛失败: {}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    for mut mashup in mashups {
+        let result = match mashup.status {
+            VirtualStatus::Pending if mashup.start_time <= now => start(&mut mashup).await,
+            VirtualStatus::Running if mashup.end_time() <= now => finish(&mut mashup).await,
+            _ => continue,
+        };
+
+        if let Err(e) = result {
+            error!("处理群赛 {} 失败: {}", mashup.id, e);
+        }
+    }
+}
+
+async fn start(mashup: &mut Mashup) -> Result<()> {
+    if let Some(filter) = mashup.filter.clone()
+        && mashup.problems.is_empty()
+    {
+        match pick_problems(mashup, &filter).await? {
+            Some(problems) => mashup.problems = problems,
+            None => {
+                mashup.status = VirtualStatus::Cancelled;
+                save(mashup).await?;
+                BOT.get().unwrap().send_group_msg(
+                    mashup.group_id,
+                    "符合条件且所有参赛者都没有通过的题目不够，群赛已取消",
+                );
+                return Ok(());
+            }
+        }
+    }
+
+    mashup.status = VirtualStatus::Running;
+    save(mashup).await?;
+
+    let problems = get_problems().await?;
+    let mut msg = format!("群赛开始！{} 结束，题目：", format_local(mashup.end_time()));
+    for (i, (contest_id, index)) in mashup.problems.iter().enumerate() {
+        let name = problems
+            .iter()
+            .find(|problem| problem.contest_id == *contest_id && problem.index == *index)
+            .map(|problem| format!(" {}", problem.name))
+            .unwrap_or_default();
+        msg.push_str(&format!(
+            "\n{}.{} {}",
+            label(i),
+            name,
+            problem_url(*contest_id, index)
+        ));
+    }
+    msg.push_str("\n使用 /contest board 查看榜单，还没报名的可以使用 /contest join 报名");
+
+    info!("start mashup {} in group {}", mashup.id, mashup.group_id);
+    BOT.get().unwrap().send_group_msg(mashup.group_id, msg);
+    Ok(())
+}
+
+/// 按条件随机选题，排除任意一名参赛者已经通过的题目，题目不够时返回 None
+async fn pick_problems(mashup: &Mashup, filter: &str) -> Result<Option<Vec<(i64, String)>>> {
+    let args = filter
+        .split_whitespace()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let candidates = get_problems_by(&ProblemFilter::parse(&args)?, mashup.creator).await?;
+
+    let mut solved = HashSet::new();
+    for (_, cf_id) in sql::mashup::get_mashup_participants(mashup.id).await? {
+        solved.extend(get_solved_set(&cf_id).await?);
+    }
+
+    let candidates = candidates
+        .iter()
+        .filter(|problem| !solved.contains(&problem.key()))
+        .collect::<Vec<_>>();
+    if candidates.len() < mashup.problem_count {
+        return Ok(None);
+    }
+
+    let mut picked = candidates
+        .choose_multiple(&mut rand::rng(), mashup.problem_count)
+        .collect::<Vec<_>>();
+    picked.sort_by_key(|problem| problem.rating);
+    Ok(Some(
+        picked.into_iter().map(|problem| problem.key()).collect(),
+    ))
+}
+
+async fn finish(mashup: &mut Mashup) -> Result<()> {
+    mashup.status = VirtualStatus::Finished;
+    save(mashup).await?;
+
+    let participants = sql::mashup::get_mashup_participants(mashup.id).await?;
+    let msg = if participants.is_empty() {
+        Message::from("群赛结束，没有人参加")
+    } else {
+        match scoreboard(mashup, &participants).await {
+            Ok(table) => {
+                let mut msg = Message::new();
+                match render("standings.py", &table).await {
+                    Ok(image) => msg = msg.add_image(&image),
+                    Err(e) => error!("生成群赛榜单失败: {}", e),
+                }
+                let top = table
+                    .rows
+                    .iter()
+                    .take(3)
+                    .map(|row| format!("第 {} 名 {}", row.rank.unwrap_or(0), row.who))
+                    .collect::<Vec<_>>();
+                msg.add_text(format!("群赛结束！\n{}", top.join("\n")))
+            }
+            Err(e) => Message::from(format!("群赛结束，统计成绩失败: {}", e)),
+        }
+    };
+
+    BOT.get().unwrap().send_group_msg(mashup.group_id, msg);
+    Ok(())
+}
+
+/// 一名参赛者的成绩
+#[derive(Debug, PartialEq)]
+struct Standing {
+    cf_id: String,
+    solved: i64,
+    penalty: i64,
+    /// 每道题 (通过时间, 错误次数)
+    problems: Vec<(Option<i64>, i64)>,
+}
+
+/// `submissions` 为比赛时间内的提交，时间为相对开始的秒数，按时间升序
+///
+/// 把题目换成群赛中的编号后按 ICPC 赛制计分
+fn standing(
+    cf_id: &str,
+    submissions: &[(i64, &Submission)],
+    problems: &[(i64, String)],
+) -> Standing {
+    let relabeled = submissions
+        .iter()
+        .filter_map(|(time, submission)| {
+            let i = problems
+                .iter()
+                .position(|key| *key == submission.problem.key())?;
+            let mut submission = (*submission).clone();
+            submission.problem.index = label(i);
+            Some((*time, submission))
+        })
+        .collect::<Vec<_>>();
+    let relabeled = relabeled
+        .iter()
+        .map(|(time, submission)| (*time, submission))
+        .collect::<Vec<_>>();
+    let labels = (0..problems.len())
+        .map(|i| ContestProblem {
+            index: label(i),
+            name: String::new(),
+            points: None,
+        })
+        .collect::<Vec<_>>();
+
+    let score = score(
+        &relabeled,
+        &labels,
+        Rules::Icpc {
+            penalty_minutes: PENALTY_MINUTES,
+        },
+    );
+    Standing {
+        cf_id: cf_id.to_string(),
+        solved: score.points as i64,
+        penalty: score.penalty,
+        problems: score
+            .problems
+            .iter()
+            .map(|&(time, rejected, _)| (time, rejected))
+            .collect(),
+    }
+}
+
+/// 排序并标出每道题的一血，并列的名次相同
+fn build_table(mut standings: Vec<Standing>, problem_count: usize) -> Table {
+    standings.sort_by_key(|standing| (std::cmp::Reverse(standing.solved), standing.penalty));
+
+    let first = (0..problem_count)
+        .map(|i| {
+            standings
+                .iter()
+                .filter_map(|standing| standing.problems[i].0)
+                .min()
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows: Vec<Row> = Vec::new();
+    for (i, standing) in standings.iter().enumerate() {
+        let tied = i > 0
+            && standings[i - 1].solved == standing.solved
+            && standings[i - 1].penalty == standing.penalty;
+        let rank = match rows.last() {
+            Some(last) if tied => last.rank,
+            _ => Some(i as i64 + 1),
+        };
+
+        let results = standing
+            .problems
+            .iter()
+            .enumerate()
+            .map(|(j, &(time, rejected))| {
+                let mut cell =
+                    result_cell(if time.is_some() { 1.0 } else { 0.0 }, rejected, time, true);
+                if time.is_some() && time == first[j] {
+                    cell.state = "first";
+                }
+                cell
+            })
+            .collect();
+
+        rows.push(Row {
+            rank,
+            who: standing.cf_id.clone(),
+            points: standing.solved.to_string(),
+            penalty: standing.penalty,
+            results,
+            hacks: String::new(),
+            delta: None,
+        });
+    }
+
+    Table {
+        contest: String::new(),
+        icpc: true,
+        problems: (0..problem_count).map(label).collect(),
+        rows,
+        truncated: false,
+    }
+}
+
+async fn scoreboard(mashup: &Mashup, participants: &[(i64, String)]) -> Result<Table> {
+    let start = mashup.start_time.timestamp();
+
+    let mut standings = Vec::new();
+    for (_, cf_id) in participants {
+        let submissions = get_recent_submissions(cf_id).await?;
+        let mut submissions = submissions
+            .iter()
+            .map(|submission| (submission.creation_time_seconds - start, submission))
+            .filter(|(time, _)| (0..mashup.duration_seconds).contains(time))
+            .collect::<Vec<_>>();
+        submissions.sort_by_key(|(time, _)| *time);
+        standings.push(standing(cf_id, &submissions, &mashup.problems));
+    }
+
+    let mut table = build_table(standings, mashup.problems.len());
+    table.contest = format!("Mashup #{}", mashup.id);
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duel::submission::SubmissionBuilder;
+
+    fn submission(contest_id: i64, index: &str, verdict: &str) -> Submission {
+        SubmissionBuilder::new(0)
+            .problem(contest_id, index)
+            .verdict(verdict)
+            .build()
+    }
+
+    #[test]
+    fn test_parse_problem_args() {
+        let args = ["1900D", "4a"].map(String::from);
+        assert_eq!(
+            parse_problem_args(&args).unwrap(),
+            ProblemChoice::Ids(vec![(1900, "D".to_string()), (4, "A".to_string())])
+        );
+
+        let args = ["1600-1900", "dp", "-c", "3"].map(String::from);
+        assert_eq!(
+            parse_problem_args(&args).unwrap(),
+            ProblemChoice::Filter {
+                args: vec!["1600-1900".to_string(), "dp".to_string()],
+                count: 3
+            }
+        );
+
+        assert!(parse_problem_args(&["dp", "-c", "100"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_scoreboard() {
+        let problems = vec![(1, "A".to_string()), (2, "B".to_string())];
+        let wa = submission(1, "A", "WRONG_ANSWER");
+        let ok_a = submission(1, "A", "OK");
+        let ok_b = submission(2, "B", "OK");
+        let other = submission(3, "A", "OK");
+
+        // 20 分钟过 A（之前错一次），40 分钟过 B
+        let alice = standing(
+            "alice",
+            &[(600, &wa), (1200, &ok_a), (1800, &other), (2400, &ok_b)],
+            &problems,
+        );
+        assert_eq!(alice.solved, 2);
+        assert_eq!(alice.penalty, 20 + 20 + 40);
+
+        // 10 分钟过 A，50 分钟过 B
+        let bob = standing("bob", &[(600, &ok_a), (3000, &ok_b)], &problems);
+        assert_eq!(bob.penalty, 10 + 50);
+        let carol = standing("carol", &[(600, &ok_a), (3000, &ok_b)], &problems);
+
+        let table = build_table(vec![alice, bob, carol], problems.len());
+        let order = table
+            .rows
+            .iter()
+            .map(|row| (row.who.as_str(), row.rank))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![("bob", Some(1)), ("carol", Some(1)), ("alice", Some(3))]
+        );
+
+        // A 的一血是 bob 和 carol，B 的一血是 alice
+        assert_eq!(table.rows[0].results[0].state, "first");
+        assert_eq!(table.rows[0].results[1].state, "solved");
+        assert_eq!(table.rows[2].results[0].state, "solved");
+        assert_eq!(table.rows[2].results[1].state, "first");
+    }
+}
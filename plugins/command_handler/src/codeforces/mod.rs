@@ -1,66 +1,204 @@
 use anyhow::Result;
 use base64::{Engine, engine::general_purpose::STANDARD};
-use kovi::{Message, MsgEvent};
+use kovi::tokio::io::AsyncWriteExt;
+use kovi::{Message, MsgEvent, PluginBuilder as plugin};
+use std::process::Stdio;
 
 use crate::{
     CONFIG, PATH,
-    utils::{IdOrText, user_id_or_text},
+    utils::{IdOrText, group_member_ids, user_id_or_text},
 };
 
+pub mod api;
+pub mod brag;
+pub mod compare;
+pub mod curation;
+pub mod group;
+pub mod mashup;
+pub mod plan;
+pub mod predict;
+pub mod problemset;
 pub mod recommend;
+pub mod standings;
+pub mod upsolve;
+pub mod virtual_contest;
+pub mod watch;
 
+pub fn init() {
+    plugin::cron("*/10 * * * *", || async {
+        // 每 10 分钟检查一次最近结束的比赛是否更新了 rating，并记录需要补的题
+        watch::check_rating_changes().await;
+        upsolve::track_contests().await;
+    })
+    .unwrap();
+
+    plugin::cron("*/10 * * * *", || async {
+        // 每 10 分钟检查一次群友的新提交，成员多时一轮会比较久，所以单独一个任务
+        brag::check_feed().await;
+    })
+    .unwrap();
+
+    plugin::cron("0 20 * * 6", || async {
+        // 每周六 20 点提醒补题
+        upsolve::remind_weekly().await;
+    })
+    .unwrap();
+
+    plugin::cron("* * * * *", || async {
+        // 每分钟检查虚拟赛和群赛是否需要开始或结束
+        virtual_contest::tick().await;
+        mashup::tick().await;
+    })
+    .unwrap();
+}
+
+/// 同一张图中最多画几个人的 rating 曲线，--group 人数更多时分成多张图
+const MAX_RATING_USERS: usize = 10;
+
+#[derive(serde::Serialize)]
+struct RatingSeries {
+    handle: String,
+    /// (时间戳, rating)
+    points: Vec<(i64, i64)>,
+}
+
+/// 画出一个或多个用户的 rating 曲线
+///
+/// 用法：/cf rating [@p, cf_id]...，不带参数时查询自己；/cf rating --group 画出本群所有绑定用户
 pub async fn rating(event: &MsgEvent, args: &[String]) {
-    let user = match args.get(2).map(|s| user_id_or_text(s)).unwrap() {
-        Ok(v) => v,
-        Err(_) => {
-            event.reply("参数非法");
-            return;
-        }
-    };
+    let args = args.get(2..).unwrap_or_default();
 
-    let cf_id = match get_cf_id(&user).await {
-        Ok(cf_id) => cf_id,
-        Err(e) => {
-            event.reply(e.to_string());
-            return;
+    let is_group_flag = |arg: &String| arg == "--group" || arg == "-g";
+    let group = args.iter().any(is_group_flag);
+    if group && !args.iter().all(is_group_flag) {
+        event.reply("用法：/cf rating [@p, cf_id]... 或 /cf rating --group/-g，两者不能同时使用");
+        return;
+    }
+    let (handles, truncated) = if group {
+        match group_handles(event).await {
+            Ok(handles) => (handles, false),
+            Err(e) => {
+                event.reply(e.to_string());
+                return;
+            }
         }
+    } else {
+        let mut handles = Vec::new();
+        let users = if args.is_empty() {
+            vec![IdOrText::At(event.user_id)]
+        } else {
+            match args
+                .iter()
+                .map(|arg| user_id_or_text(arg))
+                .collect::<Result<Vec<_>>>()
+            {
+                Ok(users) => users,
+                Err(_) => {
+                    event.reply("参数非法");
+                    return;
+                }
+            }
+        };
+        for user in users.iter() {
+            match get_cf_id(user).await {
+                Ok(cf_id) if !handles.contains(&cf_id) => handles.push(cf_id),
+                Ok(_) => {}
+                Err(e) => {
+                    event.reply(e.to_string());
+                    return;
+                }
+            }
+        }
+        let truncated = handles.len() > MAX_RATING_USERS;
+        handles.truncate(MAX_RATING_USERS);
+        (handles, truncated)
     };
 
-    let path = PATH.get().unwrap().join("codeforces");
-    let py_analyzer_path = CONFIG.get().unwrap().py_analyzer_path.clone();
-    let py_path = path.join("rating.py");
-
     event.reply("正在查询用户rating记录");
 
-    let output: std::process::Output = match crate::utils::fetch_cf_api(async move {
-        kovi::tokio::process::Command::new(py_analyzer_path)
-            .arg(py_path)
-            .arg(cf_id)
-            .output()
-            .await
-            .map_err(anyhow::Error::from)
-    })
-    .await
-    {
-        Ok(output) => output,
-        Err(e) => {
-            event.reply(e.to_string());
-            return;
+    let mut series = Vec::new();
+    let mut unrated = Vec::new();
+    // 群模式下有人改名或注销时跳过他，不影响其他人
+    let mut failed = Vec::new();
+    for handle in handles {
+        match api::user_rating(&handle).await {
+            Ok(history) if history.is_empty() => unrated.push(handle),
+            Ok(history) => series.push(RatingSeries {
+                handle,
+                points: history
+                    .iter()
+                    .map(|change| (change.rating_update_time_seconds, change.new_rating))
+                    .collect(),
+            }),
+            Err(_) if group => failed.push(handle),
+            Err(e) => {
+                event.reply(format!("查询 {} 失败: {}", handle, e));
+                return;
+            }
         }
-    };
+    }
 
-    if !output.status.success() {
-        event.reply(format!(
-            "查询失败: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+    if series.is_empty() {
+        if failed.is_empty() {
+            event.reply("没有参赛记录");
+        } else {
+            event.reply(format!("没有参赛记录，{} 查询失败", failed.join(", ")));
+        }
         return;
     }
 
-    let image = output.stdout;
-    let image = STANDARD.encode(image);
+    // 按 rating 从高到低每 MAX_RATING_USERS 人画一张图
+    let pages = if group {
+        series.chunks(MAX_RATING_USERS).collect::<Vec<_>>()
+    } else {
+        vec![series.as_slice()]
+    };
+    let mut msg = Message::new();
+    for page in pages {
+        match render("rating.py", &page).await {
+            Ok(image) => msg = msg.add_image(&image),
+            Err(e) => {
+                event.reply(format!("查询失败: {}", e));
+                return;
+            }
+        }
+    }
 
-    event.reply(Message::new().add_image(&format!("base64://{}", image)));
+    if !unrated.is_empty() {
+        msg = msg.add_text(format!("{} 没有参赛记录", unrated.join(", ")));
+    }
+    if !failed.is_empty() {
+        msg = msg.add_text(format!("\n{} 查询失败，可能已经改名", failed.join(", ")));
+    }
+    if truncated {
+        msg = msg.add_text(format!("\n人数太多，只画出了前 {} 人", MAX_RATING_USERS));
+    }
+    event.reply(msg);
+}
+
+/// 本群所有绑定了 CF 账号的成员，按 rating 从高到低排序
+async fn group_handles(event: &MsgEvent) -> Result<Vec<String>> {
+    let group_id = event
+        .group_id
+        .ok_or_else(|| anyhow::anyhow!("--group 只能在群里使用"))?;
+    let handles = bound_handles(Some(group_id)).await?;
+
+    let mut ratings = Vec::new();
+    for chunk in handles.chunks(100) {
+        let chunk = chunk.iter().map(String::as_str).collect::<Vec<_>>();
+        // 有人改名后整批查询都会失败，这时不再排序
+        match api::user_info(&chunk).await {
+            Ok(users) => ratings.extend(
+                users
+                    .iter()
+                    .map(|user| (user.rating.unwrap_or(0), user.handle.clone())),
+            ),
+            Err(_) => ratings.extend(chunk.iter().map(|handle| (0, handle.to_string()))),
+        }
+    }
+    ratings.sort_by_key(|(rating, _)| std::cmp::Reverse(*rating));
+
+    Ok(ratings.into_iter().map(|(_, handle)| handle).collect())
 }
 
 pub async fn analyze(event: &MsgEvent, args: &[String]) {
@@ -117,6 +255,60 @@ pub async fn analyze(event: &MsgEvent, args: &[String]) {
     event.reply(Message::new().add_image(&format!("base64://{}", image)));
 }
 
+/// 绑定了 CF 账号的用户，指定群时只取本群成员
+async fn bound_handles(group_id: Option<i64>) -> Result<Vec<String>> {
+    let members = match group_id {
+        Some(group_id) => Some(group_member_ids(group_id).await?),
+        None => None,
+    };
+
+    let handles = crate::sql::duel::user::get_bound_users()
+        .await?
+        .into_iter()
+        .filter(|user| {
+            members
+                .as_ref()
+                .is_none_or(|members| members.contains(&user.qq))
+        })
+        .filter_map(|user| user.cf_id)
+        .collect::<Vec<_>>();
+    if handles.is_empty() {
+        return Err(anyhow::anyhow!(match group_id {
+            Some(_) => "本群还没有人绑定 CF 账号",
+            None => "还没有人绑定 CF 账号",
+        }));
+    }
+    Ok(handles)
+}
+
+/// 运行绘图脚本，`input` 序列化为 JSON 后从标准输入传入，返回可以直接发送的图片
+///
+/// 脚本不访问网络，需要的数据都由调用方准备好
+async fn render<T: serde::Serialize>(script: &str, input: &T) -> Result<String> {
+    let path = PATH.get().unwrap().join("codeforces");
+    let py_analyzer_path = CONFIG.get().unwrap().py_analyzer_path.clone();
+    let input = kovi::serde_json::to_vec(input)?;
+
+    let mut child = kovi::tokio::process::Command::new(py_analyzer_path)
+        .arg(path.join(script))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().unwrap().write_all(&input).await?;
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(format!("base64://{}", STANDARD.encode(output.stdout)))
+}
+
 async fn get_cf_id(uit: &IdOrText<'_>) -> Result<String> {
     match uit {
         IdOrText::At(qq) => {
@@ -0,0 +1,656 @@
+//! 按周安排的训练计划
+//!
+//! 创建时从本地题库中按周挑选题目，难度从当前 rating 逐周升到目标 rating。
+//! 查看时根据 CF 上的提交自动更新进度，落后或者领先时重新安排下一周的题目。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+use kovi::{MsgEvent, chrono::Utc};
+use rand::seq::IndexedRandom;
+
+use crate::{
+    codeforces::api,
+    duel::{
+        config::DEFAULT_PERSONAL_RATING,
+        curation::get_blacklist,
+        filter::ProblemFilter,
+        problem::{Problem, filter_context, get_problems, problem_url},
+        submission::get_recent_submissions,
+    },
+    sql::{self, plan::CommitPlanExt, utils::Commit},
+};
+
+const MIN_RATING: i64 = 800;
+const MAX_RATING: i64 = 3500;
+const MAX_WEEKS: i64 = 12;
+const DEFAULT_WEEKS: i64 = 4;
+/// 不指定目标时比当前 rating 高这么多
+const DEFAULT_GAIN: i64 = 200;
+/// 每周的题目相对这周中心难度的偏移，前易后难，题目数就是它的长度
+const WEEK_OFFSETS: &[i64] = &[-100, 0, 0, 100, 100];
+/// 落后时下一周至少安排几道题
+const MIN_WEEK_PROBLEMS: usize = 2;
+const WEEK_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanStatus {
+    Active,
+    Finished,
+    Cancelled,
+}
+
+impl PlanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Finished => "finished",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "active" => Some(Self::Active),
+            "finished" => Some(Self::Finished),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainingPlan {
+    pub id: i64,
+    pub qq: i64,
+    pub cf_id: String,
+    pub start_time: i64,
+    pub weeks: i64,
+    pub start_rating: i64,
+    pub target_rating: i64,
+    /// 重点练习的标签，是筛选条件的原文，空表示不限
+    pub focus: String,
+    pub status: PlanStatus,
+    /// 最后一次重新安排的是第几周，每周最多重新安排一次
+    pub rebalanced_week: i64,
+}
+
+impl TrainingPlan {
+    /// 现在是第几周，从 1 开始，计划结束后会大于 `weeks`
+    fn current_week(&self, now: i64) -> i64 {
+        (now - self.start_time).max(0) / WEEK_SECONDS + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanItem {
+    pub week: i64,
+    pub contest_id: i64,
+    pub index: String,
+    pub rating: i64,
+    pub solved_at: Option<i64>,
+}
+
+impl PlanItem {
+    fn key(&self) -> (i64, String) {
+        (self.contest_id, self.index.clone())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Pace {
+    /// 有之前几周的题目没完成
+    Behind,
+    OnTrack,
+    /// 本周的题目已经做完，或者做了之后几周的题目
+    Ahead,
+}
+
+fn pace(items: &[PlanItem], current_week: i64) -> Pace {
+    if items
+        .iter()
+        .any(|item| item.week < current_week && item.solved_at.is_none())
+    {
+        return Pace::Behind;
+    }
+
+    let mut this_week = items.iter().filter(|item| item.week == current_week);
+    let ahead = items
+        .iter()
+        .any(|item| item.week > current_week && item.solved_at.is_some());
+    if ahead || this_week.all(|item| item.solved_at.is_some()) {
+        Pace::Ahead
+    } else {
+        Pace::OnTrack
+    }
+}
+
+fn round_rating(rating: i64) -> i64 {
+    ((rating + 50) / 100 * 100).clamp(MIN_RATING, MAX_RATING)
+}
+
+/// 第 `week` 周的中心难度，从起始 rating 线性升到目标 rating
+fn week_center(start: i64, target: i64, weeks: i64, week: i64) -> i64 {
+    if weeks <= 1 {
+        return round_rating(target);
+    }
+    round_rating(start + (target - start) * (week - 1) / (weeks - 1))
+}
+
+/// 一周中各题的难度，题目少于一周的正常数量时去掉最难的
+fn week_ratings(center: i64, count: usize) -> Vec<i64> {
+    WEEK_OFFSETS
+        .iter()
+        .take(count)
+        .map(|offset| (center + offset).clamp(MIN_RATING, MAX_RATING))
+        .collect()
+}
+
+/// 解析 /cf plan create 的参数：周数、目标 rating 和重点标签
+fn parse_create_args(args: &[String]) -> Result<(Option<i64>, Option<i64>, Vec<String>)> {
+    let mut weeks = None;
+    let mut target = None;
+    let mut focus = Vec::new();
+    for arg in args {
+        match arg.parse::<i64>() {
+            Ok(n) if weeks.is_none() && (1..=MAX_WEEKS).contains(&n) => weeks = Some(n),
+            Ok(n) if target.is_none() && (MIN_RATING..=MAX_RATING).contains(&n) => target = Some(n),
+            Ok(_) => anyhow::bail!(
+                "周数应在 1 到 {} 之间，目标 rating 应在 {} 到 {} 之间",
+                MAX_WEEKS,
+                MIN_RATING,
+                MAX_RATING
+            ),
+            Err(_) => focus.push(arg.clone()),
+        }
+    }
+    Ok((weeks, target, focus))
+}
+
+/// 按难度从题库中挑题，优先符合重点标签的题，没有时不限标签
+async fn pick_problems(
+    qq: i64,
+    focus: &str,
+    ratings: &[i64],
+    exclude: &HashSet<(i64, String)>,
+) -> Result<Vec<Arc<Problem>>> {
+    let filter = ProblemFilter::parse(&focus.split_whitespace().collect::<Vec<_>>())?;
+    let ctx = filter_context(&filter, qq).await?;
+    let problems = get_problems().await?;
+    let blacklist = get_blacklist().await?;
+
+    let mut used = exclude.clone();
+    let mut picked = Vec::new();
+    let mut rng = rand::rng();
+    for &rating in ratings {
+        let candidates = problems
+            .iter()
+            .filter(|p| p.rating == Some(rating))
+            .filter(|p| !used.contains(&p.key()))
+            .filter(|p| !p.tags.iter().any(|tag| tag == "*special"))
+            .filter(|p| !blacklist.contains(p))
+            .collect::<Vec<_>>();
+        let focused = candidates
+            .iter()
+            .filter(|p| filter.matches(p, &ctx))
+            .copied()
+            .collect::<Vec<_>>();
+        let choice = focused
+            .choose(&mut rng)
+            .or_else(|| candidates.choose(&mut rng));
+        if let Some(&problem) = choice {
+            used.insert(problem.key());
+            picked.push(problem.clone());
+        }
+    }
+    Ok(picked)
+}
+
+/// 训练计划
+///
+/// 用法：/cf plan 查看进度；/cf plan create [周数] [目标 rating] [重点标签...]；/cf plan cancel
+pub async fn plan(event: &MsgEvent, args: &[String]) {
+    match args.get(2).map(String::as_str) {
+        None => show(event).await,
+        Some("create") => create(event, &args[3..]).await,
+        Some("cancel") => cancel(event).await,
+        Some(_) => {
+            event.reply("用法：/cf plan [create [周数] [目标 rating] [重点标签...] | cancel]")
+        }
+    }
+}
+
+async fn create(event: &MsgEvent, args: &[String]) {
+    let (weeks, target, focus) = match parse_create_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+    let focus = focus.join(" ");
+    if let Err(e) = ProblemFilter::parse(&focus.split_whitespace().collect::<Vec<_>>()) {
+        event.reply(e.to_string());
+        return;
+    }
+
+    let cf_id = match sql::duel::user::get_user(event.user_id)
+        .await
+        .ok()
+        .and_then(|user| user.cf_id)
+    {
+        Some(cf_id) => cf_id,
+        None => {
+            event.reply("请先使用 /bind 绑定 CF 账号");
+            return;
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let old = match sql::plan::get_active_plan(event.user_id).await {
+        Ok(old) => old,
+        Err(e) => {
+            event.reply(format!("查询训练计划失败: {}", e));
+            return;
+        }
+    };
+    if let Some(old) = &old
+        && old.current_week(now) <= old.weeks
+    {
+        event.reply("你已经有进行中的训练计划，使用 /cf plan cancel 取消后再创建");
+        return;
+    }
+
+    // 没有 rating 的新用户和个人每日一题一样从默认 rating 开始
+    let rating = match api::user_info(&[&cf_id]).await {
+        Ok(users) => users
+            .first()
+            .and_then(|user| user.rating)
+            .unwrap_or(DEFAULT_PERSONAL_RATING),
+        Err(e) => {
+            event.reply(format!("获取rating失败: {}", e));
+            return;
+        }
+    };
+
+    let weeks = weeks.unwrap_or(DEFAULT_WEEKS);
+    let start_rating = round_rating(rating);
+    let target_rating = round_rating(target.unwrap_or(rating + DEFAULT_GAIN));
+
+    event.reply("正在生成训练计划");
+
+    let result = async {
+        let solved = get_recent_submissions(&cf_id)
+            .await?
+            .iter()
+            .filter(|s| s.is_accepted())
+            .map(|s| s.problem.key())
+            .collect::<HashSet<_>>();
+
+        let mut exclude = solved;
+        let mut items = Vec::new();
+        for week in 1..=weeks {
+            let center = week_center(start_rating, target_rating, weeks, week);
+            let ratings = week_ratings(center, WEEK_OFFSETS.len());
+            for problem in pick_problems(event.user_id, &focus, &ratings, &exclude).await? {
+                exclude.insert(problem.key());
+                items.push(PlanItem {
+                    week,
+                    contest_id: problem.contest_id,
+                    index: problem.index.clone(),
+                    rating: problem.rating.unwrap_or(center),
+                    solved_at: None,
+                });
+            }
+        }
+        if items.is_empty() {
+            anyhow::bail!("没有找到合适的题目");
+        }
+
+        let plan = TrainingPlan {
+            id: 0,
+            qq: event.user_id,
+            cf_id: cf_id.clone(),
+            start_time: now,
+            weeks,
+            start_rating,
+            target_rating,
+            focus: focus.clone(),
+            status: PlanStatus::Active,
+            rebalanced_week: 0,
+        };
+
+        let mut commit = Commit::start().await?;
+        // 过期的旧计划在创建新计划时结束
+        if let Some(mut old) = old {
+            old.status = PlanStatus::Finished;
+            commit.update_plan(&old).await?;
+        }
+        commit.add_plan(&plan, &items).await?;
+        commit.commit().await?;
+        anyhow::Ok((plan, items))
+    }
+    .await;
+
+    match result {
+        Ok((plan, items)) => {
+            let mut msg = format!(
+                "已创建 {} 周的训练计划，rating {} → {}，共 {} 道题",
+                plan.weeks,
+                plan.start_rating,
+                plan.target_rating,
+                items.len()
+            );
+            if !plan.focus.is_empty() {
+                msg.push_str(&format!("，重点：{}", plan.focus));
+            }
+            msg.push_str("\n第 1 周：");
+            push_items(&mut msg, items.iter().filter(|item| item.week == 1));
+            msg.push_str("\n使用 /cf plan 查看进度");
+            event.reply(msg);
+        }
+        Err(e) => event.reply(format!("创建训练计划失败: {}", e)),
+    }
+}
+
+async fn cancel(event: &MsgEvent) {
+    let result = async {
+        let Some(mut plan) = sql::plan::get_active_plan(event.user_id).await? else {
+            return Ok(false);
+        };
+        plan.status = PlanStatus::Cancelled;
+        Commit::start()
+            .await?
+            .update_plan(&plan)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => event.reply("已取消训练计划"),
+        Ok(false) => event.reply("你没有进行中的训练计划"),
+        Err(e) => event.reply(format!("取消训练计划失败: {}", e)),
+    }
+}
+
+/// 根据提交记录标记已经完成的题目
+async fn sync_progress(plan: &TrainingPlan, items: &mut [PlanItem]) -> Result<()> {
+    if items.iter().all(|item| item.solved_at.is_some()) {
+        return Ok(());
+    }
+
+    let submissions = get_recent_submissions(&plan.cf_id).await?;
+    let mut commit = Commit::start().await?;
+    for item in items.iter_mut().filter(|item| item.solved_at.is_none()) {
+        let solved_at = submissions
+            .iter()
+            .filter(|s| s.is_accepted() && s.problem.key() == item.key())
+            .map(|s| s.creation_time_seconds.max(plan.start_time))
+            .min();
+        if let Some(solved_at) = solved_at {
+            commit
+                .mark_plan_item_solved(plan.id, item, solved_at)
+                .await?;
+            item.solved_at = Some(solved_at);
+        }
+    }
+    commit.commit().await?;
+    Ok(())
+}
+
+/// 重新安排第 `week` 周的题目
+///
+/// 落后时把逾期的题目移到这一周，并降低这一周新题的难度、减少数量；
+/// 领先时提高这一周新题的难度
+async fn rebalance(plan: &TrainingPlan, items: &[PlanItem], week: i64, pace: &Pace) -> Result<()> {
+    let center = week_center(plan.start_rating, plan.target_rating, plan.weeks, week);
+    let overdue = items
+        .iter()
+        .filter(|item| item.week < week && item.solved_at.is_none())
+        .cloned()
+        .collect::<Vec<_>>();
+    let (center, count) = match pace {
+        Pace::Behind => (
+            center - 100,
+            WEEK_OFFSETS
+                .len()
+                .saturating_sub(overdue.len())
+                .max(MIN_WEEK_PROBLEMS),
+        ),
+        Pace::Ahead => (center + 100, WEEK_OFFSETS.len()),
+        Pace::OnTrack => return Ok(()),
+    };
+
+    let exclude = items.iter().map(PlanItem::key).collect::<HashSet<_>>();
+    let solved = get_recent_submissions(&plan.cf_id)
+        .await?
+        .iter()
+        .filter(|s| s.is_accepted())
+        .map(|s| s.problem.key())
+        .collect::<HashSet<_>>();
+    let exclude = exclude.union(&solved).cloned().collect();
+
+    let ratings = week_ratings(center, count);
+    let mut new_items = overdue;
+    new_items.extend(
+        pick_problems(plan.qq, &plan.focus, &ratings, &exclude)
+            .await?
+            .iter()
+            .map(|problem| PlanItem {
+                week,
+                contest_id: problem.contest_id,
+                index: problem.index.clone(),
+                rating: problem.rating.unwrap_or(center),
+                solved_at: None,
+            }),
+    );
+
+    let mut plan = plan.clone();
+    plan.rebalanced_week = week;
+    let mut commit = Commit::start().await?;
+    commit.replace_plan_week(plan.id, week, &new_items).await?;
+    commit.update_plan(&plan).await?;
+    commit.commit().await?;
+    Ok(())
+}
+
+async fn show(event: &MsgEvent) {
+    let plan = match sql::plan::get_active_plan(event.user_id).await {
+        Ok(Some(plan)) => plan,
+        Ok(None) => {
+            event.reply(
+                "你还没有训练计划，使用 /cf plan create [周数] [目标 rating] [重点标签...] 创建",
+            );
+            return;
+        }
+        Err(e) => {
+            event.reply(format!("查询训练计划失败: {}", e));
+            return;
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let current_week = plan.current_week(now);
+
+    let result = async {
+        let mut items = sql::plan::get_plan_items(plan.id).await?;
+        sync_progress(&plan, &mut items).await?;
+
+        let pace = pace(&items, current_week);
+        let next_week = current_week + 1;
+        let rebalanced =
+            pace != Pace::OnTrack && next_week <= plan.weeks && plan.rebalanced_week < next_week;
+        if rebalanced {
+            rebalance(&plan, &items, next_week, &pace).await?;
+            items = sql::plan::get_plan_items(plan.id).await?;
+        }
+
+        // 计划结束后不再显示为进行中
+        if current_week > plan.weeks {
+            let mut plan = plan.clone();
+            plan.status = PlanStatus::Finished;
+            Commit::start()
+                .await?
+                .update_plan(&plan)
+                .await?
+                .commit()
+                .await?;
+        }
+        anyhow::Ok((items, pace, rebalanced))
+    }
+    .await;
+
+    match result {
+        Ok((items, pace, rebalanced)) => {
+            event.reply(format_plan(&plan, &items, current_week, &pace, rebalanced))
+        }
+        Err(e) => event.reply(format!("更新训练进度失败: {}", e)),
+    }
+}
+
+fn push_items<'a>(msg: &mut String, items: impl Iterator<Item = &'a PlanItem>) {
+    for item in items {
+        let done = if item.solved_at.is_some() {
+            "（已完成）"
+        } else {
+            ""
+        };
+        msg.push_str(&format!(
+            "\n{}{} ({}){} {}",
+            item.contest_id,
+            item.index,
+            item.rating,
+            done,
+            problem_url(item.contest_id, &item.index)
+        ));
+    }
+}
+
+fn format_plan(
+    plan: &TrainingPlan,
+    items: &[PlanItem],
+    current_week: i64,
+    pace: &Pace,
+    rebalanced: bool,
+) -> String {
+    let done = items.iter().filter(|item| item.solved_at.is_some()).count();
+    let total = items.len().max(1);
+
+    let mut msg = format!(
+        "训练计划：{} 周，rating {} → {}",
+        plan.weeks, plan.start_rating, plan.target_rating
+    );
+    if !plan.focus.is_empty() {
+        msg.push_str(&format!("，重点：{}", plan.focus));
+    }
+    msg.push_str(&format!(
+        "\n完成 {}/{}（{}%）",
+        done,
+        items.len(),
+        done * 100 / total
+    ));
+
+    if current_week > plan.weeks {
+        msg.push_str("\n计划已经结束，可以使用 /cf plan create 创建新的计划");
+        return msg;
+    }
+    msg.push_str(&format!("，现在是第 {}/{} 周", current_week, plan.weeks));
+
+    let overdue = items
+        .iter()
+        .filter(|item| item.week < current_week && item.solved_at.is_none())
+        .collect::<Vec<_>>();
+    if !overdue.is_empty() {
+        let overdue = overdue
+            .iter()
+            .map(|item| format!("{}{}", item.contest_id, item.index))
+            .collect::<Vec<_>>();
+        msg.push_str(&format!(
+            "\n逾期 {} 道：{}",
+            overdue.len(),
+            overdue.join(", ")
+        ));
+    }
+
+    msg.push_str("\n本周：");
+    push_items(
+        &mut msg,
+        items.iter().filter(|item| item.week == current_week),
+    );
+
+    if current_week < plan.weeks {
+        msg.push_str("\n下周");
+        if rebalanced {
+            msg.push_str(match pace {
+                Pace::Behind => "（进度落后，逾期的题移到了下周，新题降低了难度）",
+                _ => "（进度领先，提高了难度）",
+            });
+        }
+        msg.push('：');
+        push_items(
+            &mut msg,
+            items.iter().filter(|item| item.week == current_week + 1),
+        );
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ladder() {
+        assert_eq!(week_center(1500, 1800, 4, 1), 1500);
+        assert_eq!(week_center(1500, 1800, 4, 2), 1600);
+        assert_eq!(week_center(1500, 1800, 4, 4), 1800);
+        assert_eq!(week_center(1500, 1800, 1, 1), 1800);
+        assert_eq!(week_ratings(1600, 5), vec![1500, 1600, 1600, 1700, 1700]);
+        assert_eq!(week_ratings(800, 2), vec![800, 800]);
+        assert_eq!(round_rating(1449), 1400);
+        assert_eq!(round_rating(1450), 1500);
+        assert_eq!(round_rating(0), MIN_RATING);
+    }
+
+    #[test]
+    fn test_parse_create_args() {
+        let args = ["6", "1900", "dp"].map(String::from);
+        assert_eq!(
+            parse_create_args(&args).unwrap(),
+            (Some(6), Some(1900), vec!["dp".to_string()])
+        );
+        let args = ["2000"].map(String::from);
+        assert_eq!(
+            parse_create_args(&args).unwrap(),
+            (None, Some(2000), vec![])
+        );
+        assert!(parse_create_args(&["100".to_string()]).is_err());
+    }
+
+    fn item(week: i64, solved: bool) -> PlanItem {
+        PlanItem {
+            week,
+            contest_id: 1,
+            index: "A".to_string(),
+            rating: 1500,
+            solved_at: solved.then_some(0),
+        }
+    }
+
+    #[test]
+    fn test_pace() {
+        let items = [item(1, true), item(2, false), item(2, true), item(3, false)];
+        assert_eq!(pace(&items, 2), Pace::OnTrack);
+        assert_eq!(pace(&items, 3), Pace::Behind);
+
+        let items = [item(1, true), item(2, true), item(3, false)];
+        assert_eq!(pace(&items, 2), Pace::Ahead);
+
+        let items = [item(1, true), item(2, false), item(3, true)];
+        assert_eq!(pace(&items, 2), Pace::Ahead);
+    }
+}
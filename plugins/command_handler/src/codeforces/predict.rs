@@ -0,0 +1,450 @@
+//! 比赛中和比赛刚结束时预测 rating 变化
+//!
+//! 按 CF 公开的 rating 计算方法（与 Carrot 相同）对整个榜单计算：
+//! 每个人的期望排名 seed 和实际排名的几何平均决定目标 rating，
+//! delta 为目标与当前 rating 之差的一半，最后整体修正使 delta 之和不为正。
+//!
+//! 参赛不满 6 场的账号显示的 rating 低于有效 rating，计算前要加回差值。
+//! 场次要逐个查询 user.rating，所以只对绑定了的用户修正，其他人按显示的 rating 计算。
+
+use std::{collections::HashMap, sync::Arc, sync::LazyLock, time::Duration};
+
+use kovi::{MsgEvent, log::warn, tokio};
+use moka::future::Cache;
+
+use super::{api, bound_handles, get_cf_id};
+use crate::{
+    codeforces::api::CfApiError,
+    duel::problem::GYM_CONTEST_ID_START,
+    utils::{IdOrText, elo_win_probability},
+};
+
+/// 参加过 k 场 rated 比赛的新账号，有效 rating 比显示的 rating 高 `NEW_ACCOUNT_OFFSETS[k]`
+///
+/// 新账号的有效 rating 从 1400 开始，前 6 场比赛后显示的 rating 依次补上 500、350、250、150、100、50
+const NEW_ACCOUNT_OFFSETS: [i64; 6] = [1400, 900, 550, 300, 150, 50];
+
+/// 参加过 `contests` 场 rated 比赛后有效 rating 与显示的 rating 之差
+fn new_account_offset(contests: usize) -> i64 {
+    NEW_ACCOUNT_OFFSETS.get(contests).copied().unwrap_or(0)
+}
+
+/// 有效 rating 变化 `delta` 时显示的 rating 的变化
+fn displayed_delta(contests: usize, delta: i64) -> i64 {
+    delta + new_account_offset(contests) - new_account_offset(contests + 1)
+}
+
+const MIN_TARGET_RATING: i64 = 1;
+const MAX_TARGET_RATING: i64 = 8000;
+
+/// 榜单变化很快，预测结果只缓存几分钟
+static PREDICTIONS: LazyLock<Cache<i64, Arc<Prediction>>> = LazyLock::new(|| {
+    Cache::builder()
+        .max_capacity(10)
+        .time_to_live(Duration::from_secs(5 * 60))
+        .build()
+});
+
+/// 参与计算的一名选手
+#[derive(Debug, Clone)]
+pub struct Contestant {
+    pub points: f64,
+    pub penalty: i64,
+    /// 赛前的有效 rating
+    pub rating: i64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    handle: String,
+    rank: i64,
+    old_rating: i64,
+    new_rating: i64,
+}
+
+#[derive(Debug)]
+struct Prediction {
+    contest_name: String,
+    /// rating 已经正式更新，结果来自 contest.ratingChanges
+    official: bool,
+    /// 小写 handle -> 结果
+    entries: HashMap<String, Entry>,
+}
+
+/// 预测群里绑定用户在某场比赛中的 rating 变化
+///
+/// 用法：/cf predict <比赛 id>
+pub async fn predict(event: &MsgEvent, args: &[String]) {
+    let Some(contest_id) = args.get(2).and_then(|id| id.parse::<i64>().ok()) else {
+        event.reply("用法：/cf predict <比赛 id>");
+        return;
+    };
+    if contest_id >= GYM_CONTEST_ID_START {
+        event.reply("只能预测 Codeforces 的 rated 比赛");
+        return;
+    }
+
+    let handles = match target_handles(event).await {
+        Ok(handles) => handles,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    if !PREDICTIONS.contains_key(&contest_id) {
+        event.reply("正在计算 rating 变化，请稍候");
+    }
+
+    let prediction = match PREDICTIONS
+        .try_get_with(contest_id, async {
+            build_prediction(contest_id).await.map(Arc::new)
+        })
+        .await
+    {
+        Ok(prediction) => prediction,
+        Err(e) => {
+            event.reply(format!("预测失败: {}", e));
+            return;
+        }
+    };
+
+    let mut entries = handles
+        .iter()
+        .filter_map(|handle| prediction.entries.get(&handle.to_lowercase()))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        event.reply(format!("{} 中没有找到你们的排名", prediction.contest_name));
+        return;
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.new_rating - entry.old_rating));
+
+    let mut msg = if prediction.official {
+        format!("{} 的 rating 已经正式更新：", prediction.contest_name)
+    } else {
+        format!("{} 的 rating 预测：", prediction.contest_name)
+    };
+    for entry in entries {
+        msg.push_str(&format!(
+            "\n{} 第 {} 名，{} → {} ({:+})",
+            entry.handle,
+            entry.rank,
+            entry.old_rating,
+            entry.new_rating,
+            entry.new_rating - entry.old_rating
+        ));
+    }
+    if !prediction.official {
+        msg.push_str("\n预测仅供参考，最终以官方结果为准");
+    }
+    event.reply(msg);
+}
+
+/// 群里为所有绑定用户，私聊为自己
+async fn target_handles(event: &MsgEvent) -> anyhow::Result<Vec<String>> {
+    let Some(group_id) = event.group_id else {
+        return Ok(vec![get_cf_id(&IdOrText::At(event.user_id)).await?]);
+    };
+
+    bound_handles(Some(group_id)).await
+}
+
+async fn build_prediction(contest_id: i64) -> anyhow::Result<Prediction> {
+    match api::contest_rating_changes(contest_id).await {
+        Ok(changes) if !changes.is_empty() => {
+            let entries = changes
+                .iter()
+                .map(|change| {
+                    let entry = Entry {
+                        handle: change.handle.clone(),
+                        rank: change.rank,
+                        old_rating: change.old_rating,
+                        new_rating: change.new_rating,
+                    };
+                    (change.handle.to_lowercase(), entry)
+                })
+                .collect();
+            return Ok(Prediction {
+                contest_name: changes[0].contest_name.clone(),
+                official: true,
+                entries,
+            });
+        }
+        // 比赛进行中或者 rating 还没有更新
+        Ok(_) | Err(CfApiError::Failed(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let standings = api::contest_standings(contest_id, &[], false, None).await?;
+    if standings.contest.phase == "BEFORE" {
+        return Err(anyhow::anyhow!("比赛还没有开始"));
+    }
+
+    let ratings = api::rated_list(contest_id)
+        .await?
+        .iter()
+        .filter_map(|user| Some((user.handle.to_lowercase(), user.rating?)))
+        .collect::<HashMap<_, _>>();
+
+    // 只有单人参加的正式选手计入 rating
+    let rows = standings
+        .rows
+        .iter()
+        .filter(|row| row.party.participant_type == "CONTESTANT" && row.party.members.len() == 1)
+        .map(|row| {
+            let handle = &row.party.members[0].handle;
+            (row, ratings.get(&handle.to_lowercase()).copied())
+        })
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return Err(anyhow::anyhow!("榜单上还没有人"));
+    }
+
+    let counts = contest_counts(&rows).await;
+    // 没有 rating 的是第一次参赛，不知道场次的按老账号处理
+    let rows = rows
+        .into_iter()
+        .map(|(row, rating)| {
+            let contests = match rating {
+                Some(_) => counts
+                    .get(&row.party.members[0].handle.to_lowercase())
+                    .copied()
+                    .unwrap_or(NEW_ACCOUNT_OFFSETS.len()),
+                None => 0,
+            };
+            (row, rating.unwrap_or(0), contests)
+        })
+        .collect::<Vec<_>>();
+
+    let contestants = rows
+        .iter()
+        .map(|(row, rating, contests)| Contestant {
+            points: row.points,
+            penalty: row.penalty,
+            rating: rating + new_account_offset(*contests),
+        })
+        .collect::<Vec<_>>();
+    // 完整榜单有几万人，放到阻塞线程中计算
+    let deltas = tokio::task::spawn_blocking(move || calculate_deltas(&contestants)).await?;
+
+    let entries = rows
+        .iter()
+        .zip(deltas)
+        .map(|((row, rating, contests), delta)| {
+            let handle = row.party.members[0].handle.clone();
+            let entry = Entry {
+                handle: handle.clone(),
+                rank: row.rank,
+                old_rating: *rating,
+                new_rating: rating + displayed_delta(*contests, delta),
+            };
+            (handle.to_lowercase(), entry)
+        })
+        .collect();
+
+    Ok(Prediction {
+        contest_name: standings.contest.name.clone(),
+        official: false,
+        entries,
+    })
+}
+
+/// 榜单上有 rating 的绑定用户在这场比赛之前参加过的 rated 比赛场数，键为小写 handle
+async fn contest_counts(rows: &[(&api::RanklistRow, Option<i64>)]) -> HashMap<String, usize> {
+    let bound = match bound_handles(None).await {
+        Ok(handles) => handles,
+        Err(e) => {
+            warn!("获取绑定用户失败，不修正新账号的 rating: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    let mut counts = HashMap::new();
+    for (row, rating) in rows {
+        let handle = &row.party.members[0].handle;
+        if rating.is_none() || !bound.iter().any(|b| b.eq_ignore_ascii_case(handle)) {
+            continue;
+        }
+        match api::user_rating(handle).await {
+            Ok(history) => {
+                counts.insert(handle.to_lowercase(), history.len());
+            }
+            Err(e) => warn!("获取 {} 的 rating 记录失败: {}", handle, e),
+        }
+    }
+    counts
+}
+
+/// 所有 rating 对应的 seed：1 加上每个人赢这个 rating 的概率之和
+struct SeedTable {
+    lo: i64,
+    seeds: Vec<f64>,
+}
+
+impl SeedTable {
+    fn new(ratings: &[i64]) -> Self {
+        let mut counts = HashMap::new();
+        for &rating in ratings {
+            *counts.entry(rating).or_insert(0usize) += 1;
+        }
+        let min = *ratings.iter().min().unwrap();
+        let max = *ratings.iter().max().unwrap();
+        let lo = min.min(MIN_TARGET_RATING);
+        let hi = max.max(MAX_TARGET_RATING);
+
+        // 概率只和 rating 差有关，先按差值打表
+        let diff_lo = lo - max;
+        let probabilities = (diff_lo..=hi - min)
+            .map(|diff| elo_win_probability(0, diff))
+            .collect::<Vec<_>>();
+
+        let seeds = (lo..=hi)
+            .map(|rating| {
+                1.0 + counts
+                    .iter()
+                    .map(|(&other, &count)| {
+                        count as f64 * probabilities[(rating - other - diff_lo) as usize]
+                    })
+                    .sum::<f64>()
+            })
+            .collect();
+        Self { lo, seeds }
+    }
+
+    fn seed(&self, rating: i64) -> f64 {
+        self.seeds[(rating - self.lo) as usize]
+    }
+}
+
+/// 按 CF 的 rating 算法计算每个人的 rating 变化，顺序与 `contestants` 一致
+pub fn calculate_deltas(contestants: &[Contestant]) -> Vec<i64> {
+    let n = contestants.len();
+    if n == 0 {
+        return vec![];
+    }
+
+    // 分数高、罚时少的排在前面，并列时取最靠后的名次
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&contestants[a], &contestants[b]);
+        b.points
+            .total_cmp(&a.points)
+            .then(a.penalty.cmp(&b.penalty))
+    });
+    let mut ranks = vec![0.0; n];
+    let mut rank = n;
+    for pos in (0..n).rev() {
+        if pos + 1 < n {
+            let (cur, next) = (&contestants[order[pos]], &contestants[order[pos + 1]]);
+            if cur.points != next.points || cur.penalty != next.penalty {
+                rank = pos + 1;
+            }
+        }
+        ranks[order[pos]] = rank as f64;
+    }
+
+    let ratings = contestants.iter().map(|c| c.rating).collect::<Vec<_>>();
+    let table = SeedTable::new(&ratings);
+
+    let mut deltas = contestants
+        .iter()
+        .zip(ranks)
+        .map(|(contestant, rank)| {
+            let seed = table.seed(contestant.rating) - 0.5;
+            let mid_rank = (rank * seed).sqrt();
+
+            // seed 随 rating 单调递减，二分出期望排名恰好为 mid_rank 的 rating
+            let (mut left, mut right) = (MIN_TARGET_RATING, MAX_TARGET_RATING);
+            while right - left > 1 {
+                let mid = (left + right) / 2;
+                let seed = table.seed(mid) - elo_win_probability(contestant.rating, mid);
+                if seed < mid_rank {
+                    right = mid;
+                } else {
+                    left = mid;
+                }
+            }
+            (left - contestant.rating) / 2
+        })
+        .collect::<Vec<_>>();
+
+    // 所有人的 delta 之和略小于 0
+    let sum = deltas.iter().sum::<i64>();
+    let inc = -sum / n as i64 - 1;
+    deltas.iter_mut().for_each(|delta| *delta += inc);
+
+    // rating 最高的一部分人的 delta 之和约为 0，避免 rating 膨胀
+    let mut by_rating = (0..n).collect::<Vec<_>>();
+    by_rating.sort_by_key(|&i| std::cmp::Reverse(contestants[i].rating));
+    let top = n.min(4 * (n as f64).sqrt().round() as usize);
+    let sum_top = by_rating[..top].iter().map(|&i| deltas[i]).sum::<i64>();
+    let inc = (-sum_top / top as i64).clamp(-10, 0);
+    deltas.iter_mut().for_each(|delta| *delta += inc);
+
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contestant(points: f64, penalty: i64, rating: i64) -> Contestant {
+        Contestant {
+            points,
+            penalty,
+            rating,
+        }
+    }
+
+    #[test]
+    fn test_calculate_deltas() {
+        let contestants = (0..100)
+            .map(|i| contestant(100.0 - i as f64, 0, 1500))
+            .collect::<Vec<_>>();
+        let deltas = calculate_deltas(&contestants);
+
+        // rating 相同时名次越靠前涨得越多
+        assert!(deltas.windows(2).all(|w| w[0] >= w[1]));
+        assert!(deltas[0] > 0);
+        assert!(deltas[99] < 0);
+        assert!(deltas.iter().sum::<i64>() <= 0);
+
+        // 同样的名次，rating 低的人涨得更多
+        let mut contestants = contestants;
+        contestants[0].rating = 1200;
+        let deltas_low = calculate_deltas(&contestants);
+        assert!(deltas_low[0] > deltas[0]);
+    }
+
+    #[test]
+    fn test_new_account_offset() {
+        // 第一场比赛显示的 rating 从 0 开始，有效 rating 为 1400
+        assert_eq!(new_account_offset(0), 1400);
+        assert_eq!(displayed_delta(0, 0), 500);
+        assert_eq!(displayed_delta(0, -100), 400);
+        assert_eq!(displayed_delta(5, 10), 60);
+        assert_eq!(displayed_delta(6, 10), 10);
+        assert_eq!(new_account_offset(100), 0);
+        // 6 场之后显示的 rating 等于有效 rating
+        assert_eq!(
+            (0..NEW_ACCOUNT_OFFSETS.len())
+                .map(|k| displayed_delta(k, 0))
+                .sum::<i64>(),
+            1400
+        );
+    }
+
+    #[test]
+    fn test_ties_share_rank() {
+        let contestants = vec![
+            contestant(3.0, 10, 1500),
+            contestant(2.0, 20, 1500),
+            contestant(2.0, 20, 1500),
+            contestant(1.0, 30, 1500),
+        ];
+        let deltas = calculate_deltas(&contestants);
+        assert_eq!(deltas[1], deltas[2]);
+        assert!(deltas[0] > deltas[1]);
+        assert!(deltas[2] > deltas[3]);
+    }
+}
@@ -0,0 +1,504 @@
+use kovi::{Message, MsgEvent, bot::message::Segment, chrono::Utc, serde_json::json};
+
+use super::{bound_handles, virtual_contest::user_cf_id};
+use crate::{
+    CONFIG,
+    duel::{
+        filter::{ProblemFilter, parse_num_range},
+        problem::{
+            Problem, filter_context, get_problems, parse_problem_id, problem_url, refresh_problems,
+        },
+        submission::get_solved_set,
+        tags::count_by_rating,
+    },
+    sql::duel::{problem::get_problemset_fetched_at, submission::get_problem_attempts},
+    utils::is_bot_admin,
+};
+
+/// /cf search 每页的题目数
+const SEARCH_PAGE_SIZE: usize = 10;
+/// 关键词和题目名中单词的相似度超过这个值才算匹配，和指令纠错一致
+const SEARCH_MIN_SIMILARITY: f64 = 0.6;
+
+/// /cf tags 统计时使用的难度段
+const TAG_RATING_BANDS: &[(i64, i64)] = &[
+    (800, 1100),
+    (1200, 1500),
+    (1600, 1900),
+    (2000, 2300),
+    (2400, 3500),
+];
+
+/// 查看一道题的信息，以及群友（私聊时为所有绑定用户）的通过情况
+///
+/// 用法：/cf problem <题号> [--tags/-t]，标签默认隐藏，加上选项后显示
+pub async fn problem(event: &MsgEvent, args: &[String]) {
+    let Some((contest_id, index)) = args.get(2).and_then(|id| parse_problem_id(id)) else {
+        event.reply("用法：/cf problem <题号> [--tags/-t]，例如 /cf problem 1900D");
+        return;
+    };
+    let show_tags = args[3..].iter().any(|arg| arg == "--tags" || arg == "-t");
+
+    let problems = match get_problems().await {
+        Ok(problems) => problems,
+        Err(e) => {
+            event.reply(format!("获取题库失败: {}", e));
+            return;
+        }
+    };
+    let Some(problem) = problems
+        .iter()
+        .find(|p| p.contest_id == contest_id && p.index == index)
+    else {
+        event.reply(format!("题库中没有 {}{}", contest_id, index));
+        return;
+    };
+
+    let mut msg = format!("{}{} {}", problem.contest_id, problem.index, problem.name);
+    msg.push_str(&format!("\n难度：{}", problem.rating_text()));
+    if let Some(solved_count) = problem.solved_count {
+        msg.push_str(&format!("\n通过人数：{}", solved_count));
+    }
+    if problem.tags.is_empty() {
+        msg.push_str("\n标签：无");
+    } else if show_tags {
+        msg.push_str(&format!("\n标签：{}", problem.tags.join(", ")));
+    } else {
+        msg.push_str("\n标签：已隐藏，加上 -t 显示");
+    }
+    msg.push_str(&format!(
+        "\n链接：{}",
+        problem_url(problem.contest_id, &problem.index)
+    ));
+
+    // 只看本地缓存的提交，不去请求 CF
+    let result = async {
+        let handles = bound_handles(event.group_id).await?;
+        let attempts = get_problem_attempts(contest_id, &index).await?;
+        anyhow::Ok(
+            attempts
+                .into_iter()
+                .filter(|(handle, _)| handles.iter().any(|h| h.eq_ignore_ascii_case(handle)))
+                .collect::<Vec<_>>(),
+        )
+    }
+    .await;
+
+    let who = if event.group_id.is_some() {
+        "本群"
+    } else {
+        "绑定用户中"
+    };
+    match result {
+        Ok(attempts) if attempts.is_empty() => {
+            msg.push_str(&format!("\n{}还没有人做过这道题", who));
+        }
+        Ok(attempts) => {
+            let (solved, tried): (Vec<_>, Vec<_>) =
+                attempts.into_iter().partition(|(_, solved)| *solved);
+            if !solved.is_empty() {
+                let solved = solved.into_iter().map(|(h, _)| h).collect::<Vec<_>>();
+                msg.push_str(&format!("\n{}通过：{}", who, solved.join(", ")));
+            }
+            if !tried.is_empty() {
+                let tried = tried.into_iter().map(|(h, _)| h).collect::<Vec<_>>();
+                msg.push_str(&format!("\n{}尝试过：{}", who, tried.join(", ")));
+            }
+        }
+        // 没有人绑定时不显示通过情况
+        Err(_) => {}
+    }
+
+    event.reply(msg);
+}
+
+/// /cf search 的参数
+#[derive(Debug, Default, PartialEq)]
+struct SearchArgs {
+    keywords: Vec<String>,
+    filter: Vec<String>,
+    exclude_solved: bool,
+    page: usize,
+}
+
+/// 带有筛选语法的参数（难度、带冒号的条件、括号、或、非）作为筛选条件，其余作为关键词
+///
+/// 单独的标签名也可能是题目名中的单词，所以标签要写成 `tag:dp`
+fn parse_search_args(args: &[String]) -> SearchArgs {
+    let mut search = SearchArgs {
+        page: 1,
+        ..Default::default()
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--exclude-solved" || arg == "-e" {
+            search.exclude_solved = true;
+        } else if (arg == "--page" || arg == "-p")
+            && let Some(page) = args.get(i + 1).and_then(|p| p.parse::<usize>().ok())
+        {
+            search.page = page.max(1);
+            i += 1;
+        } else if arg.contains([':', '(', ')', '（', '）', '|', '!', '！'])
+            || parse_num_range(arg).is_some()
+        {
+            search.filter.push(arg.clone());
+        } else {
+            search.keywords.push(arg.to_lowercase());
+        }
+        i += 1;
+    }
+    search
+}
+
+/// 题目名和关键词的匹配程度，0 表示不匹配
+///
+/// 题目名包含全部关键词时为 1，否则每个关键词取和题目名中最相近的单词的相似度，
+/// 有一个关键词不够相似就不匹配，匹配时取平均
+fn name_score(keywords: &[String], name: &str) -> f64 {
+    let name = name.to_lowercase();
+    if keywords
+        .iter()
+        .all(|keyword| name.contains(keyword.as_str()))
+    {
+        return 1.0;
+    }
+
+    let words = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let mut total = 0.0;
+    for keyword in keywords {
+        let best = words
+            .iter()
+            .map(|word| strsim::normalized_damerau_levenshtein(keyword, word))
+            .fold(0.0, f64::max);
+        if best <= SEARCH_MIN_SIMILARITY {
+            return 0.0;
+        }
+        total += best;
+    }
+    total / keywords.len() as f64 * 0.99
+}
+
+/// 按题目名搜索题目
+///
+/// 用法：/cf search <关键词...> [筛选条件...] [--exclude-solved/-e] [--page/-p 页码]
+pub async fn search(event: &MsgEvent, args: &[String]) {
+    let search = parse_search_args(args.get(2..).unwrap_or_default());
+    if search.keywords.is_empty() && search.filter.is_empty() {
+        event.reply("用法：/cf search <关键词...> [筛选条件...] [-e] [-p 页码]");
+        return;
+    }
+
+    let filter = match ProblemFilter::parse(&search.filter) {
+        Ok(filter) => filter,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+    let ctx = match filter_context(&filter, event.user_id).await {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    let solved = if search.exclude_solved {
+        let cf_id = match user_cf_id(event.user_id).await {
+            Ok(cf_id) => cf_id,
+            Err(e) => {
+                event.reply(e.to_string());
+                return;
+            }
+        };
+        match get_solved_set(&cf_id).await {
+            Ok(solved) => solved,
+            Err(e) => {
+                event.reply(format!("获取做题记录失败: {}", e));
+                return;
+            }
+        }
+    } else {
+        Default::default()
+    };
+
+    let problems = match get_problems().await {
+        Ok(problems) => problems,
+        Err(e) => {
+            event.reply(format!("获取题库失败: {}", e));
+            return;
+        }
+    };
+
+    // 没有关键词时只按筛选条件，新题在前
+    let mut results: Vec<(f64, &Problem)> = problems
+        .iter()
+        .filter(|p| !solved.contains(&p.key()))
+        .filter(|p| filter.matches(p, &ctx))
+        .filter_map(|p| {
+            let score = if search.keywords.is_empty() {
+                1.0
+            } else {
+                name_score(&search.keywords, &p.name)
+            };
+            (score > 0.0).then_some((score, p.as_ref()))
+        })
+        .collect();
+    results.sort_by(|(a, p), (b, q)| {
+        b.total_cmp(a)
+            .then(q.contest_id.cmp(&p.contest_id))
+            .then(p.index.cmp(&q.index))
+    });
+
+    if results.is_empty() {
+        event.reply("没有找到符合条件的题目");
+        return;
+    }
+
+    let pages = results.len().div_ceil(SEARCH_PAGE_SIZE);
+    let page = search.page.min(pages);
+    let mut msg = format!(
+        "找到 {} 道题目（第 {}/{} 页）：",
+        results.len(),
+        page,
+        pages
+    );
+    for (i, (_, problem)) in results
+        .iter()
+        .enumerate()
+        .skip((page - 1) * SEARCH_PAGE_SIZE)
+        .take(SEARCH_PAGE_SIZE)
+    {
+        msg.push_str(&format!(
+            "\n{}. {}{} {} ({}) {}",
+            i + 1,
+            problem.contest_id,
+            problem.index,
+            problem.name,
+            problem.rating_text(),
+            problem_url(problem.contest_id, &problem.index)
+        ));
+    }
+    if page < pages {
+        msg.push_str(&format!("\n使用 -p {} 查看下一页", page + 1));
+    }
+    event.reply(msg);
+}
+
+/// 强制从 CF 刷新题库（仅 bot 管理员）
+pub async fn refresh(event: &MsgEvent) {
+    if !is_bot_admin(event.user_id) {
+        event.reply("只有管理员可以刷新题库");
+        return;
+    }
+
+    event.reply("正在刷新题库");
+
+    match refresh_problems().await {
+        Ok(diff) => event.reply(format!(
+            "刷新题库成功：新增 {} 道，更新 {} 道，移除 {} 道",
+            diff.added, diff.updated, diff.removed
+        )),
+        Err(e) => event.reply(format!("刷新题库失败: {}", e)),
+    }
+}
+
+/// 查询缓存题库的大小和更新时间
+pub async fn status(event: &MsgEvent) {
+    let problems = match get_problems().await {
+        Ok(problems) => problems,
+        Err(e) => {
+            event.reply(format!("获取题库失败: {}", e));
+            return;
+        }
+    };
+
+    let age = match get_problemset_fetched_at().await {
+        Ok(Some(time)) => {
+            let duration = Utc::now().signed_duration_since(time);
+            format!(
+                "{}（{}d {}h {}m 前）",
+                time.with_timezone(&kovi::chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                duration.num_days(),
+                duration.num_hours() % 24,
+                duration.num_minutes() % 60
+            )
+        }
+        Ok(None) => "从未更新".to_string(),
+        Err(e) => {
+            event.reply(format!("获取题库更新时间失败: {}", e));
+            return;
+        }
+    };
+
+    let unrated = problems
+        .iter()
+        .filter(|problem| problem.rating.is_none())
+        .count();
+    let estimated = problems
+        .iter()
+        .filter(|problem| problem.estimated_rating.is_some())
+        .count();
+
+    event.reply(format!(
+        "题库共有 {} 道题目，其中 {} 道没有官方难度，已估计 {} 道\n上次更新：{}",
+        problems.len(),
+        unrated,
+        estimated,
+        age
+    ));
+}
+
+/// 按标签统计题目数量
+///
+/// 用法：/cf tags [rating|rating 范围]
+/// 不带参数时按难度段统计，带参数时只统计该难度内的题目
+pub async fn tags(event: &MsgEvent, args: &[String]) {
+    let range = match args.get(2) {
+        Some(arg) => match parse_num_range(arg) {
+            Some(range) => Some(range),
+            None => {
+                event.reply("参数非法：需要 rating 或 rating 范围，例如 1600 或 1600-1900");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let problems = match get_problems().await {
+        Ok(problems) => problems,
+        Err(e) => {
+            event.reply(format!("获取题库失败: {}", e));
+            return;
+        }
+    };
+
+    let counts = count_by_rating(&problems);
+
+    let mut result = match range {
+        Some((lo, hi)) if lo == hi => format!("rating {} 的各标签题目数量：\n", lo),
+        Some((lo, hi)) => format!("rating {}-{} 的各标签题目数量：\n", lo, hi),
+        None => format!(
+            "各标签题目数量（{}）：\n",
+            TAG_RATING_BANDS
+                .iter()
+                .map(|(lo, hi)| format!("{}-{}", lo, hi))
+                .collect::<Vec<_>>()
+                .join(" / ")
+        ),
+    };
+
+    for (tag, by_rating) in counts.iter() {
+        let count_in = |lo: i64, hi: i64| -> usize {
+            by_rating
+                .iter()
+                .filter(|(rating, _)| rating.is_some_and(|r| (lo..=hi).contains(&r)))
+                .map(|(_, count)| count)
+                .sum()
+        };
+        let total = by_rating.values().sum::<usize>();
+
+        match range {
+            Some((lo, hi)) => {
+                let count = count_in(lo, hi);
+                if count > 0 {
+                    result.push_str(&format!("{}: {}\n", tag, count));
+                }
+            }
+            None => {
+                let bands = TAG_RATING_BANDS
+                    .iter()
+                    .map(|&(lo, hi)| count_in(lo, hi).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                result.push_str(&format!("{} ({}): {}\n", tag, total, bands));
+            }
+        }
+    }
+
+    if let Some(config) = CONFIG.get() {
+        let mut aliases = config
+            .tag_alias
+            .iter()
+            .map(|(alias, tag)| format!("{} -> {}", alias, tag))
+            .collect::<Vec<_>>();
+        aliases.sort();
+        result.push_str(&format!("\n标签别名：\n{}", aliases.join("\n")));
+    }
+
+    let seg = Segment::new(
+        "node",
+        json!({
+            "user_id": event.self_id,
+            "nickname": "呵呵哒",
+            "content": [{
+                "type": "text",
+                "data": {
+                    "text": result
+                }
+            }]
+        }),
+    );
+
+    event.reply(Message::from(vec![seg]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_args() {
+        let args = [
+            "Game",
+            "on",
+            "Tree",
+            "1600-1900",
+            "idx:A-C",
+            "-e",
+            "-p",
+            "2",
+        ]
+        .map(String::from);
+        assert_eq!(
+            parse_search_args(&args),
+            SearchArgs {
+                keywords: vec!["game".to_string(), "on".to_string(), "tree".to_string()],
+                filter: vec!["1600-1900".to_string(), "idx:A-C".to_string()],
+                exclude_solved: true,
+                page: 2,
+            }
+        );
+
+        let args = ["(dp", "|", "greedy)", "!math"].map(String::from);
+        let search = parse_search_args(&args);
+        assert!(search.keywords.is_empty());
+        assert_eq!(search.filter, args);
+        assert_eq!(search.page, 1);
+
+        // 和标签同名的单词按题目名搜索
+        let args = ["Strings", "tag:greedy"].map(String::from);
+        let search = parse_search_args(&args);
+        assert_eq!(search.keywords, vec!["strings".to_string()]);
+        assert_eq!(search.filter, vec!["tag:greedy".to_string()]);
+    }
+
+    #[test]
+    fn test_name_score() {
+        let keywords = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(name_score(&keywords("tree"), "Game on Tree"), 1.0);
+        assert_eq!(name_score(&keywords("game tree"), "Game on Tree"), 1.0);
+
+        // 拼错一个字母也能找到，但排在完全匹配后面
+        let typo = name_score(&keywords("gmae"), "Game on Tree");
+        assert!(typo > 0.0 && typo < 1.0);
+
+        assert_eq!(name_score(&keywords("permutation"), "Game on Tree"), 0.0);
+    }
+}
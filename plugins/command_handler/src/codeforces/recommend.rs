@@ -1,13 +1,24 @@
 use anyhow::Result;
-use kovi::MsgEvent;
+use kovi::{
+    MsgEvent,
+    chrono::{DateTime, Utc},
+    log::{error, warn},
+};
 use rand::Rng;
 use rand::seq::SliceRandom;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use super::virtual_contest::format_local;
 use crate::{
-    duel::{problem::get_problems, submission::get_recent_submissions},
-    utils::{IdOrText, get_user_rating},
+    duel::{
+        curation::{Blacklist, get_blacklist},
+        filter::ProblemFilter,
+        problem::{Problem, filter_context, get_problems, problem_url},
+        submission::{Submission, get_recent_submissions},
+    },
+    sql::{self, recommendation::CommitRecommendationExt, utils::Commit},
+    utils::{IdOrText, elo_win_probability, get_user_rating},
 };
 
 // 常量定义
@@ -15,8 +26,25 @@ const MAX_RECOMMEND_COUNT: usize = 10;
 const MIN_RATING: i64 = 800;
 const MAX_RATING: i64 = 3500;
 const RATING_STEP: i64 = 100;
+/// 标签掌握度的半衰期，越早的提交权重越低
+const MASTERY_HALF_LIFE_DAYS: f64 = 180.0;
+/// 掌握度低于这个值的标签会在推荐理由中指出
+const WEAK_MASTERY: f64 = 0.6;
+/// 最近这么多天内推荐过的题目不再推荐
+const RECENT_EXCLUDE_DAYS: i64 = 30;
+/// 推荐后这么多天内通过才算推荐成功，用于统计通过率
+const SOLVE_WINDOW_DAYS: i64 = 7;
+/// 每个难度只用最近这么多条有结果的记录调整难度
+const MAX_FEEDBACK_SAMPLES: usize = 30;
+/// 有结果的记录少于这个数时不调整难度
+const MIN_FEEDBACK_SAMPLES: usize = 5;
+/// 根据推荐记录调整的 rating 的上限
+const MAX_RATING_OFFSET: i64 = 300;
+/// /cf recommend history 列出的条数
+const HISTORY_LIMIT: usize = 10;
+const DAY_SECONDS: i64 = 24 * 60 * 60;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RecommendDifficulty {
     Easy,      // 80% 概率解出
     Moderate,  // 50% 概率解出
@@ -24,7 +52,7 @@ pub enum RecommendDifficulty {
 }
 
 impl RecommendDifficulty {
-    fn from_str(s: &str) -> Option<Self> {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "easy" | "简单" => Some(Self::Easy),
             "moderate" | "medium" | "中等" => Some(Self::Moderate),
@@ -33,7 +61,14 @@ impl RecommendDifficulty {
         }
     }
 
-    #[allow(dead_code)]
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Moderate => "moderate",
+            Self::Difficult => "difficult",
+        }
+    }
+
     fn get_target_solve_probability(&self) -> f64 {
         match self {
             Self::Easy => 0.8,
@@ -61,16 +96,141 @@ impl RecommendDifficulty {
     }
 }
 
+/// 一个标签最近的做题情况，每道题按最后一次提交的时间衰减加权
+#[derive(Debug, Clone, Copy, Default)]
+struct TagMastery {
+    solved: f64,
+    attempted: f64,
+}
+
+impl TagMastery {
+    /// 加权通过率，加上先验避免只做过一两题时过于极端，没做过时为 0.5
+    fn ratio(&self) -> f64 {
+        (self.solved + 1.0) / (self.attempted + 2.0)
+    }
+}
+
+/// 选中的题目和推荐理由
+struct Pick<'a> {
+    problem: &'a Arc<Problem>,
+    /// 预计通过概率，题目没有难度时为 None
+    probability: Option<f64>,
+    /// 题目标签中掌握度最低的一个
+    weakest_tag: Option<(&'a str, TagMastery)>,
+}
+
+impl<'a> Pick<'a> {
+    fn new(
+        problem: &'a Arc<Problem>,
+        mastery: &HashMap<String, TagMastery>,
+        rating: i64,
+        estimated: bool,
+    ) -> Self {
+        let weakest_tag = problem
+            .tags
+            .iter()
+            .filter(|tag| !tag.starts_with('*'))
+            .map(|tag| {
+                let tag_mastery = mastery.get(tag).copied().unwrap_or_default();
+                (tag.as_str(), tag_mastery)
+            })
+            .min_by(|(_, a), (_, b)| a.ratio().total_cmp(&b.ratio()));
+
+        Self {
+            problem,
+            probability: problem
+                .rating_with_estimate(estimated)
+                .map(|problem_rating| elo_win_probability(rating, problem_rating)),
+            weakest_tag,
+        }
+    }
+
+    /// 通过概率越接近目标权重越高，包含掌握度低的标签时权重更高
+    fn weight(&self, target_probability: f64) -> f64 {
+        let fit = self.probability.map_or(0.1, |p| {
+            let z = (p - target_probability) / 0.15;
+            (-z * z / 2.0).exp()
+        });
+        let weakness = self
+            .weakest_tag
+            .map_or(0.0, |(_, tag_mastery)| 1.0 - tag_mastery.ratio());
+        fit * (1.0 + 2.0 * weakness)
+    }
+
+    fn reason(&self) -> String {
+        let mut reasons = Vec::new();
+        if let Some(p) = self.probability {
+            reasons.push(format!("预计通过率 {:.0}%", p * 100.0));
+        }
+        match self.weakest_tag {
+            Some((tag, tag_mastery)) if tag_mastery.attempted < 0.5 => {
+                reasons.push(format!("最近很少做 {} 的题", tag));
+            }
+            Some((tag, tag_mastery)) if tag_mastery.ratio() < WEAK_MASTERY => {
+                reasons.push(format!(
+                    "{} 最近的通过率只有 {:.0}%",
+                    tag,
+                    tag_mastery.ratio() * 100.0
+                ));
+            }
+            _ => {}
+        }
+        reasons.join("，")
+    }
+}
+
+/// 一次推荐的记录
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+    pub id: i64,
+    pub qq: i64,
+    pub cf_id: String,
+    pub contest_id: i64,
+    pub index: String,
+    pub problem_rating: Option<i64>,
+    pub user_rating: i64,
+    /// 按难度推荐时的难度，指定 rating 时为 None
+    pub difficulty: Option<RecommendDifficulty>,
+    /// 推荐时的指令参数
+    pub params: String,
+    pub recommended_at: i64,
+    pub solved_at: Option<i64>,
+}
+
+impl Recommendation {
+    /// 推荐是否成功：期限内通过为 Some(true)，超过期限还没通过为 Some(false)，还没到期限为 None
+    fn outcome(&self, now: i64) -> Option<bool> {
+        let deadline = self.recommended_at + SOLVE_WINDOW_DAYS * DAY_SECONDS;
+        match self.solved_at {
+            Some(solved_at) => Some(solved_at <= deadline),
+            None if now > deadline => Some(false),
+            None => None,
+        }
+    }
+}
+
 // 解析命令参数结构
 struct CommandArgs {
     difficulty: RecommendDifficulty,
     exclude_solved: bool,
     count: usize,
     specific_rating: Option<i64>,
+    filter: ProblemFilter,
 }
 
 pub async fn recommend(event: &MsgEvent, args: &[String]) {
-    let cmd_args = parse_args(args);
+    if args.get(2).is_some_and(|arg| arg == "history") {
+        history(event).await;
+        return;
+    }
+
+    let cmd_args = match parse_args(args) {
+        Ok(cmd_args) => cmd_args,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
     let user = IdOrText::At(event.user_id);
 
     // 获取用户CF ID和rating
@@ -82,12 +242,28 @@ pub async fn recommend(event: &MsgEvent, args: &[String]) {
         }
     };
 
+    // 根据之前推荐的题目的通过情况调整难度，推荐记录读不到时不调整，也不排除最近推荐过的题
+    let now = Utc::now().timestamp();
+    let records = match load_history(event.user_id, &cf_id).await {
+        Ok(records) => records,
+        Err(e) => {
+            warn!("获取 {} 的推荐记录失败: {}", cf_id, e);
+            Vec::new()
+        }
+    };
+    let offset = rating_offset(&records, &cmd_args.difficulty, now);
+    let recent = records
+        .iter()
+        .filter(|record| now - record.recommended_at < RECENT_EXCLUDE_DAYS * DAY_SECONDS)
+        .map(|record| (record.contest_id, record.index.clone()))
+        .collect::<HashSet<_>>();
+
     // 确定推荐范围并通知用户
-    let (min_rating, max_rating, filter_msg) = get_rating_range(&cmd_args, rating);
+    let (min_rating, max_rating, filter_msg) = get_rating_range(&cmd_args, rating, offset);
     event.reply(format!("正在为您推荐{}", filter_msg));
 
     // 获取用户提交数据和题库
-    let (tag_weight, solved_problems) =
+    let (mastery, solved_problems) =
         get_user_submission_data(&cf_id, cmd_args.exclude_solved).await;
 
     let problems = match get_problems().await {
@@ -98,8 +274,35 @@ pub async fn recommend(event: &MsgEvent, args: &[String]) {
         }
     };
 
+    let ctx = match filter_context(&cmd_args.filter, event.user_id).await {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    let blacklist = match get_blacklist().await {
+        Ok(blacklist) => blacklist,
+        Err(e) => {
+            event.reply(format!("获取题目黑名单失败: {}", e));
+            return;
+        }
+    };
+
     // 过滤候选题目
-    let candidates = filter_candidate_problems(&problems, min_rating, max_rating, &solved_problems);
+    let candidates = filter_candidate_problems(
+        &problems,
+        min_rating,
+        max_rating,
+        &solved_problems,
+        &blacklist,
+        cmd_args.filter.uses_estimated(),
+    )
+    .into_iter()
+    .filter(|p| !recent.contains(&p.key()))
+    .filter(|p| cmd_args.filter.matches(p, &ctx))
+    .collect::<Vec<_>>();
 
     if candidates.is_empty() {
         let msg = if cmd_args.exclude_solved {
@@ -112,19 +315,61 @@ pub async fn recommend(event: &MsgEvent, args: &[String]) {
     }
 
     // 选择题目
-    let final_problems = select_problems(&candidates, &tag_weight, &cmd_args, rating);
+    let picks = select_problems(&candidates, &mastery, &cmd_args, rating + offset);
+
+    if let Err(e) = save_history(event.user_id, &cf_id, rating, &cmd_args, args, &picks).await {
+        error!("保存 {} 的推荐记录失败: {}", cf_id, e);
+    }
 
     // 发送结果
-    let msg = format_recommendation_output(&final_problems, &cmd_args);
+    let msg = format_recommendation_output(&picks, &cmd_args);
     event.reply(msg);
 }
 
-// 解析命令行参数
-fn parse_args(args: &[String]) -> CommandArgs {
+/// 按难度为用户挑选题目，排除已通过的题目，并偏向用户掌握度低的标签
+///
+/// 供每日任务等不经过 /cf recommend 指令的功能复用
+pub(crate) async fn recommend_problems(
+    cf_id: &str,
+    rating: i64,
+    difficulty: RecommendDifficulty,
+    count: usize,
+) -> Result<Vec<Arc<crate::duel::problem::Problem>>> {
+    let cmd_args = CommandArgs {
+        difficulty,
+        exclude_solved: true,
+        count,
+        specific_rating: None,
+        filter: ProblemFilter::default(),
+    };
+
+    let (min_rating, max_rating, _) = get_rating_range(&cmd_args, rating, 0);
+    let (mastery, solved_problems) = get_user_submission_data(cf_id, true).await;
+    let problems = get_problems().await?;
+    let blacklist = get_blacklist().await?;
+
+    let candidates = filter_candidate_problems(
+        &problems,
+        min_rating,
+        max_rating,
+        &solved_problems,
+        &blacklist,
+        false,
+    );
+
+    Ok(select_problems(&candidates, &mastery, &cmd_args, rating)
+        .into_iter()
+        .map(|pick| pick.problem.clone())
+        .collect())
+}
+
+// 解析命令行参数，无法识别的参数作为题目筛选条件
+fn parse_args(args: &[String]) -> Result<CommandArgs> {
     let mut difficulty = RecommendDifficulty::Moderate;
     let mut exclude_solved = false;
     let mut count = 1;
     let mut specific_rating = None;
+    let mut filter_args = Vec::new();
 
     let mut i = 2;
     while i < args.len() {
@@ -147,46 +392,48 @@ fn parse_args(args: &[String]) -> CommandArgs {
         {
             specific_rating = Some(r);
             i += 1;
+        } else {
+            filter_args.push(arg.as_str());
         }
 
         i += 1;
     }
 
-    CommandArgs {
+    Ok(CommandArgs {
         difficulty,
         exclude_solved,
         count,
         specific_rating,
-    }
+        filter: ProblemFilter::parse(&filter_args)?,
+    })
 }
 
 // 选择推荐题目
 fn select_problems<'a>(
-    candidates: &[&'a Arc<crate::duel::problem::Problem>],
-    tag_weight: &HashMap<String, usize>,
+    candidates: &[&'a Arc<Problem>],
+    mastery: &HashMap<String, TagMastery>,
     cmd_args: &CommandArgs,
     rating: i64,
-) -> Vec<&'a Arc<crate::duel::problem::Problem>> {
+) -> Vec<Pick<'a>> {
     let mut rng = rand::rng();
-    if cmd_args.specific_rating.is_some() {
+    let estimated = cmd_args.filter.uses_estimated();
+    let picks = candidates
+        .iter()
+        .map(|problem| Pick::new(problem, mastery, rating, estimated))
+        .collect::<Vec<_>>();
+
+    if cmd_args.specific_rating.is_some() || cmd_args.filter.has_rating() {
         // 指定rating：随机选择
-        let mut shuffled = candidates.to_vec();
+        let mut shuffled = picks;
         shuffled.shuffle(&mut rng);
-        shuffled.into_iter().take(cmd_args.count).collect()
+        shuffled.truncate(cmd_args.count);
+        shuffled
     } else {
-        // 根据难度：加权随机选择
-        let target_rating = match cmd_args.difficulty {
-            RecommendDifficulty::Easy => rating - 250,
-            RecommendDifficulty::Moderate => rating,
-            RecommendDifficulty::Difficult => rating + 350,
-        };
-
-        let weights: Vec<f64> = candidates
-            .iter()
-            .map(|p| calculate_problem_weight(p, tag_weight, target_rating))
-            .collect();
+        // 根据难度：按通过概率和标签掌握度加权随机选择
+        let target = cmd_args.difficulty.get_target_solve_probability();
+        let weights: Vec<f64> = picks.iter().map(|pick| pick.weight(target)).collect();
 
-        weighted_random_select(candidates, &weights, cmd_args.count, &mut rng)
+        weighted_random_select(picks, &weights, cmd_args.count, &mut rng)
     }
 }
 
@@ -196,13 +443,15 @@ fn filter_candidate_problems<'a>(
     min_rating: i64,
     max_rating: i64,
     solved_problems: &Option<HashSet<(i64, String)>>,
+    blacklist: &Blacklist,
+    estimated: bool,
 ) -> Vec<&'a Arc<crate::duel::problem::Problem>> {
     problems
         .iter()
         .filter(|p| {
             // 检查rating是否在范围内
             let rating_in_range = p
-                .rating
+                .rating_with_estimate(estimated)
                 .map(|r| (min_rating..=max_rating).contains(&r))
                 .unwrap_or(false);
 
@@ -215,87 +464,72 @@ fn filter_candidate_problems<'a>(
             // 检查是否为特殊题目
             let not_special = !p.tags.iter().any(|tag| tag == "*special");
 
-            rating_in_range && not_solved && not_special
+            rating_in_range && not_solved && not_special && !blacklist.contains(p)
         })
         .collect()
 }
 
 // 格式化推荐结果输出
-fn format_recommendation_output(
-    problems: &[&Arc<crate::duel::problem::Problem>],
-    cmd_args: &CommandArgs,
-) -> String {
-    match problems {
-        [problem] => format_single_problem(problem),
-        problems => format_multiple_problems(problems, cmd_args),
+fn format_recommendation_output(picks: &[Pick], cmd_args: &CommandArgs) -> String {
+    match picks {
+        [pick] => format_single_problem(pick),
+        picks => format_multiple_problems(picks, cmd_args),
     }
 }
 
 // 格式化单个题目
-fn format_single_problem(problem: &Arc<crate::duel::problem::Problem>) -> String {
-    let link = format!(
-        "https://codeforces.com/problemset/problem/{}/{}",
-        problem.contest_id, problem.index
-    );
+fn format_single_problem(pick: &Pick) -> String {
+    let problem = pick.problem;
+    let link = problem_url(problem.contest_id, &problem.index);
     format!(
-        "推荐题目：\n题目：{} {}\n难度：{}\n标签：{}\n链接：{}",
+        "推荐题目：\n题目：{} {}\n难度：{}\n标签：{}\n理由：{}\n链接：{}",
         problem.contest_id,
         problem.index,
-        problem.rating.unwrap_or(0),
+        problem.rating_text(),
         problem.tags.join(", "),
+        pick.reason(),
         link
     )
 }
 
 // 格式化多个题目
-fn format_multiple_problems(
-    problems: &[&Arc<crate::duel::problem::Problem>],
-    cmd_args: &CommandArgs,
-) -> String {
+fn format_multiple_problems(picks: &[Pick], cmd_args: &CommandArgs) -> String {
     let title = match cmd_args.specific_rating {
         Some(r) => format!("推荐的rating {} 题目：\n", r),
-        None => format!("推荐的{:?}难度题目：\n", cmd_args.difficulty),
+        None => format!(
+            "推荐的{:?}难度题目（目标通过率 {:.0}%）：\n",
+            cmd_args.difficulty,
+            cmd_args.difficulty.get_target_solve_probability() * 100.0
+        ),
     };
 
-    problems
-        .iter()
-        .enumerate()
-        .fold(title, |mut acc, (i, problem)| {
-            let link = format!(
-                "https://codeforces.com/problemset/problem/{}/{}",
-                problem.contest_id, problem.index
-            );
-            acc.push_str(&format!(
-                "{}. {} {} (难度: {})\n   标签: {}\n   链接: {}\n\n",
-                i + 1,
-                problem.contest_id,
-                problem.index,
-                problem.rating.unwrap_or(0),
-                problem.tags.join(", "),
-                link
-            ));
-            acc
-        })
+    picks.iter().enumerate().fold(title, |mut acc, (i, pick)| {
+        let problem = pick.problem;
+        let link = problem_url(problem.contest_id, &problem.index);
+        acc.push_str(&format!(
+            "{}. {} {} (难度: {})\n   标签: {}\n   理由: {}\n   链接: {}\n\n",
+            i + 1,
+            problem.contest_id,
+            problem.index,
+            problem.rating_text(),
+            problem.tags.join(", "),
+            pick.reason(),
+            link
+        ));
+        acc
+    })
 }
 
 // 获取用户提交记录相关的数据
 async fn get_user_submission_data(
     cf_id: &str,
     exclude_solved: bool,
-) -> (HashMap<String, usize>, Option<HashSet<(i64, String)>>) {
+) -> (HashMap<String, TagMastery>, Option<HashSet<(i64, String)>>) {
     let Ok(submissions) = get_recent_submissions(cf_id).await else {
         return (HashMap::new(), None);
     };
 
-    // 统计用户未通过题目的 tag 权重
-    let tag_weight = submissions
-        .iter()
-        .filter(|s| !s.is_accepted())
-        .flat_map(|s| &s.problem.tags)
-        .fold(HashMap::new(), |mut acc, tag| {
-            *acc.entry(tag.clone()).or_insert(0) += 1;
-            acc
-        });
+    let mastery = tag_mastery(&submissions, Utc::now().timestamp());
 
     // 如果需要排除已解决题目，处理已解决集合
     let solved_problems = if exclude_solved {
@@ -310,11 +544,42 @@ async fn get_user_submission_data(
         None
     };
 
-    (tag_weight, solved_problems)
+    (mastery, solved_problems)
+}
+
+/// 按标签统计做过的题目中通过的比例，每道题只算一次，越近的题权重越高
+fn tag_mastery(submissions: &[Submission], now: i64) -> HashMap<String, TagMastery> {
+    // 每道题最后一次提交的时间以及是否通过过
+    let mut problems: HashMap<(i64, String), (&Problem, i64, bool)> = HashMap::new();
+    for submission in submissions {
+        let entry = problems.entry(submission.problem.key()).or_insert((
+            &submission.problem,
+            submission.creation_time_seconds,
+            false,
+        ));
+        entry.1 = entry.1.max(submission.creation_time_seconds);
+        entry.2 |= submission.is_accepted();
+    }
+
+    let mut mastery: HashMap<String, TagMastery> = HashMap::new();
+    for (problem, time, solved) in problems.into_values() {
+        let age_days = (now - time).max(0) as f64 / (24.0 * 60.0 * 60.0);
+        let weight = 0.5f64.powf(age_days / MASTERY_HALF_LIFE_DAYS);
+        for tag in problem.tags.iter() {
+            let tag_mastery = mastery.entry(tag.clone()).or_default();
+            tag_mastery.attempted += weight;
+            if solved {
+                tag_mastery.solved += weight;
+            }
+        }
+    }
+    mastery
 }
 
 // 根据难度或指定rating获取过滤范围
-fn get_rating_range(cmd_args: &CommandArgs, user_rating: i64) -> (i64, i64, String) {
+//
+// 按难度推荐时 `offset` 是根据推荐记录对用户 rating 的调整
+fn get_rating_range(cmd_args: &CommandArgs, user_rating: i64, offset: i64) -> (i64, i64, String) {
     if let Some(specific_r) = cmd_args.specific_rating {
         // 如果指定了具体rating，就推荐该rating的题目
         (
@@ -322,27 +587,35 @@ fn get_rating_range(cmd_args: &CommandArgs, user_rating: i64) -> (i64, i64, Stri
             specific_r,
             format!("rating {} 的题目", specific_r),
         )
+    } else if cmd_args.filter.has_rating() {
+        // 筛选条件中已经给出了难度，交给筛选条件处理
+        (MIN_RATING, MAX_RATING, "符合筛选条件的题目".to_string())
     } else {
         // 否则根据难度推荐
-        let (min, max) = cmd_args.difficulty.get_rating_range(user_rating);
+        let (min, max) = cmd_args.difficulty.get_rating_range(user_rating + offset);
+        let adjusted = if offset != 0 {
+            format!("，根据推荐记录调整 {:+}", offset)
+        } else {
+            String::new()
+        };
         (
             min,
             max,
             format!(
-                "{:?}难度题目（用户rating: {}）",
-                cmd_args.difficulty, user_rating
+                "{:?}难度题目（用户rating: {}{}）",
+                cmd_args.difficulty, user_rating, adjusted
             ),
         )
     }
 }
 
 // 加权随机选择题目
-fn weighted_random_select<'a>(
-    candidates: &[&'a Arc<crate::duel::problem::Problem>],
+fn weighted_random_select<T>(
+    candidates: Vec<T>,
     weights: &[f64],
     count: usize,
     rng: &mut impl Rng,
-) -> Vec<&'a Arc<crate::duel::problem::Problem>> {
+) -> Vec<T> {
     let mut indices: Vec<usize> = (0..candidates.len()).collect();
     let desired_count = count.min(candidates.len());
     let mut selected_indices = Vec::with_capacity(desired_count);
@@ -374,31 +647,222 @@ fn weighted_random_select<'a>(
         selected_indices.push(indices.remove(selected_pos));
     }
 
-    selected_indices.iter().map(|&i| candidates[i]).collect()
+    let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+    selected_indices
+        .iter()
+        .filter_map(|&i| candidates[i].take())
+        .collect()
 }
 
-// 计算题目权重
-fn calculate_problem_weight(
-    problem: &crate::duel::problem::Problem,
-    tag_weight: &HashMap<String, usize>,
-    target_rating: i64,
-) -> f64 {
-    let score = calculate_tag_score(problem, tag_weight) as f64;
-    let diff = (problem.rating.unwrap_or(target_rating) - target_rating).abs() as f64;
-    let rating_bonus = 1.0 / (1.0 + diff / 100.0);
-    (score + 1.0) * rating_bonus
+/// 读取用户的推荐记录，并把推荐之后通过了的题目标记出来
+async fn load_history(qq: i64, cf_id: &str) -> Result<Vec<Recommendation>> {
+    let mut records = sql::recommendation::get_recommendations(qq).await?;
+    if records.iter().all(|record| record.solved_at.is_some()) {
+        return Ok(records);
+    }
+
+    let submissions = get_recent_submissions(cf_id).await?;
+    let solves = find_solves(&records, &submissions);
+    if solves.is_empty() {
+        return Ok(records);
+    }
+
+    let mut commit = Commit::start().await?;
+    for (&id, &solved_at) in solves.iter() {
+        commit.mark_recommendation_solved(id, solved_at).await?;
+    }
+    commit.commit().await?;
+
+    for record in records.iter_mut() {
+        if let Some(&solved_at) = solves.get(&record.id) {
+            record.solved_at = Some(solved_at);
+        }
+    }
+    Ok(records)
+}
+
+/// 还没通过的推荐在推荐之后第一次通过的时间，键为记录 id
+fn find_solves(records: &[Recommendation], submissions: &[Submission]) -> HashMap<i64, i64> {
+    let mut accepted: HashMap<(i64, String), Vec<i64>> = HashMap::new();
+    for submission in submissions.iter().filter(|s| s.is_accepted()) {
+        accepted
+            .entry(submission.problem.key())
+            .or_default()
+            .push(submission.creation_time_seconds);
+    }
+
+    records
+        .iter()
+        .filter(|record| record.solved_at.is_none())
+        .filter_map(|record| {
+            let solved_at = accepted
+                .get(&(record.contest_id, record.index.clone()))?
+                .iter()
+                .filter(|&&time| time >= record.recommended_at)
+                .min()?;
+            Some((record.id, *solved_at))
+        })
+        .collect()
 }
 
-// 计算题目的tag权重分数
-fn calculate_tag_score(
-    problem: &crate::duel::problem::Problem,
-    tag_weight: &std::collections::HashMap<String, usize>,
-) -> usize {
-    problem
-        .tags
+/// 根据最近的推荐结果调整用户在这个难度下的 rating
+///
+/// 实际通过率高于目标时说明推荐的题偏简单，调高 rating，反之调低。
+/// 用 ELO 模型把通过率的差距换算成 rating 差，记录少时向目标通过率收缩。
+fn rating_offset(records: &[Recommendation], difficulty: &RecommendDifficulty, now: i64) -> i64 {
+    let outcomes = records
         .iter()
-        .map(|t| tag_weight.get(t).copied().unwrap_or(0))
-        .sum::<usize>()
+        .filter(|record| record.difficulty.as_ref() == Some(difficulty))
+        .filter_map(|record| record.outcome(now))
+        .take(MAX_FEEDBACK_SAMPLES)
+        .collect::<Vec<_>>();
+    if outcomes.len() < MIN_FEEDBACK_SAMPLES {
+        return 0;
+    }
+
+    let target = difficulty.get_target_solve_probability();
+    let solved = outcomes.iter().filter(|&&solved| solved).count() as f64;
+    // 相当于额外加上 MIN_FEEDBACK_SAMPLES 条正好符合目标的记录
+    let prior = MIN_FEEDBACK_SAMPLES as f64;
+    let observed = (solved + target * prior) / (outcomes.len() as f64 + prior);
+
+    let odds = |p: f64| p / (1.0 - p);
+    let offset = 400.0 * (odds(observed) / odds(target)).log10();
+    ((offset / 10.0).round() as i64 * 10).clamp(-MAX_RATING_OFFSET, MAX_RATING_OFFSET)
+}
+
+async fn save_history(
+    qq: i64,
+    cf_id: &str,
+    rating: i64,
+    cmd_args: &CommandArgs,
+    args: &[String],
+    picks: &[Pick<'_>],
+) -> Result<()> {
+    // 指定了难度的推荐不参与难度调整
+    let difficulty = (cmd_args.specific_rating.is_none() && !cmd_args.filter.has_rating())
+        .then(|| cmd_args.difficulty.clone());
+    let now = Utc::now().timestamp();
+
+    let mut commit = Commit::start().await?;
+    for pick in picks {
+        commit
+            .add_recommendation(&Recommendation {
+                id: 0,
+                qq,
+                cf_id: cf_id.to_string(),
+                contest_id: pick.problem.contest_id,
+                index: pick.problem.index.clone(),
+                problem_rating: pick.problem.rating.or(pick.problem.estimated_rating),
+                user_rating: rating,
+                difficulty: difficulty.clone(),
+                params: args.get(2..).unwrap_or_default().join(" "),
+                recommended_at: now,
+                solved_at: None,
+            })
+            .await?;
+    }
+    commit.commit().await?;
+    Ok(())
+}
+
+/// 查看最近的推荐记录和各难度推荐的实际通过率
+async fn history(event: &MsgEvent) {
+    let cf_id = match get_cf_id(&IdOrText::At(event.user_id)).await {
+        Ok(cf_id) => cf_id,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    let records = match load_history(event.user_id, &cf_id).await {
+        Ok(records) => records,
+        Err(e) => {
+            event.reply(format!("获取推荐记录失败: {}", e));
+            return;
+        }
+    };
+
+    if records.is_empty() {
+        event.reply("还没有推荐记录，先用 /cf recommend 推荐几道题吧");
+        return;
+    }
+
+    event.reply(format_history(&records, Utc::now().timestamp()));
+}
+
+fn format_history(records: &[Recommendation], now: i64) -> String {
+    let mut msg = "最近的推荐：".to_string();
+    for record in records.iter().take(HISTORY_LIMIT) {
+        let outcome = match record.solved_at {
+            Some(solved_at) => format!(
+                "{}后通过",
+                format_duration(solved_at - record.recommended_at)
+            ),
+            None if record.outcome(now).is_some() => "没有通过".to_string(),
+            None => "还没通过".to_string(),
+        };
+        let rating = record
+            .problem_rating
+            .map_or("未知".to_string(), |rating| rating.to_string());
+        let difficulty = record
+            .difficulty
+            .as_ref()
+            .map_or("指定难度".to_string(), |d| format!("{:?}", d));
+        msg.push_str(&format!(
+            "\n{} {}{} ({}, {})：{}",
+            format_local(DateTime::from_timestamp(record.recommended_at, 0).unwrap_or_default()),
+            record.contest_id,
+            record.index,
+            rating,
+            difficulty,
+            outcome
+        ));
+    }
+
+    let mut stats = Vec::new();
+    for difficulty in [
+        RecommendDifficulty::Easy,
+        RecommendDifficulty::Moderate,
+        RecommendDifficulty::Difficult,
+    ] {
+        let outcomes = records
+            .iter()
+            .filter(|record| record.difficulty.as_ref() == Some(&difficulty))
+            .filter_map(|record| record.outcome(now))
+            .collect::<Vec<_>>();
+        if outcomes.is_empty() {
+            continue;
+        }
+        let solved = outcomes.iter().filter(|&&solved| solved).count();
+        stats.push(format!(
+            "{:?}：{}/{}（目标 {:.0}%），难度调整 {:+}",
+            difficulty,
+            solved,
+            outcomes.len(),
+            difficulty.get_target_solve_probability() * 100.0,
+            rating_offset(records, &difficulty, now)
+        ));
+    }
+    if !stats.is_empty() {
+        msg.push_str(&format!(
+            "\n\n推荐后 {} 天内通过的比例：\n{}",
+            SOLVE_WINDOW_DAYS,
+            stats.join("\n")
+        ));
+    }
+    msg
+}
+
+fn format_duration(seconds: i64) -> String {
+    if seconds < 3600 {
+        format!("{} 分钟", (seconds / 60).max(1))
+    } else if seconds < DAY_SECONDS {
+        format!("{} 小时", seconds / 3600)
+    } else {
+        format!("{} 天", seconds / DAY_SECONDS)
+    }
 }
 
 // 获取用户信息（CF ID 和 rating）
@@ -427,6 +891,7 @@ async fn get_cf_id(uit: &IdOrText<'_>) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::duel::submission::SubmissionBuilder;
 
     #[test]
     fn test_recommend_difficulty_from_str() {
@@ -507,6 +972,153 @@ mod tests {
         assert!((difficult.get_target_solve_probability() - 0.2).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_elo_win_probability() {
+        assert!((elo_win_probability(1500, 1500) - 0.5).abs() < 1e-9);
+        // 相差 400 分时概率比为 10:1
+        assert!((elo_win_probability(1900, 1500) - 10.0 / 11.0).abs() < 1e-9);
+        assert!((elo_win_probability(1500, 1900) - 1.0 / 11.0).abs() < 1e-9);
+
+        // 目标概率附近的题目权重最高
+        let problem = Arc::new(Problem::new(1, "A".to_string(), Some(1500), vec![]));
+        let pick = Pick::new(&problem, &HashMap::new(), 1500, false);
+        assert!(pick.weight(0.5) > pick.weight(0.8));
+        assert!(pick.weight(0.5) > pick.weight(0.2));
+    }
+
+    fn submission(id: i64, days_ago: i64, tags: &[&str], verdict: &str) -> Submission {
+        SubmissionBuilder::new(id)
+            .time(1_000_000_000 - days_ago * 24 * 60 * 60)
+            .problem(id, "A")
+            .rating(1500)
+            .tags(tags)
+            .verdict(verdict)
+            .build()
+    }
+
+    #[test]
+    fn test_tag_mastery() {
+        let mut submissions = Vec::new();
+        // dp 错过很多次，但都最终通过了
+        for id in 0..20 {
+            submissions.push(submission(id, 10, &["dp"], "WRONG_ANSWER"));
+            submissions.push(submission(id, 10, &["dp"], "WRONG_ANSWER"));
+            submissions.push(submission(id, 10, &["dp"], "OK"));
+        }
+        // graphs 最近做的都没过，很久以前过过几题
+        for id in 100..105 {
+            submissions.push(submission(id, 5, &["graphs"], "WRONG_ANSWER"));
+        }
+        for id in 200..210 {
+            submissions.push(submission(id, 1000, &["graphs"], "OK"));
+        }
+
+        let mastery = tag_mastery(&submissions, 1_000_000_000);
+        assert!(mastery["dp"].ratio() > 0.9);
+        assert!(mastery["graphs"].ratio() < 0.3);
+        assert!(!mastery.contains_key("math"));
+
+        let dp = Arc::new(Problem::new(
+            1,
+            "A".to_string(),
+            Some(1500),
+            vec!["dp".to_string()],
+        ));
+        let graphs = Arc::new(Problem::new(
+            2,
+            "A".to_string(),
+            Some(1500),
+            vec!["dp".to_string(), "graphs".to_string()],
+        ));
+        let dp = Pick::new(&dp, &mastery, 1500, false);
+        let graphs = Pick::new(&graphs, &mastery, 1500, false);
+        assert!(graphs.weight(0.5) > dp.weight(0.5));
+        assert_eq!(graphs.weakest_tag.unwrap().0, "graphs");
+        assert!(graphs.reason().contains("graphs 最近的通过率只有"));
+    }
+
+    fn record(
+        id: i64,
+        difficulty: RecommendDifficulty,
+        solved_after: Option<i64>,
+    ) -> Recommendation {
+        Recommendation {
+            id,
+            qq: 1,
+            cf_id: "tourist".to_string(),
+            contest_id: id,
+            index: "A".to_string(),
+            problem_rating: Some(1500),
+            user_rating: 1500,
+            difficulty: Some(difficulty),
+            params: String::new(),
+            recommended_at: 0,
+            solved_at: solved_after,
+        }
+    }
+
+    #[test]
+    fn test_find_solves() {
+        let mut records = vec![
+            record(1, RecommendDifficulty::Moderate, None),
+            record(2, RecommendDifficulty::Moderate, None),
+            record(3, RecommendDifficulty::Moderate, None),
+        ];
+        for record in records.iter_mut() {
+            record.recommended_at = 1_000_000_000;
+        }
+        let submissions = vec![
+            // 推荐之前就通过了，不算
+            submission(1, 10, &[], "OK"),
+            submission(2, 0, &[], "WRONG_ANSWER"),
+            submission(2, 0, &[], "OK"),
+        ];
+
+        let solves = find_solves(&records, &submissions);
+        assert_eq!(solves.len(), 1);
+        assert_eq!(solves[&2], 1_000_000_000);
+    }
+
+    #[test]
+    fn test_rating_offset() {
+        let now = 30 * DAY_SECONDS;
+        let easy = RecommendDifficulty::Easy;
+        let hard = RecommendDifficulty::Difficult;
+
+        // 记录太少时不调整
+        let records = (0..3)
+            .map(|id| record(id, easy.clone(), Some(60)))
+            .collect::<Vec<_>>();
+        assert_eq!(rating_offset(&records, &easy, now), 0);
+
+        // 简单题全都很快通过，调高 rating
+        let records = (0..10)
+            .map(|id| record(id, easy.clone(), Some(60)))
+            .collect::<Vec<_>>();
+        let offset = rating_offset(&records, &easy, now);
+        assert!(offset > 100 && offset <= MAX_RATING_OFFSET, "{}", offset);
+        // 其他难度没有记录
+        assert_eq!(rating_offset(&records, &hard, now), 0);
+
+        // 难题一道都没过（超过期限才通过也不算），调低 rating
+        let records = (0..10)
+            .map(|id| record(id, hard.clone(), (id == 0).then_some(30 * DAY_SECONDS)))
+            .collect::<Vec<_>>();
+        assert!(rating_offset(&records, &hard, now) < 0);
+
+        // 通过率符合目标时不调整
+        let records = (0..10)
+            .map(|id| record(id, hard.clone(), (id < 2).then_some(60)))
+            .collect::<Vec<_>>();
+        assert_eq!(rating_offset(&records, &hard, now), 0);
+
+        // 还没到期限的记录不计入
+        let records = (0..10)
+            .map(|id| record(id, easy.clone(), None))
+            .collect::<Vec<_>>();
+        assert_eq!(rating_offset(&records, &easy, DAY_SECONDS), 0);
+    }
+
     #[test]
     fn test_valid_rating_values() {
         // 测试有效的rating值
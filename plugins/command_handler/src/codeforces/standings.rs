@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use kovi::{Message, MsgEvent};
+
+use super::{api, bound_handles, render};
+use crate::codeforces::api::{CfApiError, RanklistRow, Standings};
+
+/// 图片中最多展示的行数
+const MAX_ROWS: usize = 50;
+
+#[derive(serde::Serialize)]
+pub(super) struct Cell {
+    pub(super) text: String,
+    /// solved, rejected 或 none
+    pub(super) state: &'static str,
+}
+
+#[derive(serde::Serialize)]
+pub(super) struct Row {
+    pub(super) rank: Option<i64>,
+    /// 和 CF 榜单一样，打星选手加 *，虚拟参赛加 #
+    pub(super) who: String,
+    pub(super) points: String,
+    pub(super) penalty: i64,
+    pub(super) results: Vec<Cell>,
+    pub(super) hacks: String,
+    pub(super) delta: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub(super) struct Table {
+    pub(super) contest: String,
+    /// ICPC 赛制显示罚时
+    pub(super) icpc: bool,
+    pub(super) problems: Vec<String>,
+    pub(super) rows: Vec<Row>,
+    pub(super) truncated: bool,
+}
+
+/// 查看群友（私聊时为所有绑定用户）在一场比赛中的排名
+///
+/// 用法：/cf standings <比赛 id> [--unofficial/-u]，加上选项后包括打星和虚拟参赛
+pub async fn standings(event: &MsgEvent, args: &[String]) {
+    let Some(contest_id) = args.get(2).and_then(|id| id.parse::<i64>().ok()) else {
+        event.reply("用法：/cf standings <比赛 id> [--unofficial/-u]");
+        return;
+    };
+    let unofficial = args[3..]
+        .iter()
+        .any(|arg| arg == "--unofficial" || arg == "-u");
+
+    let handles = match bound_handles(event.group_id).await {
+        Ok(handles) => handles,
+        Err(e) => {
+            event.reply(e.to_string());
+            return;
+        }
+    };
+
+    let standings = match fetch_standings(contest_id, handles, unofficial).await {
+        Ok(standings) => standings,
+        Err(e) => {
+            event.reply(format!("获取榜单失败: {}", e));
+            return;
+        }
+    };
+
+    let rows = standings
+        .rows
+        .iter()
+        .filter(|row| row.party.participant_type != "PRACTICE")
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        event.reply(format!("{} 中没有找到你们的排名", standings.contest.name));
+        return;
+    }
+
+    // unrated 或者还没有更新 rating 时没有 rating 变化
+    let deltas = match api::contest_rating_changes(contest_id).await {
+        Ok(changes) => changes
+            .iter()
+            .map(|change| {
+                let delta = change.new_rating - change.old_rating;
+                (change.handle.to_lowercase(), delta)
+            })
+            .collect(),
+        Err(_) => HashMap::new(),
+    };
+
+    let icpc = standings.contest.kind == "ICPC";
+    let table = Table {
+        contest: standings.contest.name.clone(),
+        icpc,
+        problems: standings
+            .problems
+            .iter()
+            .map(|problem| problem.index.clone())
+            .collect(),
+        truncated: rows.len() > MAX_ROWS,
+        rows: rows
+            .into_iter()
+            .take(MAX_ROWS)
+            .map(|row| table_row(row, icpc, &deltas))
+            .collect(),
+    };
+
+    match render("standings.py", &table).await {
+        Ok(image) => event.reply(Message::new().add_image(&image)),
+        Err(e) => event.reply(format!("生成图片失败: {}", e)),
+    }
+}
+
+/// 请求指定用户的榜单，有人改了名时去掉这个人重试
+pub(super) async fn fetch_standings(
+    contest_id: i64,
+    mut handles: Vec<String>,
+    unofficial: bool,
+) -> Result<std::sync::Arc<Standings>, CfApiError> {
+    loop {
+        let refs = handles.iter().map(String::as_str).collect::<Vec<_>>();
+        match api::contest_standings(contest_id, &refs, unofficial, None).await {
+            Err(CfApiError::HandleNotFound(handle)) => {
+                let len = handles.len();
+                handles.retain(|h| !h.eq_ignore_ascii_case(&handle));
+                if handles.len() == len || handles.is_empty() {
+                    return Err(CfApiError::HandleNotFound(handle));
+                }
+            }
+            res => return res,
+        }
+    }
+}
+
+fn table_row(row: &RanklistRow, icpc: bool, deltas: &HashMap<String, i64>) -> Row {
+    let handles = row
+        .party
+        .members
+        .iter()
+        .map(|member| member.handle.as_str())
+        .collect::<Vec<_>>();
+    let mark = match row.party.participant_type.as_str() {
+        "OUT_OF_COMPETITION" => " *",
+        "VIRTUAL" => " #",
+        _ => "",
+    };
+
+    let results = row
+        .problem_results
+        .iter()
+        .map(|result| {
+            result_cell(
+                result.points,
+                result.rejected_attempt_count,
+                result.best_submission_time_seconds,
+                icpc,
+            )
+        })
+        .collect();
+
+    let hacks = match (row.successful_hack_count, row.unsuccessful_hack_count) {
+        (0, 0) => String::new(),
+        (0, bad) => format!("-{}", bad),
+        (good, 0) => format!("+{}", good),
+        (good, bad) => format!("+{}:-{}", good, bad),
+    };
+
+    let official = row.party.participant_type == "CONTESTANT";
+    Row {
+        rank: (official && row.rank > 0).then_some(row.rank),
+        who: format!("{}{}", handles.join(", "), mark),
+        points: format!("{}", row.points),
+        penalty: row.penalty,
+        results,
+        hacks,
+        delta: handles
+            .first()
+            .filter(|_| official)
+            .and_then(|handle| deltas.get(&handle.to_lowercase()).copied()),
+    }
+}
+
+/// 一道题的格子：通过时显示分数（ICPC 赛制为 + 和错误次数）和通过时间，没通过时显示错误次数
+pub(super) fn result_cell(points: f64, rejected: i64, time: Option<i64>, icpc: bool) -> Cell {
+    match time {
+        Some(time) if points > 0.0 => {
+            let score = if icpc {
+                if rejected > 0 {
+                    format!("+{}", rejected)
+                } else {
+                    "+".to_string()
+                }
+            } else {
+                format!("{}", points)
+            };
+            Cell {
+                text: format!("{}\n{}:{:02}", score, time / 3600, time / 60 % 60),
+                state: "solved",
+            }
+        }
+        _ if rejected > 0 => Cell {
+            text: format!("-{}", rejected),
+            state: "rejected",
+        },
+        _ => Cell {
+            text: String::new(),
+            state: "none",
+        },
+    }
+}
@@ -0,0 +1,479 @@
+//! 赛后补题记录
+//!
+//! 比赛结束后记录每个绑定用户赛时尝试过但没通过的题，以及最后通过的题的下一题，
+//! 查看时根据提交记录自动勾掉已经通过的题，每周提醒一次还没补的题。
+
+use std::collections::HashMap;
+
+use kovi::{
+    MsgEvent,
+    chrono::Utc,
+    log::{error, info},
+};
+
+use super::standings::fetch_standings;
+use crate::{
+    BOT,
+    codeforces::api::{self, ContestProblem, ProblemResult},
+    duel::submission::get_solved_set,
+    sql::{self, upsolve::CommitUpsolveExt, utils::Commit},
+    subscription,
+    utils::{IdOrText, group_member_ids, user_id_or_text},
+};
+
+pub const UPSOLVE_TOPIC: &str = "cf_upsolve_weekly";
+
+/// 只记录最近这么久之内结束的比赛
+const TRACK_WINDOW_SECONDS: i64 = 3 * 24 * 60 * 60;
+
+/// 一次最多列出几道题
+const MAX_LIST: usize = 20;
+
+/// 每周提醒时每人最多列出几道题
+const MAX_REMIND: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpsolveReason {
+    /// 赛时交过但没通过
+    Attempted,
+    /// 最后通过的题的下一题
+    Next,
+}
+
+impl UpsolveReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpsolveReason::Attempted => "attempted",
+            UpsolveReason::Next => "next",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "attempted" => Some(UpsolveReason::Attempted),
+            "next" => Some(UpsolveReason::Next),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            UpsolveReason::Attempted => "赛时没过",
+            UpsolveReason::Next => "下一题",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpsolveItem {
+    pub qq: i64,
+    pub cf_id: String,
+    pub contest_id: i64,
+    pub index: String,
+    pub name: String,
+    pub reason: UpsolveReason,
+}
+
+impl UpsolveItem {
+    fn key(&self) -> (i64, String) {
+        (self.contest_id, self.index.clone())
+    }
+}
+
+/// 一行榜单中需要补的题在 `problems` 中的下标
+///
+/// 赛时交过没通过的题都要补；另外最后通过的题的下一题如果没交过也要补，
+/// 一题没过时就是第一题。什么都没交过的行没有需要补的题。
+fn pending_problems(
+    problems: &[ContestProblem],
+    results: &[ProblemResult],
+) -> Vec<(usize, UpsolveReason)> {
+    let solved = |result: &ProblemResult| result.points > 0.0;
+    let attempted = |result: &ProblemResult| !solved(result) && result.rejected_attempt_count > 0;
+
+    let mut pending = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| attempted(result))
+        .map(|(i, _)| (i, UpsolveReason::Attempted))
+        .collect::<Vec<_>>();
+
+    let last_solved = results.iter().rposition(solved);
+    if last_solved.is_none() && pending.is_empty() {
+        return pending;
+    }
+
+    let next = last_solved.map_or(0, |i| i + 1);
+    if next < problems.len()
+        && results
+            .get(next)
+            .is_none_or(|result| !solved(result) && !attempted(result))
+    {
+        pending.push((next, UpsolveReason::Next));
+        pending.sort_by_key(|(i, _)| *i);
+    }
+    pending
+}
+
+/// 记录最近结束的比赛中绑定用户需要补的题，每场比赛只记录一次
+pub async fn track_contests() {
+    let contests = match api::contest_list(false).await {
+        Ok(contests) => contests,
+        Err(e) => {
+            error!("获取比赛列表失败: {}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let finished = contests
+        .iter()
+        .filter(|contest| {
+            contest.phase == "FINISHED"
+                && contest.start_time_seconds.is_some_and(|start| {
+                    now - (start + contest.duration_seconds) < TRACK_WINDOW_SECONDS
+                })
+        })
+        .collect::<Vec<_>>();
+    if finished.is_empty() {
+        return;
+    }
+
+    let users = match sql::duel::user::get_bound_users().await {
+        Ok(users) => users,
+        Err(e) => {
+            error!("获取绑定用户失败: {}", e);
+            return;
+        }
+    };
+    let mut qqs: HashMap<String, Vec<i64>> = HashMap::new();
+    for user in users.iter() {
+        if let Some(cf_id) = &user.cf_id {
+            qqs.entry(cf_id.to_lowercase()).or_default().push(user.qq);
+        }
+    }
+    if qqs.is_empty() {
+        return;
+    }
+    let handles = users
+        .iter()
+        .filter_map(|user| user.cf_id.clone())
+        .collect::<Vec<_>>();
+
+    for contest in finished {
+        match sql::upsolve::is_tracked(contest.id).await {
+            Ok(false) => {}
+            Ok(true) => continue,
+            Err(e) => {
+                error!("查询比赛 {} 的补题记录失败: {}", contest.id, e);
+                continue;
+            }
+        }
+
+        let items = match fetch_standings(contest.id, handles.clone(), true).await {
+            Ok(standings) => {
+                let mut items = Vec::new();
+                for row in standings.rows.iter().filter(|row| {
+                    matches!(
+                        row.party.participant_type.as_str(),
+                        "CONTESTANT" | "OUT_OF_COMPETITION"
+                    )
+                }) {
+                    let pending = pending_problems(&standings.problems, &row.problem_results);
+                    for member in row.party.members.iter() {
+                        let Some(qqs) = qqs.get(&member.handle.to_lowercase()) else {
+                            continue;
+                        };
+                        for &qq in qqs {
+                            items.extend(pending.iter().map(|&(i, reason)| UpsolveItem {
+                                qq,
+                                cf_id: member.handle.clone(),
+                                contest_id: contest.id,
+                                index: standings.problems[i].index.clone(),
+                                name: standings.problems[i].name.clone(),
+                                reason,
+                            }));
+                        }
+                    }
+                }
+                items
+            }
+            // 看不到榜单的比赛不用再查了，其他错误下次重试
+            Err(e) if e.is_contest_rejected() => Vec::new(),
+            Err(e) => {
+                error!("获取比赛 {} 的榜单失败: {}", contest.id, e);
+                continue;
+            }
+        };
+
+        let result = async {
+            let mut commit = Commit::start().await?;
+            commit.add_upsolve_items(contest.id, &items).await?;
+            commit.commit().await?;
+            anyhow::Ok(())
+        }
+        .await;
+        match result {
+            Ok(_) => info!("记录了比赛 {} 的 {} 道补题", contest.id, items.len()),
+            Err(e) => error!("记录比赛 {} 的补题失败: {}", contest.id, e),
+        }
+    }
+}
+
+/// 去掉已经通过的题，并在数据库中标记为已补
+///
+/// 返回 (还没补的, 这次发现已经补了的)
+async fn refresh(
+    cf_id: &str,
+    items: Vec<UpsolveItem>,
+) -> anyhow::Result<(Vec<UpsolveItem>, Vec<UpsolveItem>)> {
+    let solved = get_solved_set(cf_id).await?;
+    let (done, pending): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .partition(|item| solved.contains(&item.key()));
+
+    if !done.is_empty() {
+        let mut commit = Commit::start().await?;
+        for item in done.iter() {
+            commit.mark_upsolved(item).await?;
+        }
+        commit.commit().await?;
+    }
+    Ok((pending, done))
+}
+
+/// 用法：/cf upsolve [@p] 或 /cf upsolve subscribe|unsubscribe
+pub async fn upsolve(event: &MsgEvent, args: &[String]) {
+    match args.get(2).map(String::as_str) {
+        Some("subscribe") => subscribe(event, true).await,
+        Some("unsubscribe") => subscribe(event, false).await,
+        user => list(event, user).await,
+    }
+}
+
+/// 查看自己或别人还没补的题
+async fn list(event: &MsgEvent, user: Option<&str>) {
+    let qq = match user {
+        None => event.user_id,
+        Some(arg) => match user_id_or_text(arg) {
+            Ok(IdOrText::At(qq)) => qq,
+            _ => {
+                event.reply("用法：/cf upsolve [@p] 或 /cf upsolve subscribe|unsubscribe");
+                return;
+            }
+        },
+    };
+
+    let cf_id = match sql::duel::user::get_user(qq)
+        .await
+        .ok()
+        .and_then(|u| u.cf_id)
+    {
+        Some(cf_id) => cf_id,
+        None => {
+            event.reply("用户未绑定 cf 账号");
+            return;
+        }
+    };
+
+    let result = async {
+        let items = sql::upsolve::get_pending(qq).await?;
+        refresh(&cf_id, items).await
+    }
+    .await;
+    let (pending, done) = match result {
+        Ok(res) => res,
+        Err(e) => {
+            event.reply(format!("查询补题记录失败: {}", e));
+            return;
+        }
+    };
+
+    event.reply(format_pending(&cf_id, &pending, &done));
+}
+
+fn format_pending(cf_id: &str, pending: &[UpsolveItem], done: &[UpsolveItem]) -> String {
+    let mut msg = String::new();
+    if !done.is_empty() {
+        let done = done
+            .iter()
+            .map(|item| format!("{}{}", item.contest_id, item.index))
+            .collect::<Vec<_>>();
+        msg.push_str(&format!("新补完 {} 道：{}\n", done.len(), done.join(", ")));
+    }
+
+    if pending.is_empty() {
+        msg.push_str(&format!("{} 没有需要补的题", cf_id));
+        return msg;
+    }
+
+    msg.push_str(&format!("{} 还有 {} 道题要补：", cf_id, pending.len()));
+    for item in pending.iter().take(MAX_LIST) {
+        msg.push_str(&format!(
+            "\n{}{} {}（{}）",
+            item.contest_id,
+            item.index,
+            item.name,
+            item.reason.label()
+        ));
+    }
+    if pending.len() > MAX_LIST {
+        msg.push_str(&format!("\n...等 {} 道", pending.len()));
+    }
+    msg
+}
+
+/// 订阅或取消订阅每周补题提醒
+async fn subscribe(event: &MsgEvent, subscribe: bool) {
+    subscription::toggle(event, UPSOLVE_TOPIC, subscribe, "每周补题提醒").await;
+}
+
+/// 向订阅的群提醒群友还没补的题
+pub async fn remind_weekly() {
+    let groups = match sql::subscription::get_subscribed_groups(UPSOLVE_TOPIC).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("获取补题提醒订阅失败: {}", e);
+            return;
+        }
+    };
+
+    if groups.is_empty() {
+        return;
+    }
+
+    let items = match sql::upsolve::get_all_pending().await {
+        Ok(items) => items,
+        Err(e) => {
+            error!("获取补题记录失败: {}", e);
+            return;
+        }
+    };
+
+    let mut by_user: HashMap<i64, Vec<UpsolveItem>> = HashMap::new();
+    for item in items {
+        by_user.entry(item.qq).or_default().push(item);
+    }
+
+    // 先去掉已经补了的题，查询失败时照常提醒
+    let mut pending = Vec::new();
+    for (qq, items) in by_user {
+        let cf_id = items[0].cf_id.clone();
+        let items = match refresh(&cf_id, items.clone()).await {
+            Ok((items, _)) => items,
+            Err(e) => {
+                error!("刷新 {} 的补题记录失败: {}", cf_id, e);
+                items
+            }
+        };
+        if !items.is_empty() {
+            pending.push((qq, cf_id, items));
+        }
+    }
+    pending.sort_by_key(|(_, _, items)| std::cmp::Reverse(items.len()));
+
+    let bot = BOT.get().unwrap();
+
+    for group_id in groups {
+        let members = match group_member_ids(group_id).await {
+            Ok(members) => members,
+            Err(e) => {
+                error!("获取群 {} 成员失败: {}", group_id, e);
+                continue;
+            }
+        };
+
+        let lines = pending
+            .iter()
+            .filter(|(qq, _, _)| members.contains(qq))
+            .map(|(_, cf_id, items)| {
+                let problems = items
+                    .iter()
+                    .take(MAX_REMIND)
+                    .map(|item| format!("{}{}", item.contest_id, item.index))
+                    .collect::<Vec<_>>();
+                let more = if items.len() > MAX_REMIND { " 等" } else { "" };
+                format!(
+                    "{} 还有 {} 道：{}{}",
+                    cf_id,
+                    items.len(),
+                    problems.join(", "),
+                    more
+                )
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            continue;
+        }
+
+        info!("send upsolve reminder to group: {}", group_id);
+        bot.send_group_msg(group_id, format!("本周补题提醒：\n{}", lines.join("\n")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(n: usize) -> Vec<ContestProblem> {
+        (0..n)
+            .map(|i| ContestProblem {
+                index: ((b'A' + i as u8) as char).to_string(),
+                name: String::new(),
+                points: None,
+            })
+            .collect()
+    }
+
+    fn result(points: f64, rejected: i64) -> ProblemResult {
+        ProblemResult {
+            points,
+            rejected_attempt_count: rejected,
+            best_submission_time_seconds: (points > 0.0).then_some(600),
+        }
+    }
+
+    #[test]
+    fn test_pending_problems() {
+        use UpsolveReason::*;
+
+        // 过了 A、B，C 错了，D 没交
+        let results = [
+            result(1.0, 0),
+            result(1.0, 1),
+            result(0.0, 2),
+            result(0.0, 0),
+        ];
+        assert_eq!(
+            pending_problems(&problems(4), &results),
+            vec![(2, Attempted)]
+        );
+
+        // 过了 A、C，B 错了，D 是下一题
+        let results = [
+            result(1.0, 0),
+            result(0.0, 1),
+            result(1.0, 0),
+            result(0.0, 0),
+        ];
+        assert_eq!(
+            pending_problems(&problems(4), &results),
+            vec![(1, Attempted), (3, Next)]
+        );
+
+        // 全过了
+        let results = [result(500.0, 0), result(1000.0, 0)];
+        assert!(pending_problems(&problems(2), &results).is_empty());
+
+        // 一题没过，只错了 B
+        let results = [result(0.0, 0), result(0.0, 3)];
+        assert_eq!(
+            pending_problems(&problems(2), &results),
+            vec![(0, Next), (1, Attempted)]
+        );
+
+        // 什么都没交
+        let results = [result(0.0, 0), result(0.0, 0)];
+        assert!(pending_problems(&problems(2), &results).is_empty());
+    }
+}
@@ -0,0 +1,805 @@
+//! 群内虚拟赛
+//!
+//! 创建后群友报名，到开始时间时挑选一场所有参赛者都没有提交过的比赛并播报。
+//! 比赛时间内对这场比赛的提交都计入成绩（CF 上的虚拟参赛或者直接在题目页提交都可以），
+//! 结束后按 CF 的计分方式算出成绩，并和正式榜单对比得到相当于正式比赛的名次。
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use kovi::{
+    Message, MsgEvent,
+    chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Utc},
+    log::{error, info},
+};
+use rand::seq::IndexedRandom;
+
+use super::{
+    api::{self, Contest, ContestProblem, Standings},
+    render,
+    standings::{Row, Table, result_cell},
+};
+use crate::{
+    BOT,
+    duel::{
+        problem::get_problems,
+        submission::{Submission, get_recent_submissions},
+    },
+    sql::{self, utils::Commit, virtual_contest::CommitVirtualContestExt},
+    utils::is_group_admin,
+};
+
+/// 不指定开始时间时，创建后多久开始
+const DEFAULT_DELAY_MINUTES: i64 = 10;
+/// 太长的比赛不适合群内虚拟赛
+const MAX_DURATION_SECONDS: i64 = 5 * 60 * 60;
+/// ICPC 赛制每次错误提交的罚时（分钟）
+const ICPC_PENALTY_MINUTES: i64 = 10;
+/// CF 赛制每次错误提交扣的分
+const CF_WRONG_PENALTY: f64 = 50.0;
+/// CF 赛制题目分数最低为满分的 30%
+const CF_MIN_SCORE_RATIO: f64 = 0.3;
+/// CF 赛制比赛结束时题目分数降到满分的 52%
+const CF_END_SCORE_RATIO: f64 = 0.52;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualStatus {
+    Pending,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+impl VirtualStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Finished => "finished",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "running" => Some(Self::Running),
+            "finished" => Some(Self::Finished),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VirtualContest {
+    pub id: i64,
+    pub group_id: i64,
+    pub creator: i64,
+    /// 创建时指定的比赛，或者开始时挑选出的比赛
+    pub contest_id: Option<i64>,
+    /// 挑选比赛的条件，原样保存，开始时再解析
+    pub filter: String,
+    pub start_time: DateTime<Utc>,
+    /// 开始后才确定
+    pub duration_seconds: Option<i64>,
+    pub status: VirtualStatus,
+}
+
+/// 挑选比赛的条件
+#[derive(Debug, Default, PartialEq)]
+struct ContestFilter {
+    /// 比赛名称中需要包含其中之一
+    divisions: Vec<&'static str>,
+    years: Option<(i32, i32)>,
+    /// 题目平均难度的范围
+    rating: Option<(i64, i64)>,
+}
+
+fn parse_range(s: &str) -> Option<(i64, i64)> {
+    match s.split_once('-') {
+        Some((lo, hi)) => Some((lo.parse().ok()?, hi.parse().ok()?)),
+        None => {
+            let value = s.parse().ok()?;
+            Some((value, value))
+        }
+    }
+}
+
+impl ContestFilter {
+    fn parse(text: &str) -> Result<Self> {
+        let mut filter = Self::default();
+        for token in text.split_whitespace() {
+            let token = token.to_lowercase();
+            let division = match token.as_str() {
+                "div1" => Some("Div. 1"),
+                "div2" => Some("Div. 2"),
+                "div3" => Some("Div. 3"),
+                "div4" => Some("Div. 4"),
+                "edu" => Some("Educational"),
+                "global" => Some("Global"),
+                _ => None,
+            };
+            if let Some(division) = division {
+                filter.divisions.push(division);
+            } else if let Some(years) = token.strip_prefix("year:") {
+                let (lo, hi) =
+                    parse_range(years).ok_or_else(|| anyhow::anyhow!("无法识别年份 {}", years))?;
+                filter.years = Some((lo as i32, hi as i32));
+            } else if let Some(rating) = token.strip_prefix("rating:") {
+                let (lo, hi) = parse_range(rating)
+                    .ok_or_else(|| anyhow::anyhow!("无法识别难度 {}", rating))?;
+                // 单个难度表示附近 100 分以内
+                filter.rating = Some(if lo == hi {
+                    (lo - 100, hi + 100)
+                } else {
+                    (lo, hi)
+                });
+            } else {
+                return Err(anyhow::anyhow!("无法识别的条件 {}", token));
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, contest: &Contest, average_rating: Option<i64>) -> bool {
+        if !self.divisions.is_empty()
+            && !self
+                .divisions
+                .iter()
+                .any(|division| contest.name.contains(division))
+        {
+            return false;
+        }
+        if let Some((lo, hi)) = self.years {
+            let year = contest
+                .start_time_seconds
+                .and_then(|start| Local.timestamp_opt(start, 0).single())
+                .map(|time| time.year());
+            if !year.is_some_and(|year| (lo..=hi).contains(&year)) {
+                return false;
+            }
+        }
+        if let Some((lo, hi)) = self.rating
+            && !average_rating.is_some_and(|rating| (lo..=hi).contains(&rating))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// 创建虚拟赛的参数：比赛 id、挑选条件和开始时间
+fn parse_create_args(args: &[String]) -> Result<(Option<i64>, String, Option<NaiveTime>)> {
+    let mut contest_id = None;
+    let mut filter = Vec::new();
+    let mut start = None;
+    for arg in args {
+        if let Ok(id) = arg.parse::<i64>() {
+            contest_id = Some(id);
+        } else if let Ok(time) = NaiveTime::parse_from_str(arg, "%H:%M") {
+            start = Some(time);
+        } else {
+            filter.push(arg.as_str());
+        }
+    }
+
+    let filter = filter.join(" ");
+    ContestFilter::parse(&filter)?;
+    if contest_id.is_some() && !filter.is_empty() {
+        return Err(anyhow::anyhow!("指定比赛 id 时不能再指定挑选条件"));
+    }
+    Ok((contest_id, filter, start))
+}
+
+/// 下一个 `time` 时刻，今天已经过了就是明天
+pub(super) fn next_time(time: NaiveTime) -> DateTime<Utc> {
+    let now = Local::now();
+    let today = now
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or(now);
+    if today > now {
+        today.to_utc()
+    } else {
+        (today + kovi::chrono::Duration::days(1)).to_utc()
+    }
+}
+
+pub(super) fn format_local(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%m-%d %H:%M").to_string()
+}
+
+fn contest_url(contest_id: i64) -> String {
+    format!("https://codeforces.com/contest/{}", contest_id)
+}
+
+pub(super) async fn user_cf_id(qq: i64) -> Result<String> {
+    sql::duel::user::get_user(qq)
+        .await
+        .ok()
+        .and_then(|user| user.cf_id)
+        .ok_or_else(|| anyhow::anyhow!("请先使用 /bind 绑定 CF 账号"))
+}
+
+async fn save(contest: &VirtualContest) -> Result<()> {
+    Commit::start()
+        .await?
+        .update_virtual_contest(contest)
+        .await?
+        .commit()
+        .await?;
+    Ok(())
+}
+
+/// 创建虚拟赛
+///
+/// 用法：/cf virtual create [比赛 id | 条件...] [HH:MM]
+pub async fn create(event: &MsgEvent, args: &[String]) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let cf_id = user_cf_id(event.user_id).await?;
+        if sql::virtual_contest::get_active_virtual_contest(group_id)
+            .await?
+            .is_some()
+        {
+            return Err(anyhow::anyhow!("本群已经有一场虚拟赛了"));
+        }
+
+        let (contest_id, filter, start) = parse_create_args(args.get(3..).unwrap_or_default())?;
+        let contest_name = match contest_id {
+            Some(contest_id) => {
+                let contests = api::contest_list(false).await?;
+                let contest = contests
+                    .iter()
+                    .find(|contest| contest.id == contest_id)
+                    .ok_or_else(|| anyhow::anyhow!("找不到比赛 {}", contest_id))?;
+                if contest.phase != "FINISHED" {
+                    return Err(anyhow::anyhow!("{} 还没有结束", contest.name));
+                }
+                contest.name.clone()
+            }
+            None if filter.is_empty() => "随机挑选".to_string(),
+            None => format!("按条件 {} 挑选", filter),
+        };
+
+        let contest = VirtualContest {
+            id: 0,
+            group_id,
+            creator: event.user_id,
+            contest_id,
+            filter,
+            start_time: match start {
+                Some(start) => next_time(start),
+                None => Utc::now() + kovi::chrono::Duration::minutes(DEFAULT_DELAY_MINUTES),
+            },
+            duration_seconds: None,
+            status: VirtualStatus::Pending,
+        };
+        Commit::start()
+            .await?
+            .add_virtual_contest(&contest, &cf_id)
+            .await?
+            .commit()
+            .await?;
+
+        Ok(format!(
+            "已创建虚拟赛，{} 开始，比赛：{}\n使用 /cf virtual join 报名，开始时会挑选所有参赛者都没有提交过的比赛",
+            format_local(contest.start_time),
+            contest_name
+        ))
+    }
+    .await;
+
+    match result {
+        Ok(msg) => event.reply(msg),
+        Err(e) => event.reply(format!("创建虚拟赛失败: {}", e)),
+    }
+}
+
+/// 报名或者退出本群还没开始的虚拟赛
+pub async fn join(event: &MsgEvent, join: bool) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let contest = sql::virtual_contest::get_active_virtual_contest(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有虚拟赛"))?;
+        if contest.status != VirtualStatus::Pending {
+            return Err(anyhow::anyhow!("虚拟赛已经开始了"));
+        }
+
+        let mut commit = Commit::start().await?;
+        if join {
+            let cf_id = user_cf_id(event.user_id).await?;
+            commit
+                .join_virtual_contest(contest.id, event.user_id, &cf_id)
+                .await?;
+        } else {
+            commit
+                .leave_virtual_contest(contest.id, event.user_id)
+                .await?;
+        }
+        commit.commit().await?;
+        Ok(contest)
+    }
+    .await;
+
+    match result {
+        Ok(contest) if join => event.reply(format!(
+            "报名成功，虚拟赛 {} 开始",
+            format_local(contest.start_time)
+        )),
+        Ok(_) => event.reply("已退出虚拟赛"),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+/// 取消本群的虚拟赛（创建者或群管理员）
+pub async fn cancel(event: &MsgEvent) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let mut contest = sql::virtual_contest::get_active_virtual_contest(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有虚拟赛"))?;
+        if contest.creator != event.user_id && !is_group_admin(event) {
+            return Err(anyhow::anyhow!("只有创建者或群管理员可以取消虚拟赛"));
+        }
+        contest.status = VirtualStatus::Cancelled;
+        save(&contest).await
+    }
+    .await;
+
+    match result {
+        Ok(_) => event.reply("已取消虚拟赛"),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+/// 查看本群虚拟赛的报名情况或者当前榜单
+pub async fn status(event: &MsgEvent) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    let result = async {
+        let contest = sql::virtual_contest::get_active_virtual_contest(group_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("本群没有虚拟赛"))?;
+        let participants = sql::virtual_contest::get_virtual_participants(contest.id).await?;
+
+        match (contest.status, contest.contest_id) {
+            (VirtualStatus::Running, Some(contest_id)) => {
+                let (table, lines) = results(&contest, contest_id, &participants).await?;
+                let image = render("standings.py", &table).await?;
+                Ok(Message::new().add_image(&image).add_text(lines.join("\n")))
+            }
+            _ => anyhow::Ok(Message::from(format!(
+                "虚拟赛 {} 开始，已报名：{}",
+                format_local(contest.start_time),
+                participants
+                    .iter()
+                    .map(|(_, cf_id)| cf_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+    .await;
+
+    match result {
+        Ok(msg) => event.reply(msg),
+        Err(e) => event.reply(e.to_string()),
+    }
+}
+
+/// 定时检查：到时间的虚拟赛开始，时间结束的虚拟赛公布成绩
+pub async fn tick() {
+    let contests = match sql::virtual_contest::get_active_virtual_contests().await {
+        Ok(contests) => contests,
+        Err(e) => {
+            error!("获取虚拟赛失败: {}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    for mut contest in contests {
+        let result = match contest.status {
+            VirtualStatus::Pending if contest.start_time <= now => start(&mut contest).await,
+            VirtualStatus::Running
+                if contest.duration_seconds.is_some_and(|duration| {
+                    contest.start_time + kovi::chrono::Duration::seconds(duration) <= now
+                }) =>
+            {
+                finish(&mut contest).await
+            }
+            _ => continue,
+        };
+
+        if let Err(e) = result {
+            error!("处理虚拟赛 {} 失败: {}", contest.id, e);
+        }
+    }
+}
+
+async fn start(contest: &mut VirtualContest) -> Result<()> {
+    let bot = BOT.get().unwrap();
+    let participants = sql::virtual_contest::get_virtual_participants(contest.id).await?;
+    if participants.is_empty() {
+        contest.status = VirtualStatus::Cancelled;
+        save(contest).await?;
+        bot.send_group_msg(contest.group_id, "没有人报名，虚拟赛已取消");
+        return Ok(());
+    }
+
+    // 所有参赛者提交过的比赛
+    let mut submitted = HashSet::new();
+    for (_, cf_id) in participants.iter() {
+        let submissions = get_recent_submissions(cf_id).await?;
+        submitted.extend(
+            submissions
+                .iter()
+                .map(|submission| submission.problem.contest_id),
+        );
+    }
+
+    let contests = api::contest_list(false).await?;
+    let picked = match contest.contest_id {
+        Some(contest_id) => contests.iter().find(|c| c.id == contest_id),
+        None => {
+            let filter = ContestFilter::parse(&contest.filter)?;
+            let problems = get_problems().await?;
+            let candidates = contests
+                .iter()
+                .filter(|c| {
+                    c.phase == "FINISHED"
+                        && c.duration_seconds <= MAX_DURATION_SECONDS
+                        && !submitted.contains(&c.id)
+                })
+                .filter(|c| {
+                    let ratings = problems
+                        .iter()
+                        .filter(|problem| problem.contest_id == c.id)
+                        .filter_map(|problem| problem.rating)
+                        .collect::<Vec<_>>();
+                    let average = (!ratings.is_empty())
+                        .then(|| ratings.iter().sum::<i64>() / ratings.len() as i64);
+                    filter.matches(c, average)
+                })
+                .collect::<Vec<_>>();
+            candidates.choose(&mut rand::rng()).copied()
+        }
+    };
+
+    let Some(picked) = picked else {
+        contest.status = VirtualStatus::Cancelled;
+        save(contest).await?;
+        bot.send_group_msg(
+            contest.group_id,
+            "没有找到符合条件且所有人都没有提交过的比赛，虚拟赛已取消",
+        );
+        return Ok(());
+    };
+
+    contest.contest_id = Some(picked.id);
+    contest.duration_seconds = Some(picked.duration_seconds);
+    contest.status = VirtualStatus::Running;
+    save(contest).await?;
+
+    let mut msg = format!(
+        "虚拟赛开始！\n{}\n{}\n时长 {}:{:02}，结束时间 {}\n参赛者：{}\n在 CF 上开始虚拟参赛或者直接提交都可以，比赛时间内的提交都会计入成绩",
+        picked.name,
+        contest_url(picked.id),
+        picked.duration_seconds / 3600,
+        picked.duration_seconds / 60 % 60,
+        format_local(contest.start_time + kovi::chrono::Duration::seconds(picked.duration_seconds)),
+        participants
+            .iter()
+            .map(|(_, cf_id)| cf_id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if submitted.contains(&picked.id) {
+        msg.push_str("\n注意：有参赛者之前在这场比赛中提交过");
+    }
+
+    info!(
+        "start virtual contest {} in group {}",
+        picked.id, contest.group_id
+    );
+    bot.send_group_msg(contest.group_id, msg);
+    Ok(())
+}
+
+async fn finish(contest: &mut VirtualContest) -> Result<()> {
+    let bot = BOT.get().unwrap();
+    contest.status = VirtualStatus::Finished;
+    save(contest).await?;
+
+    let Some(contest_id) = contest.contest_id else {
+        return Ok(());
+    };
+    let participants = sql::virtual_contest::get_virtual_participants(contest.id).await?;
+
+    let msg = match results(contest, contest_id, &participants).await {
+        Ok((table, lines)) => {
+            let mut msg = Message::new();
+            match render("standings.py", &table).await {
+                Ok(image) => msg = msg.add_image(&image),
+                Err(e) => error!("生成虚拟赛榜单失败: {}", e),
+            }
+            msg.add_text(format!("虚拟赛结束！\n{}", lines.join("\n")))
+        }
+        Err(e) => Message::from(format!("虚拟赛结束，统计成绩失败: {}", e)),
+    };
+
+    bot.send_group_msg(contest.group_id, msg);
+    Ok(())
+}
+
+/// 一名参赛者的成绩
+#[derive(Debug, PartialEq)]
+pub(super) struct Score {
+    pub(super) points: f64,
+    pub(super) penalty: i64,
+    /// 每道题 (通过时间, 错误次数, 得分)
+    pub(super) problems: Vec<(Option<i64>, i64, f64)>,
+}
+
+/// 计分规则
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Rules {
+    /// 按通过题数和罚时排名，每次错误提交罚时若干分钟
+    Icpc { penalty_minutes: i64 },
+    /// 题目分数随时间衰减，`duration` 为比赛时长（秒）
+    Cf { duration: i64 },
+}
+
+/// 按 CF 的规则计算成绩，`submissions` 为比赛时间内对这场比赛的提交，时间为相对开始的秒数
+pub(super) fn score(
+    submissions: &[(i64, &Submission)],
+    problems: &[ContestProblem],
+    rules: Rules,
+) -> Score {
+    let mut total = Score {
+        points: 0.0,
+        penalty: 0,
+        problems: Vec::new(),
+    };
+
+    for problem in problems {
+        let mut rejected = 0;
+        let mut solved_at = None;
+        for (time, submission) in submissions
+            .iter()
+            .filter(|(_, submission)| submission.problem.index == problem.index)
+        {
+            match submission.verdict.as_deref() {
+                Some("OK") => {
+                    solved_at = Some(*time);
+                    break;
+                }
+                None | Some("TESTING") | Some("COMPILATION_ERROR") => {}
+                Some(_) if submission.failed_first_test() => {}
+                Some(_) => rejected += 1,
+            }
+        }
+
+        let points = match (solved_at, rules) {
+            (Some(time), Rules::Icpc { penalty_minutes }) => {
+                total.penalty += time / 60 + rejected * penalty_minutes;
+                1.0
+            }
+            (Some(time), Rules::Cf { duration }) => {
+                let max = problem.points.unwrap_or(0.0);
+                let decay = max * (1.0 - CF_END_SCORE_RATIO) * (time / 60) as f64
+                    / (duration / 60).max(1) as f64;
+                (max - decay - CF_WRONG_PENALTY * rejected as f64)
+                    .max(max * CF_MIN_SCORE_RATIO)
+                    .floor()
+            }
+            (None, _) => 0.0,
+        };
+        total.points += points;
+        total.problems.push((solved_at, rejected, points));
+    }
+    total
+}
+
+/// 在正式榜单中的名次：比自己好的正式选手数加一
+fn official_rank(standings: &Standings, score: &Score) -> i64 {
+    standings
+        .rows
+        .iter()
+        .filter(|row| row.party.participant_type == "CONTESTANT")
+        .filter(|row| {
+            row.points > score.points || (row.points == score.points && row.penalty < score.penalty)
+        })
+        .count() as i64
+        + 1
+}
+
+/// 计算所有参赛者的成绩，返回榜单图片的数据和每个人相当于正式比赛名次的说明
+async fn results(
+    contest: &VirtualContest,
+    contest_id: i64,
+    participants: &[(i64, String)],
+) -> Result<(Table, Vec<String>)> {
+    let standings = api::contest_standings(contest_id, &[], false, None).await?;
+    let icpc = standings.contest.kind == "ICPC";
+    let start = contest.start_time.timestamp();
+    let duration = contest
+        .duration_seconds
+        .unwrap_or(standings.contest.duration_seconds);
+    let official = standings
+        .rows
+        .iter()
+        .filter(|row| row.party.participant_type == "CONTESTANT")
+        .count();
+
+    let mut scores = Vec::new();
+    for (_, cf_id) in participants {
+        let submissions = get_recent_submissions(cf_id).await?;
+        let mut submissions = submissions
+            .iter()
+            .filter(|submission| submission.problem.contest_id == contest_id)
+            .map(|submission| (submission.creation_time_seconds - start, submission))
+            .filter(|(time, _)| (0..duration).contains(time))
+            .collect::<Vec<_>>();
+        submissions.sort_by_key(|(time, _)| *time);
+        let rules = if icpc {
+            Rules::Icpc {
+                penalty_minutes: ICPC_PENALTY_MINUTES,
+            }
+        } else {
+            Rules::Cf { duration }
+        };
+        scores.push((cf_id, score(&submissions, &standings.problems, rules)));
+    }
+    scores.sort_by(|(_, a), (_, b)| {
+        b.points
+            .total_cmp(&a.points)
+            .then(a.penalty.cmp(&b.penalty))
+    });
+
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+    for (cf_id, score) in scores {
+        let rank = official_rank(&standings, &score);
+        lines.push(format!(
+            "{} 相当于正式比赛第 {} 名（共 {} 人）",
+            cf_id, rank, official
+        ));
+        rows.push(Row {
+            rank: Some(rank),
+            who: cf_id.clone(),
+            points: format!("{}", score.points),
+            penalty: score.penalty,
+            results: score
+                .problems
+                .iter()
+                .map(|&(time, rejected, points)| result_cell(points, rejected, time, icpc))
+                .collect(),
+            hacks: String::new(),
+            delta: None,
+        });
+    }
+
+    let table = Table {
+        contest: format!("{} (virtual)", standings.contest.name),
+        icpc,
+        problems: standings
+            .problems
+            .iter()
+            .map(|problem| problem.index.clone())
+            .collect(),
+        rows,
+        truncated: false,
+    };
+    Ok((table, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duel::submission::SubmissionBuilder;
+
+    fn submission(index: &str, verdict: &str) -> Submission {
+        SubmissionBuilder::new(0)
+            .problem(1, index)
+            .verdict(verdict)
+            .passed_tests(1)
+            .participant_type("VIRTUAL")
+            .build()
+    }
+
+    fn problem(index: &str, points: Option<f64>) -> ContestProblem {
+        ContestProblem {
+            index: index.to_string(),
+            name: String::new(),
+            points,
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = ContestFilter::parse("div2 Edu year:2020-2022 rating:1600").unwrap();
+        assert_eq!(
+            filter,
+            ContestFilter {
+                divisions: vec!["Div. 2", "Educational"],
+                years: Some((2020, 2022)),
+                rating: Some((1500, 1700)),
+            }
+        );
+        assert!(ContestFilter::parse("div5").is_err());
+
+        let args = ["1900", "20:30"].map(String::from);
+        let (contest_id, filter, start) = parse_create_args(&args).unwrap();
+        assert_eq!(contest_id, Some(1900));
+        assert!(filter.is_empty());
+        assert_eq!(start, NaiveTime::from_hms_opt(20, 30, 0));
+        assert!(parse_create_args(&["1900", "div2"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_score() {
+        let wa = submission("A", "WRONG_ANSWER");
+        let ce = submission("A", "COMPILATION_ERROR");
+        let wa_test1 = SubmissionBuilder::new(0)
+            .problem(1, "A")
+            .verdict("WRONG_ANSWER")
+            .passed_tests(0)
+            .build();
+        let ok_a = submission("A", "OK");
+        let ok_b = submission("B", "OK");
+        let wa_c = submission("C", "WRONG_ANSWER");
+        let submissions = [
+            (60, &wa),
+            (90, &ce),
+            (120, &wa_test1),
+            (600, &ok_a),
+            (1200, &ok_b),
+            (1300, &wa_c),
+        ];
+
+        // ICPC：罚时为通过的分钟数加上每次错误 10 分钟，编译错误和第一个测试点的错误不计
+        let problems = [problem("A", None), problem("B", None), problem("C", None)];
+        let icpc = score(
+            &submissions,
+            &problems,
+            Rules::Icpc {
+                penalty_minutes: ICPC_PENALTY_MINUTES,
+            },
+        );
+        assert_eq!(icpc.points, 2.0);
+        assert_eq!(icpc.penalty, 10 + 10 + 20);
+        assert_eq!(icpc.problems[2], (None, 1, 0.0));
+
+        // CF：2 小时的比赛每分钟扣满分的 1/250，每次错误扣 50 分
+        let problems = [
+            problem("A", Some(500.0)),
+            problem("B", Some(1000.0)),
+            problem("C", Some(1500.0)),
+        ];
+        let cf = score(&submissions, &problems, Rules::Cf { duration: 7200 });
+        assert_eq!(cf.problems[0], (Some(600), 1, 500.0 - 20.0 - 50.0));
+        assert_eq!(cf.problems[1], (Some(1200), 0, 1000.0 - 80.0));
+        assert_eq!(cf.points, 430.0 + 920.0);
+    }
+}
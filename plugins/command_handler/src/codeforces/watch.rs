@@ -0,0 +1,240 @@
+//! 比赛结束后向订阅的群播报群友的 rating 变化
+//!
+//! 定时检查最近结束的比赛，`contest.ratingChanges` 有结果后播报一次，
+//! 播报过的比赛和确认 unrated 的比赛记录在数据库中，重启后不会重复查询。
+
+use std::collections::HashMap;
+
+use kovi::{
+    MsgEvent,
+    chrono::Utc,
+    log::{error, info},
+};
+
+use crate::{
+    BOT,
+    codeforces::api::{self, RatingChange},
+    sql::{self, announcement::CommitAnnouncementExt, utils::Commit},
+    subscription,
+    utils::group_member_ids,
+};
+
+pub const RATING_CHANGE_TOPIC: &str = "cf_rating_change";
+
+/// 只检查最近这么久之内结束的比赛
+const WATCH_WINDOW_SECONDS: i64 = 3 * 24 * 60 * 60;
+
+/// CF 一般在比赛结束后几个小时内更新 rating，结束超过这么久仍然没有 rating 变化的比赛视为 unrated
+const RATING_PENDING_SECONDS: i64 = 24 * 60 * 60;
+
+/// CF 的段位：(最低 rating, 名称, 颜色)
+const RANKS: &[(i64, &str, &str)] = &[
+    (i64::MIN, "Newbie", "灰"),
+    (1200, "Pupil", "绿"),
+    (1400, "Specialist", "青"),
+    (1600, "Expert", "蓝"),
+    (1900, "Candidate Master", "紫"),
+    (2100, "Master", "橙"),
+    (2300, "International Master", "橙"),
+    (2400, "Grandmaster", "红"),
+    (2600, "International Grandmaster", "红"),
+    (3000, "Legendary Grandmaster", "红"),
+];
+
+fn rank_of(rating: i64) -> usize {
+    RANKS
+        .iter()
+        .rposition(|(min, _, _)| rating >= *min)
+        .unwrap_or(0)
+}
+
+/// 订阅或取消订阅比赛 rating 变化播报
+pub async fn subscribe(event: &MsgEvent, subscribe: bool) {
+    subscription::toggle(
+        event,
+        RATING_CHANGE_TOPIC,
+        subscribe,
+        "CF 比赛 rating 变化播报",
+    )
+    .await;
+}
+
+/// 检查最近结束的比赛，rating 已经更新的就播报
+pub async fn check_rating_changes() {
+    let groups = match sql::subscription::get_subscribed_groups(RATING_CHANGE_TOPIC).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("获取 rating 播报订阅失败: {}", e);
+            return;
+        }
+    };
+
+    if groups.is_empty() {
+        return;
+    }
+
+    let contests = match api::contest_list(false).await {
+        Ok(contests) => contests,
+        Err(e) => {
+            error!("获取比赛列表失败: {}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    for (contest, end) in contests.iter().filter_map(|contest| {
+        let end = contest.start_time_seconds? + contest.duration_seconds;
+        (contest.phase == "FINISHED" && now - end < WATCH_WINDOW_SECONDS).then_some((contest, end))
+    }) {
+        match sql::announcement::is_announced(contest.id).await {
+            Ok(false) => {}
+            Ok(true) => continue,
+            Err(e) => {
+                error!("查询比赛 {} 的播报记录失败: {}", contest.id, e);
+                continue;
+            }
+        }
+
+        let changes = match api::contest_rating_changes(contest.id).await {
+            // rating 还没有更新，或者是 unrated 的比赛
+            Ok(changes) if changes.is_empty() => {
+                if now - end >= RATING_PENDING_SECONDS {
+                    mark_announced(contest.id).await;
+                }
+                continue;
+            }
+            Ok(changes) => changes,
+            // CF 明确拒绝了这场比赛，比如比赛不计 rating
+            Err(e) if e.is_contest_rejected() => {
+                mark_announced(contest.id).await;
+                continue;
+            }
+            Err(e) => {
+                error!("获取比赛 {} 的 rating 变化失败: {}", contest.id, e);
+                continue;
+            }
+        };
+
+        announce(&groups, &contest.name, &changes).await;
+        mark_announced(contest.id).await;
+    }
+}
+
+/// 记录比赛已经处理过，之后不再查询
+async fn mark_announced(contest_id: i64) {
+    let result = async {
+        let mut commit = Commit::start().await?;
+        commit.mark_announced(contest_id).await?;
+        commit.commit().await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        error!("记录比赛 {} 的播报失败: {}", contest_id, e);
+    }
+}
+
+async fn announce(groups: &[i64], contest_name: &str, changes: &[RatingChange]) {
+    let users = match sql::duel::user::get_bound_users().await {
+        Ok(users) => users,
+        Err(e) => {
+            error!("获取绑定用户失败: {}", e);
+            return;
+        }
+    };
+
+    let changes = changes
+        .iter()
+        .map(|change| (change.handle.to_lowercase(), change))
+        .collect::<HashMap<_, _>>();
+
+    let bot = BOT.get().unwrap();
+
+    for &group_id in groups {
+        let members = match group_member_ids(group_id).await {
+            Ok(members) => members,
+            Err(e) => {
+                error!("获取群 {} 成员失败: {}", group_id, e);
+                continue;
+            }
+        };
+
+        let group_changes = users
+            .iter()
+            .filter(|user| members.contains(&user.qq))
+            .filter_map(|user| changes.get(&user.cf_id.as_ref()?.to_lowercase()))
+            .copied()
+            .collect::<Vec<_>>();
+        if group_changes.is_empty() {
+            continue;
+        }
+
+        info!(
+            "send rating changes of {} to group: {}",
+            contest_name, group_id
+        );
+        bot.send_group_msg(group_id, format_changes(contest_name, group_changes));
+    }
+}
+
+/// 按 rating 变化从高到低列出每个人的排名和 rating，升段的额外标出
+fn format_changes(contest_name: &str, mut changes: Vec<&RatingChange>) -> String {
+    changes.sort_by_key(|change| std::cmp::Reverse(change.new_rating - change.old_rating));
+
+    let mut msg = format!("{} 的 rating 已更新：", contest_name);
+    for (i, change) in changes.iter().enumerate() {
+        let delta = change.new_rating - change.old_rating;
+        msg.push_str(&format!(
+            "\n{}. {} 第 {} 名，{} → {} ({:+})",
+            i + 1,
+            change.handle,
+            change.rank,
+            change.old_rating,
+            change.new_rating,
+            delta
+        ));
+
+        let (old, new) = (rank_of(change.old_rating), rank_of(change.new_rating));
+        if new > old {
+            let (_, title, color) = RANKS[new];
+            if color != RANKS[old].2 {
+                msg.push_str(&format!("，升为 {}，变{}了！", title, color));
+            } else {
+                msg.push_str(&format!("，升为 {}！", title));
+            }
+        }
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(handle: &str, rank: i64, old_rating: i64, new_rating: i64) -> RatingChange {
+        RatingChange {
+            contest_id: 1,
+            contest_name: "Round".to_string(),
+            handle: handle.to_string(),
+            rank,
+            rating_update_time_seconds: 0,
+            old_rating,
+            new_rating,
+        }
+    }
+
+    #[test]
+    fn test_format_changes() {
+        let a = change("a", 300, 1580, 1620);
+        let b = change("b", 100, 2250, 2310);
+        let c = change("c", 900, 1450, 1400);
+        let msg = format_changes("Round", vec![&a, &b, &c]);
+        assert_eq!(
+            msg,
+            "Round 的 rating 已更新：\n\
+             1. b 第 100 名，2250 → 2310 (+60)，升为 International Master！\n\
+             2. a 第 300 名，1580 → 1620 (+40)，升为 Expert，变蓝了！\n\
+             3. c 第 900 名，1450 → 1400 (-50)"
+        );
+    }
+}
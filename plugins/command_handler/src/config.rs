@@ -1,16 +1,42 @@
+use std::collections::HashMap;
 use std::sync::LazyLock;
 
 use kovi::serde_json::{self, Value};
 
+use crate::duel::tags::default_tag_alias;
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct Config {
     pub py_analyzer_path: String,
+    /// 标签别名，键为别名，值为 CF 中的标签名
+    #[serde(default = "default_tag_alias")]
+    pub tag_alias: HashMap<String, String>,
+    /// 每日一题只从这个题单中选题，不设置时从整个题库中选
+    #[serde(default)]
+    pub daily_pool: Option<String>,
+    /// CF API 的 key 和 secret，设置后所有请求都会签名，可以访问私有的 gym 和 mashup
+    #[serde(default)]
+    pub cf_api_key: Option<String>,
+    #[serde(default)]
+    pub cf_api_secret: Option<String>,
+    /// 做题喜报中，题目 rating 比用户高出至少这么多才播报
+    #[serde(default = "default_brag_rating_gap")]
+    pub brag_rating_gap: i64,
+}
+
+fn default_brag_rating_gap() -> i64 {
+    300
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             py_analyzer_path: "python3".to_string(),
+            tag_alias: default_tag_alias(),
+            daily_pool: None,
+            cf_api_key: None,
+            cf_api_secret: None,
+            brag_rating_gap: default_brag_rating_gap(),
         }
     }
 }
@@ -22,7 +48,13 @@ pub static COMMAND: LazyLock<Value> = LazyLock::new(|| {
             "daily": {
                 "problem": "daily_problem",
                 "ranklist": "daily_ranklist",
-                "finish": "daily_finish"
+                "finish": "daily_finish",
+                "personal": {
+                    "problem": "personal_daily_problem",
+                    "finish": "personal_daily_finish"
+                },
+                "subscribe": "daily_subscribe",
+                "unsubscribe": "daily_unsubscribe"
             },
             "accept": "accept",
             "decline": "decline",
@@ -43,7 +75,55 @@ pub static COMMAND: LazyLock<Value> = LazyLock::new(|| {
         "cf": {
             "rating": "cf_rating",
             "analyze": "cf_analyze",
-            "recommend": "cf_recommend"
+            "recommend": "cf_recommend",
+            "compare": "cf_compare",
+            "predict": "cf_predict",
+            "standings": "cf_standings",
+            "upsolve": "cf_upsolve",
+            "plan": "cf_plan",
+            "group": {
+                "analyze": "cf_group_analyze"
+            },
+            "tags": "cf_tags",
+            "problem": "cf_problem",
+            "search": "cf_search",
+            "blacklist": {
+                "add": "blacklist_add",
+                "remove": "blacklist_remove",
+                "list": "blacklist_list"
+            },
+            "pool": {
+                "add": "pool_add",
+                "import": "pool_import",
+                "remove": "pool_remove",
+                "list": "pool_list"
+            },
+            "virtual": {
+                "create": "virtual_create",
+                "join": "virtual_join",
+                "leave": "virtual_leave",
+                "cancel": "virtual_cancel",
+                "status": "virtual_status"
+            },
+            "notify": {
+                "subscribe": "cf_notify_subscribe",
+                "unsubscribe": "cf_notify_unsubscribe"
+            },
+            "brag": {
+                "subscribe": "cf_brag_subscribe",
+                "unsubscribe": "cf_brag_unsubscribe"
+            },
+            "problemset": {
+                "refresh": "problemset_refresh",
+                "status": "problemset_status"
+            }
+        },
+        "contest": {
+            "host": "mashup_host",
+            "join": "mashup_join",
+            "leave": "mashup_leave",
+            "board": "mashup_board",
+            "cancel": "mashup_cancel"
         },
         "at": {
             "rating": "at_rating"
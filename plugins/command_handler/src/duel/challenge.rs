@@ -8,6 +8,7 @@ use rand::seq::IndexedRandom;
 use sqlx::sqlite::SqliteRow;
 use sqlx::{Decode, Encode, FromRow, Row, Sqlite, Type};
 
+use crate::duel::filter::{Atom, ProblemFilter};
 use crate::duel::problem::{Problem, get_problems_by};
 use crate::duel::submission::{Submission, SubmissionError, get_last_submission};
 
@@ -15,6 +16,7 @@ use crate::sql;
 use crate::sql::duel::challenge::CommitChallengeExt;
 use crate::sql::duel::user::CommitUserExt;
 use crate::sql::utils::Commit;
+use crate::utils::elo_win_probability;
 
 #[derive(Clone)]
 pub struct Challenge {
@@ -133,8 +135,7 @@ impl Challenge {
     ///
     /// - `user1` 用户 1 的 ID
     /// - `user2` 用户 2 的 ID
-    /// - `rating` 评分
-    /// - `tags` 题目标签
+    /// - `tags` 题目筛选条件，必须包含难度或题单
     ///
     /// ## 返回值
     ///
@@ -142,7 +143,6 @@ impl Challenge {
     pub async fn from_args(
         user1: i64,
         user2: i64,
-        rating: i64,
         tags: Vec<String>,
     ) -> Result<(Self, String, String)> {
         if user1 == user2 {
@@ -163,9 +163,14 @@ impl Challenge {
             return Err(anyhow!("你或对方正在决斗中"));
         }
 
-        if !(800..=3500).contains(&rating) || rating % 100 != 0 {
-            return Err(anyhow!("rating 应该是 800 到 3500 之间的整数"));
-        }
+        // 只从题单中选题时可以不指定难度，题单中的 gym 题目没有难度
+        let filter = ProblemFilter::parse(&tags)?;
+        filter.check_ratings()?;
+        let rating = match filter.min_rating() {
+            Some(rating) => rating,
+            None if !filter.pools().is_empty() => 0,
+            None => return Err(anyhow!("参数非法：需要提供题目难度或题单")),
+        };
 
         let time = chrono::Utc::now();
 
@@ -184,13 +189,24 @@ impl Challenge {
         Ok((challenge, u1_cf_id, u2_cf_id))
     }
 
+    /// 题目筛选条件
+    /// 旧版本的对局把难度单独存在 rating 中，这里补上
+    fn filter(&self) -> Result<ProblemFilter> {
+        let filter = ProblemFilter::parse(&self.tags)?;
+        if filter.has_rating() || !filter.pools().is_empty() {
+            Ok(filter)
+        } else {
+            Ok(filter.and(Atom::Rating(self.rating, self.rating)))
+        }
+    }
+
     #[inline]
     pub fn is_started(&self) -> bool {
         !matches!(self.status, ChallengeStatus::Pending)
     }
 
     pub async fn start(&mut self) -> Result<Arc<Problem>> {
-        let problems = get_problems_by(&self.tags, self.rating, self.user1).await?;
+        let problems = get_problems_by(&self.filter()?, self.user1).await?;
         let problem = problems
             .choose(&mut rand::rng())
             .ok_or_else(|| anyhow::anyhow!("没有找到题目"))?;
@@ -327,7 +343,7 @@ impl Challenge {
     }
 
     pub async fn change(&mut self) -> Result<Arc<Problem>> {
-        let problems = get_problems_by(&self.tags, self.rating, self.user1).await?;
+        let problems = get_problems_by(&self.filter()?, self.user1).await?;
         let problem = problems
             .choose(&mut rand::rng())
             .ok_or_else(|| anyhow::anyhow!("没有找到题目"))?;
@@ -348,8 +364,8 @@ fn calculate_elo_rating(rating1: i64, rating2: i64, result: bool) -> (i64, i64)
     let r1 = rating1 as f64;
     let r2 = rating2 as f64;
 
-    let e1 = 1.0 / (1.0 + 10.0f64.powf((r2 - r1) / 400.0));
-    let e2 = 1.0 / (1.0 + 10.0f64.powf((r1 - r2) / 400.0));
+    let e1 = elo_win_probability(rating1, rating2);
+    let e2 = elo_win_probability(rating2, rating1);
 
     let s1 = if result { 1.0 } else { 0.0 };
     let s2 = if result { 0.0 } else { 1.0 };
@@ -1,46 +1,14 @@
 // 在（国际象棋等的）大师级比赛中，ELO Rating 的 K 值一般是 16 或 32
 // 但是参加 duel 的都是我们代码部队的国际伟大大师或者传奇伟大大师，所以将 K 设为 128
 pub const ELO_K: f64 = 128.0;
+// 单挑和随机选题的难度范围，也是没有指定难度时的默认范围
+pub const MIN_PROBLEM_RATING: i64 = 800;
+pub const MAX_PROBLEM_RATING: i64 = 3500;
 // 最大每日一题难度
 pub const MAX_DAILY_RATING: i64 = 1200;
-
-pub const TAGS: &[&str] = &[
-    "binary search",
-    "bitmasks",
-    "brute force",
-    "chinese remainder theorem",
-    "combinatorics",
-    "constructive algorithms",
-    "data structures",
-    "dfs and similar",
-    "divide and conquer",
-    "dp",
-    "dsu",
-    "expression parsing",
-    "fft",
-    "flows",
-    "games",
-    "geometry",
-    "graph matchings",
-    "graphs",
-    "greedy",
-    "hashing",
-    "implementation",
-    "interactive",
-    "math",
-    "matrices",
-    "meet-in-the-middle",
-    "number theory",
-    "probabilities",
-    "schedules",
-    "shortest paths",
-    "sortings",
-    "string suffix structures",
-    "strings",
-    "ternary search",
-    "trees",
-    "two pointers",
-    "*special",
-    "not-seen",
-    "new",
-];
+// 个人每日一题：题目难度与用户 rating 相当时获得的分数
+pub const PERSONAL_DAILY_BASE_SCORE: f64 = 1000.0;
+// 个人每日一题：获取不到用户 CF rating 时使用的默认 rating
+pub const DEFAULT_PERSONAL_RATING: i64 = 1000;
+// 每周播报每日任务排行时展示的人数
+pub const WEEKLY_ANNOUNCE_TOP: usize = 5;
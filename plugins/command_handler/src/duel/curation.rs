@@ -0,0 +1,124 @@
+//! 题目黑名单与题单
+//!
+//! 黑名单的每条规则都是一个筛选表达式（语法见 [`crate::duel::filter`]），
+//! 命中任意一条规则的题目不会被随机选中；题单是管理员整理的题目集合，
+//! 可以在筛选表达式中用 `pool:名字` 引用。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+
+use anyhow::{Result, anyhow};
+use kovi::log::warn;
+use kovi::tokio::sync::RwLock;
+
+use crate::duel::filter::{FilterContext, ProblemFilter};
+use crate::duel::problem::{Problem, get_problems};
+use crate::duel::tags::valid_tags;
+use crate::sql;
+
+/// 一条黑名单规则
+#[derive(Debug, Clone)]
+pub struct BlacklistRule {
+    pub id: i64,
+    pub rule: String,
+    pub reason: String,
+    filter: ProblemFilter,
+}
+
+/// 解析后的黑名单
+#[derive(Debug, Default)]
+pub struct Blacklist {
+    pub rules: Vec<BlacklistRule>,
+}
+
+impl Blacklist {
+    pub fn contains(&self, problem: &Problem) -> bool {
+        let ctx = FilterContext::default();
+        self.rules
+            .iter()
+            .any(|rule| rule.filter.matches(problem, &ctx))
+    }
+}
+
+static BLACKLIST: LazyLock<RwLock<Option<Arc<Blacklist>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// 解析一条黑名单规则
+///
+/// 规则对所有人生效，所以不能依赖个人做题记录（not-seen），也不能引用题单
+pub fn parse_rule<S: AsRef<str>>(args: &[S]) -> Result<ProblemFilter> {
+    let filter = ProblemFilter::parse(args)?;
+    if filter == ProblemFilter::default() {
+        return Err(anyhow!("规则不能为空"));
+    }
+    if filter.needs_seen() {
+        return Err(anyhow!("黑名单规则不能使用 not-seen"));
+    }
+    if !filter.pools().is_empty() {
+        return Err(anyhow!("黑名单规则不能引用题单"));
+    }
+    Ok(filter)
+}
+
+/// 获取黑名单，第一次调用或黑名单修改后从数据库加载
+pub async fn get_blacklist() -> Result<Arc<Blacklist>> {
+    if let Some(blacklist) = BLACKLIST.read().await.as_ref() {
+        return Ok(blacklist.clone());
+    }
+
+    // 规则中的标签要等题库加载后才能识别
+    get_problems().await?;
+
+    let mut cache = BLACKLIST.write().await;
+    if let Some(blacklist) = cache.as_ref() {
+        return Ok(blacklist.clone());
+    }
+
+    let rules = sql::duel::curation::get_blacklist()
+        .await?
+        .into_iter()
+        .filter_map(|(id, rule, reason)| {
+            // 标签别名等配置可能已经改变，解析失败的规则跳过
+            let words = rule.split_whitespace().collect::<Vec<_>>();
+            match parse_rule(&words) {
+                Ok(filter) => Some(BlacklistRule {
+                    id,
+                    rule,
+                    reason,
+                    filter,
+                }),
+                Err(e) => {
+                    warn!("黑名单规则 #{} ({}) 解析失败，已跳过: {}", id, rule, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let blacklist = Arc::new(Blacklist { rules });
+    // 题库没加载成功时规则可能解析错了，不缓存，下次重新解析
+    if !valid_tags().is_empty() {
+        *cache = Some(blacklist.clone());
+    }
+    Ok(blacklist)
+}
+
+/// 黑名单修改后调用，下次使用时重新加载
+pub async fn invalidate_blacklist() {
+    *BLACKLIST.write().await = None;
+}
+
+/// 加载筛选条件中引用的题单，题单不存在时报错
+pub async fn load_pools(names: &[&str]) -> Result<HashMap<String, HashSet<(i64, String)>>> {
+    let mut pools = HashMap::new();
+    for &name in names {
+        if pools.contains_key(name) {
+            continue;
+        }
+        let pool = sql::duel::curation::get_pool(name).await?;
+        if pool.is_empty() {
+            return Err(anyhow!("题单 {} 不存在", name));
+        }
+        pools.insert(name.to_string(), pool);
+    }
+    Ok(pools)
+}
@@ -0,0 +1,255 @@
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use kovi::chrono::{Datelike, Duration, Local, NaiveDate};
+use kovi::log::{error, info};
+use kovi::tokio::sync::Mutex;
+
+use crate::BOT;
+use crate::codeforces::recommend::{RecommendDifficulty, recommend_problems};
+use crate::duel::config::{
+    DEFAULT_PERSONAL_RATING, MAX_DAILY_RATING, PERSONAL_DAILY_BASE_SCORE, WEEKLY_ANNOUNCE_TOP,
+};
+use crate::duel::problem::Problem;
+use crate::duel::user::User;
+use crate::sql;
+use crate::sql::duel::daily::CommitPersonalDailyExt;
+use crate::sql::utils::Commit;
+use crate::utils::{elo_win_probability, get_user_rating, group_member_ids};
+
+/// 每周每日任务播报的订阅主题
+pub const WEEKLY_TOPIC: &str = "daily_weekly";
+
+/// 每日任务排行榜的统计区间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyPeriod {
+    /// 本周（从周一开始）
+    Week,
+    /// 本月（从 1 号开始）
+    Month,
+    /// 全部
+    All,
+}
+
+impl FromStr for DailyPeriod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "week" | "weekly" | "周" | "本周" => Ok(Self::Week),
+            "month" | "monthly" | "月" | "本月" => Ok(Self::Month),
+            "all" | "全部" => Ok(Self::All),
+            _ => Err(anyhow::anyhow!("统计区间应为 week, month 或 all")),
+        }
+    }
+}
+
+impl DailyPeriod {
+    /// 区间起始日期，`All` 没有起始日期
+    pub fn start_date(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Week => {
+                Some(today - Duration::days(today.weekday().num_days_from_monday() as i64))
+            }
+            Self::Month => today.with_day(1),
+            Self::All => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Week => "本周",
+            Self::Month => "本月",
+            Self::All => "总",
+        }
+    }
+}
+
+/// 一段时间内某个用户的每日任务成绩
+#[derive(Debug, Clone)]
+pub struct DailyRank {
+    pub qq: i64,
+    pub cf_id: Option<String>,
+    pub score: i64,
+    pub solved: i64,
+}
+
+/// 获取 `period` 区间内的每日任务排行
+pub async fn get_daily_rank(period: DailyPeriod) -> anyhow::Result<Vec<DailyRank>> {
+    let today = Local::now().date_naive();
+    match period.start_date(today) {
+        Some(start) => {
+            let since = start.format("%Y-%m-%d").to_string();
+            sql::duel::daily::get_daily_rank_since(&since).await
+        }
+        None => {
+            // 总榜使用用户表中的累计积分，其中包含每日记录出现之前的历史积分
+            let users = sql::duel::user::get_top_20_daily().await?;
+            Ok(users
+                .into_iter()
+                .map(|user| DailyRank {
+                    qq: user.qq,
+                    cf_id: user.cf_id,
+                    score: user.daily_score,
+                    solved: 0,
+                })
+                .collect())
+        }
+    }
+}
+
+/// 向订阅的群播报本周每日任务的前几名
+pub async fn announce_weekly() {
+    let groups = match sql::subscription::get_subscribed_groups(WEEKLY_TOPIC).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("获取每周播报订阅失败: {}", e);
+            return;
+        }
+    };
+
+    if groups.is_empty() {
+        return;
+    }
+
+    let ranks = match get_daily_rank(DailyPeriod::Week).await {
+        Ok(ranks) => ranks,
+        Err(e) => {
+            error!("获取本周每日任务排行失败: {}", e);
+            return;
+        }
+    };
+
+    let bot = BOT.get().unwrap();
+
+    for group_id in groups {
+        let members = match group_member_ids(group_id).await {
+            Ok(members) => members,
+            Err(e) => {
+                error!("获取群 {} 成员失败: {}", group_id, e);
+                continue;
+            }
+        };
+
+        let top = ranks
+            .iter()
+            .filter(|rank| members.contains(&rank.qq))
+            .take(WEEKLY_ANNOUNCE_TOP)
+            .collect::<Vec<_>>();
+
+        let msg = if top.is_empty() {
+            "本周还没有人完成每日任务，下周加油！".to_string()
+        } else {
+            top.iter().enumerate().fold(
+                "本周每日任务完成情况：\n".to_string(),
+                |mut acc, (i, rank)| {
+                    acc.push_str(&format!(
+                        "{}. {} 完成 {} 题，得分 {}\n",
+                        i + 1,
+                        rank.cf_id.as_deref().unwrap_or("未绑定"),
+                        rank.solved,
+                        rank.score
+                    ));
+                    acc
+                },
+            )
+        };
+
+        info!("send weekly daily ranklist to group: {}", group_id);
+        bot.send_group_msg(group_id, msg);
+    }
+}
+
+/// 用户的个人每日一题
+#[derive(Debug, Clone)]
+pub struct PersonalDaily {
+    pub qq: i64,
+    pub time: String,
+    pub problem: Problem,
+    /// 出题时用户的 CF rating
+    pub user_rating: i64,
+    /// 完成后获得的分数，未完成为 None
+    pub score: Option<i64>,
+}
+
+impl PersonalDaily {
+    /// 按题目难度相对用户 rating 的高低计算得分
+    ///
+    /// 使用 ELO 的期望胜率：题目越难，解出的期望越低，得分越高。
+    /// 题目难度与用户 rating 相当时得到 `PERSONAL_DAILY_BASE_SCORE` 分，
+    /// 和公共每日一题计入同一个积分，所以最多不超过公共每日一题的 `MAX_DAILY_RATING` 分
+    pub fn calc_score(&self) -> i64 {
+        let problem_rating = self.problem.rating.unwrap_or(self.user_rating);
+        let solve_probability = elo_win_probability(self.user_rating, problem_rating);
+        ((PERSONAL_DAILY_BASE_SCORE * 2.0 * (1.0 - solve_probability)).round() as i64)
+            .min(MAX_DAILY_RATING)
+    }
+}
+
+/// 获取用户今天的个人每日一题，没有则根据用户 rating 和薄弱标签挑选一道
+pub async fn get_personal_daily(user: &User) -> anyhow::Result<PersonalDaily> {
+    static PERSONAL_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    let _lock = PERSONAL_LOCK.lock().await;
+
+    let now = Local::now().format("%Y-%m-%d").to_string();
+    if let Some(daily) = sql::duel::daily::get_personal_daily(user.qq, &now).await? {
+        return Ok(daily);
+    }
+
+    let cf_id = user
+        .cf_id
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("你好像没有绑定 CF 账号哦"))?;
+
+    let user_rating = get_user_rating(cf_id)
+        .await
+        .unwrap_or(DEFAULT_PERSONAL_RATING);
+
+    let problem = recommend_problems(cf_id, user_rating, RecommendDifficulty::Moderate, 1)
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("没有找到题目，请稍后再试"))?;
+
+    let daily = PersonalDaily {
+        qq: user.qq,
+        time: now,
+        problem: problem.as_ref().clone(),
+        user_rating,
+        score: None,
+    };
+
+    Commit::start()
+        .await?
+        .set_personal_daily(&daily)
+        .await?
+        .commit()
+        .await?;
+
+    Ok(daily)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily(problem_rating: i64, user_rating: i64) -> PersonalDaily {
+        PersonalDaily {
+            qq: 1,
+            time: "2026-01-01".to_string(),
+            problem: Problem::new(1, "A".to_string(), Some(problem_rating), vec![]),
+            user_rating,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_calc_score() {
+        assert_eq!(
+            daily(1500, 1500).calc_score(),
+            PERSONAL_DAILY_BASE_SCORE as i64
+        );
+        assert!(daily(1300, 1500).calc_score() < PERSONAL_DAILY_BASE_SCORE as i64);
+        // 再难也不超过公共每日一题的最高分
+        assert_eq!(daily(2500, 1500).calc_score(), MAX_DAILY_RATING);
+    }
+}
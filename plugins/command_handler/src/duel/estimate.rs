@@ -0,0 +1,301 @@
+//! 估计没有官方难度的题目的 rating
+//!
+//! 用已有难度的题目做最小二乘拟合，特征为通过人数的对数、题目序号和比赛的 Div：
+//! 通过人数越少、序号越靠后越难；同样的序号，Div 越高的比赛题目越简单。
+//! Div 从比赛名称中识别，估计值只保存在内存中，每次刷新题库后重新计算。
+//! 启动时先使用不带估计值的缓存题库，避免请求比赛列表拖慢启动。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use kovi::log::warn;
+
+use crate::codeforces::api;
+use crate::duel::problem::Problem;
+
+/// 至少需要这么多道有难度的题目才进行拟合
+const MIN_SAMPLES: usize = 100;
+const MIN_ESTIMATE: i64 = 800;
+const MAX_ESTIMATE: i64 = 3500;
+
+/// 识别不出 Div 的比赛按 Div. 2 处理，这是最常见的比赛
+const DEFAULT_DIVISION: f64 = 2.0;
+/// 岭回归的正则系数，所有比赛都识别不出 Div 时 Div 和常数项共线，正则项保证方程可解
+const RIDGE: f64 = 1e-3;
+
+const FEATURES: usize = 4;
+
+/// 从比赛名称中识别 Div
+///
+/// Div. 1 + Div. 2 和 Global Round 记为 1.5，Educational Round 按 Div. 2 算
+pub fn contest_division(name: &str) -> Option<f64> {
+    let name = name
+        .to_lowercase()
+        .replace("div.", "div")
+        .replace("division", "div");
+    if name.contains("div 1 + div 2") || name.contains("div 1 + 2") || name.contains("global round")
+    {
+        Some(1.5)
+    } else if name.contains("div 1") {
+        Some(1.0)
+    } else if name.contains("div 2") || name.contains("educational") {
+        Some(2.0)
+    } else if name.contains("div 3") {
+        Some(3.0)
+    } else if name.contains("div 4") {
+        Some(4.0)
+    } else {
+        None
+    }
+}
+
+/// 所有能识别出 Div 的比赛，获取比赛列表失败时为空，这时所有比赛都按默认的 Div 处理
+pub async fn contest_divisions() -> HashMap<i64, f64> {
+    match api::contest_list(false).await {
+        Ok(contests) => contests
+            .iter()
+            .filter_map(|contest| Some((contest.id, contest_division(&contest.name)?)))
+            .collect(),
+        Err(e) => {
+            warn!("获取比赛列表失败，估计难度时不区分 Div: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+fn features(problem: &Problem, divisions: &HashMap<i64, f64>) -> Option<[f64; FEATURES]> {
+    let solved = problem.solved_count?;
+    let letter = problem.index.chars().next()?.to_ascii_uppercase();
+    if !letter.is_ascii_uppercase() {
+        return None;
+    }
+
+    Some([
+        1.0,
+        ((solved + 1) as f64).ln(),
+        (letter as u8 - b'A') as f64,
+        divisions
+            .get(&problem.contest_id)
+            .copied()
+            .unwrap_or(DEFAULT_DIVISION),
+    ])
+}
+
+/// 拟合得到的线性模型
+#[derive(Debug, Clone, Copy)]
+pub struct Estimator<'a> {
+    weights: [f64; FEATURES],
+    divisions: &'a HashMap<i64, f64>,
+}
+
+impl<'a> Estimator<'a> {
+    /// 用有难度且有通过人数的题目拟合，样本不足时返回 None
+    pub fn fit(problems: &[Arc<Problem>], divisions: &'a HashMap<i64, f64>) -> Option<Self> {
+        let samples = problems
+            .iter()
+            .filter_map(|problem| Some((features(problem, divisions)?, problem.rating? as f64)))
+            .collect::<Vec<_>>();
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        // 正规方程 (XᵀX) w = Xᵀy
+        let mut a = [[0.0; FEATURES]; FEATURES];
+        let mut b = [0.0; FEATURES];
+        for (x, y) in samples.iter() {
+            for i in 0..FEATURES {
+                for j in 0..FEATURES {
+                    a[i][j] += x[i] * x[j];
+                }
+                b[i] += x[i] * y;
+            }
+        }
+        // 常数项不加正则
+        for (i, row) in a.iter_mut().enumerate().skip(1) {
+            row[i] += RIDGE * samples.len() as f64;
+        }
+
+        solve(a, b).map(|weights| Self { weights, divisions })
+    }
+
+    /// 估计题目难度，取整到 100 并限制在 CF 的难度范围内
+    pub fn estimate(&self, problem: &Problem) -> Option<i64> {
+        let x = features(problem, self.divisions)?;
+        let rating = x
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(x, w)| x * w)
+            .sum::<f64>();
+        if !rating.is_finite() {
+            return None;
+        }
+        Some(((rating / 100.0).round() as i64 * 100).clamp(MIN_ESTIMATE, MAX_ESTIMATE))
+    }
+}
+
+/// 高斯消元，矩阵奇异时返回 None
+fn solve(mut a: [[f64; FEATURES]; FEATURES], mut b: [f64; FEATURES]) -> Option<[f64; FEATURES]> {
+    for col in 0..FEATURES {
+        let pivot = (col..FEATURES).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        for row in 0..FEATURES {
+            if row == col {
+                continue;
+            }
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            for (x, p) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *x -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; FEATURES];
+    for i in 0..FEATURES {
+        x[i] = b[i] / a[i][i];
+    }
+    Some(x)
+}
+
+/// 为题库中没有难度的题目填上估计值，`divisions` 见 [`contest_divisions`]
+pub fn fill_estimates(problems: &mut [Arc<Problem>], divisions: &HashMap<i64, f64>) -> usize {
+    let Some(estimator) = Estimator::fit(problems, divisions) else {
+        return 0;
+    };
+
+    let mut count = 0;
+    for problem in problems
+        .iter_mut()
+        .filter(|problem| problem.rating.is_none())
+    {
+        let estimated = estimator.estimate(problem);
+        if estimated.is_some() {
+            count += 1;
+        }
+        if problem.estimated_rating != estimated {
+            Arc::make_mut(problem).estimated_rating = estimated;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(contest_id: i64, index: &str, rating: Option<i64>, solved: i64) -> Arc<Problem> {
+        let mut problem = Problem::new(contest_id, index.to_string(), rating, vec![]);
+        problem.solved_count = Some(solved);
+        Arc::new(problem)
+    }
+
+    #[test]
+    fn test_fill_estimates() {
+        // 难度严格按照 3000 - 200 ln(solved+1) + 100 * 序号 生成
+        let truth = |index: u8, solved: i64| {
+            3000.0 - 200.0 * ((solved + 1) as f64).ln() + 100.0 * index as f64
+        };
+
+        let mut problems = Vec::new();
+        for contest_id in 1000..1050 {
+            for index in 0..4u8 {
+                let solved = 100 + (contest_id * 37 + index as i64 * 101) % 5000;
+                let letter = ((b'A' + index) as char).to_string();
+                let rating = truth(index, solved).round() as i64;
+                problems.push(problem(contest_id, &letter, Some(rating), solved));
+            }
+        }
+        problems.push(problem(1025, "B", None, 1000));
+        problems.push(problem(1025, "C", None, 1000));
+
+        assert_eq!(fill_estimates(&mut problems, &HashMap::new()), 2);
+
+        let expected = |index: u8| ((truth(index, 1000) / 100.0).round() as i64) * 100;
+        let estimated = problems
+            .iter()
+            .filter(|problem| problem.rating.is_none())
+            .map(|problem| problem.estimated_rating)
+            .collect::<Vec<_>>();
+        assert_eq!(estimated, vec![Some(expected(1)), Some(expected(2))]);
+
+        // 有难度的题目不会被估计
+        assert!(
+            problems
+                .iter()
+                .filter(|problem| problem.rating.is_some())
+                .all(|problem| problem.estimated_rating.is_none())
+        );
+    }
+
+    #[test]
+    fn test_too_few_samples() {
+        let mut problems = vec![problem(1, "A", Some(800), 10000), problem(1, "B", None, 10)];
+        assert_eq!(fill_estimates(&mut problems, &HashMap::new()), 0);
+        assert_eq!(problems[1].estimated_rating, None);
+    }
+
+    #[test]
+    fn test_division_feature() {
+        // 同样的通过人数和序号，Div. 1 比 Div. 3 难 600
+        let truth = |division: f64, index: u8, solved: i64| {
+            3000.0 - 200.0 * ((solved + 1) as f64).ln() + 100.0 * index as f64 - 300.0 * division
+        };
+
+        let mut divisions = HashMap::new();
+        let mut problems = Vec::new();
+        for contest_id in 0..60 {
+            let division = [1.0, 2.0, 3.0][contest_id as usize % 3];
+            divisions.insert(contest_id, division);
+            for index in 0..4u8 {
+                let solved = 100 + (contest_id * 37 + index as i64 * 101) % 5000;
+                let letter = ((b'A' + index) as char).to_string();
+                let rating = truth(division, index, solved).round() as i64;
+                problems.push(problem(contest_id, &letter, Some(rating), solved));
+            }
+        }
+        problems.push(problem(0, "E", None, 1000));
+        problems.push(problem(2, "E", None, 1000));
+
+        assert_eq!(fill_estimates(&mut problems, &divisions), 2);
+        let estimated = problems
+            .iter()
+            .filter_map(|problem| problem.estimated_rating)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            estimated,
+            vec![
+                ((truth(1.0, 4, 1000) / 100.0).round() as i64) * 100,
+                ((truth(3.0, 4, 1000) / 100.0).round() as i64) * 100
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contest_division() {
+        assert_eq!(
+            contest_division("Codeforces Round 1000 (Div. 2)"),
+            Some(2.0)
+        );
+        assert_eq!(
+            contest_division("Codeforces Round #800 (Div. 1)"),
+            Some(1.0)
+        );
+        assert_eq!(
+            contest_division("Codeforces Round 934 (Div. 1 + Div. 2)"),
+            Some(1.5)
+        );
+        assert_eq!(contest_division("Codeforces Global Round 25"), Some(1.5));
+        assert_eq!(
+            contest_division("Educational Codeforces Round 160 (Rated for Div. 2)"),
+            Some(2.0)
+        );
+        assert_eq!(contest_division("Codeforces Round 900 (Div. 3)"), Some(3.0));
+        assert_eq!(contest_division("Kotlin Heroes: Episode 10"), None);
+    }
+}
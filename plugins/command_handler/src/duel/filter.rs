@@ -0,0 +1,693 @@
+//! 题目筛选语言
+//!
+//! `/duel problem`、`/duel challenge` 和 `/cf recommend` 共用的筛选表达式，
+//! 解析一次得到语法树，之后对每道题目求值。
+//!
+//! 语法：
+//! - 空格分隔的条件表示「且」，`|` 表示「或」，`!` 表示「非」，可以用括号分组
+//! - `dp`、`data structures`、`data_structures`、`数据结构`：标签，支持配置中的别名
+//! - `1600`、`1600-1900`、`rating:1600-1900`：难度或难度范围
+//! - `contest:1900`、`contest:1800-1900`：比赛 id 或比赛 id 范围
+//! - `solved:1000`、`solved>=1000`：最少通过人数
+//! - `idx:A`、`idx:A-C`：题目序号范围
+//! - `1900D`：指定题目
+//! - `pool:name`：管理员整理的题单中的题目
+//! - `new`：比赛 id 大于 1000 的题目
+//! - `not-seen`：自己没有通过的题目
+//! - `estimated`：难度条件也匹配没有官方难度、只有估计难度的题目
+//!
+//! 例如 `(dp|greedy) !math 1600-1900 idx:A-C`
+
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+
+use anyhow::{Result, anyhow};
+
+use crate::duel::problem::{Problem, parse_problem_id};
+
+use super::config::{MAX_PROBLEM_RATING, MIN_PROBLEM_RATING};
+use super::tags::{MAX_TAG_WORDS, check_tag, is_tag, normalize_tag};
+
+/// 题目筛选条件的语法树
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Atom(Atom),
+}
+
+/// 不可再分的筛选条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Atom {
+    Tag(String),
+    /// 难度范围（闭区间）
+    Rating(i64, i64),
+    /// 比赛 id 范围（闭区间）
+    Contest(i64, i64),
+    /// 最少通过人数
+    MinSolved(i64),
+    /// 题目序号范围（闭区间），比较时只看序号的字母部分
+    Index(String, String),
+    /// 指定题目
+    Problem(i64, String),
+    /// 题单中的题目
+    Pool(String),
+    New,
+    NotSeen,
+}
+
+/// 求值时需要的额外信息
+#[derive(Debug, Default)]
+pub struct FilterContext {
+    /// 用户通过的题目，只有筛选条件中包含 `not-seen` 时才需要
+    pub seen: Option<HashSet<(i64, String)>>,
+    /// 筛选条件中用到的题单
+    pub pools: HashMap<String, HashSet<(i64, String)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Word(String),
+}
+
+/// 一条完整的筛选表达式，空表达式匹配所有题目
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProblemFilter {
+    root: Option<Filter>,
+    /// 是否使用估计难度，由表达式中的 `estimated` 开启
+    estimated: bool,
+}
+
+impl ProblemFilter {
+    /// 从指令参数解析筛选表达式
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Self> {
+        let text = args
+            .iter()
+            .map(|arg| arg.as_ref())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (tokens, estimated) = take_estimated_flag(tokenize(&text));
+
+        if tokens.is_empty() {
+            return Ok(Self {
+                root: None,
+                estimated,
+            });
+        }
+
+        let mut tokens = tokens.into_iter().peekable();
+        let root = parse_or(&mut tokens)?;
+        if let Some(token) = tokens.next() {
+            return Err(anyhow!("筛选条件中有多余的 {}", token_str(&token)));
+        }
+
+        Ok(Self {
+            root: Some(root),
+            estimated,
+        })
+    }
+
+    /// 在当前表达式上追加一个「且」条件
+    pub fn and(self, atom: Atom) -> Self {
+        let root = match self.root {
+            None => Filter::Atom(atom),
+            Some(Filter::And(mut list)) => {
+                list.push(Filter::Atom(atom));
+                Filter::And(list)
+            }
+            Some(root) => Filter::And(vec![root, Filter::Atom(atom)]),
+        };
+        Self {
+            root: Some(root),
+            estimated: self.estimated,
+        }
+    }
+
+    /// 是否使用估计难度
+    pub fn uses_estimated(&self) -> bool {
+        self.estimated
+    }
+
+    /// 表达式中是否出现了难度条件
+    pub fn has_rating(&self) -> bool {
+        self.any_atom(&|atom| matches!(atom, Atom::Rating(..)))
+    }
+
+    /// 表达式中是否出现了 `not-seen`
+    pub fn needs_seen(&self) -> bool {
+        self.any_atom(&|atom| matches!(atom, Atom::NotSeen))
+    }
+
+    /// 表达式中用到的题单
+    pub fn pools(&self) -> Vec<&str> {
+        let mut pools = Vec::new();
+        if let Some(root) = &self.root {
+            visit_atoms(root, &mut |atom| {
+                if let Atom::Pool(name) = atom {
+                    pools.push(name.as_str());
+                }
+            });
+        }
+        pools
+    }
+
+    /// 表达式中出现的难度区间
+    pub fn ratings(&self) -> Vec<(i64, i64)> {
+        let mut ratings = Vec::new();
+        if let Some(root) = &self.root {
+            visit_atoms(root, &mut |atom| {
+                if let Atom::Rating(lo, hi) = atom {
+                    ratings.push((*lo, *hi));
+                }
+            });
+        }
+        ratings
+    }
+
+    /// 表达式中出现的最低难度，用于展示
+    pub fn min_rating(&self) -> Option<i64> {
+        self.ratings().into_iter().map(|(lo, _)| lo).min()
+    }
+
+    /// 检查表达式中的难度都是 800 到 3500 之间的整百数
+    pub fn check_ratings(&self) -> Result<()> {
+        let valid = |rating: i64| {
+            (MIN_PROBLEM_RATING..=MAX_PROBLEM_RATING).contains(&rating) && rating % 100 == 0
+        };
+        if self
+            .ratings()
+            .iter()
+            .all(|&(lo, hi)| valid(lo) && valid(hi))
+        {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "rating 应该是 {} 到 {} 之间的整数",
+                MIN_PROBLEM_RATING,
+                MAX_PROBLEM_RATING
+            ))
+        }
+    }
+
+    /// 没有难度条件也没有题单时限制在默认的难度范围内，避免选到没有难度的题
+    pub fn with_default_rating(self) -> Self {
+        if self.has_rating() || !self.pools().is_empty() {
+            self
+        } else {
+            self.and(Atom::Rating(MIN_PROBLEM_RATING, MAX_PROBLEM_RATING))
+        }
+    }
+
+    pub fn matches(&self, problem: &Problem, ctx: &FilterContext) -> bool {
+        self.root
+            .as_ref()
+            .is_none_or(|root| eval(root, problem, ctx, self.estimated))
+    }
+
+    fn any_atom(&self, f: &dyn Fn(&Atom) -> bool) -> bool {
+        let mut found = false;
+        if let Some(root) = &self.root {
+            visit_atoms(root, &mut |atom| found |= f(atom));
+        }
+        found
+    }
+}
+
+fn visit_atoms<'a>(filter: &'a Filter, f: &mut dyn FnMut(&'a Atom)) {
+    match filter {
+        Filter::And(list) | Filter::Or(list) => list.iter().for_each(|sub| visit_atoms(sub, f)),
+        Filter::Not(sub) => visit_atoms(sub, f),
+        Filter::Atom(atom) => f(atom),
+    }
+}
+
+fn eval(filter: &Filter, problem: &Problem, ctx: &FilterContext, estimated: bool) -> bool {
+    match filter {
+        Filter::And(list) => list.iter().all(|sub| eval(sub, problem, ctx, estimated)),
+        Filter::Or(list) => list.iter().any(|sub| eval(sub, problem, ctx, estimated)),
+        Filter::Not(sub) => !eval(sub, problem, ctx, estimated),
+        Filter::Atom(atom) => eval_atom(atom, problem, ctx, estimated),
+    }
+}
+
+fn eval_atom(atom: &Atom, problem: &Problem, ctx: &FilterContext, estimated: bool) -> bool {
+    match atom {
+        Atom::Tag(tag) => problem.tags.iter().any(|t| t == tag),
+        Atom::Rating(lo, hi) => problem
+            .rating_with_estimate(estimated)
+            .is_some_and(|r| (*lo..=*hi).contains(&r)),
+        Atom::Contest(lo, hi) => (*lo..=*hi).contains(&problem.contest_id),
+        Atom::MinSolved(min) => problem.solved_count.is_some_and(|c| c >= *min),
+        Atom::Index(lo, hi) => {
+            let index = index_letters(&problem.index);
+            lo.as_str() <= index && index <= hi.as_str()
+        }
+        Atom::Problem(contest_id, index) => {
+            problem.contest_id == *contest_id && problem.index == *index
+        }
+        Atom::Pool(name) => ctx
+            .pools
+            .get(name)
+            .is_some_and(|pool| pool.contains(&(problem.contest_id, problem.index.clone()))),
+        Atom::New => problem.contest_id > 1000,
+        Atom::NotSeen => ctx
+            .seen
+            .as_ref()
+            .is_none_or(|seen| !seen.contains(&(problem.contest_id, problem.index.clone()))),
+    }
+}
+
+fn is_estimated_flag(word: &str) -> bool {
+    matches!(word.to_lowercase().as_str(), "estimated" | "估计")
+}
+
+/// 取出最外层单独出现的 `estimated`，它是对整个表达式生效的开关，不参与求值
+///
+/// 出现在括号里或者 `!`、`|` 旁边的 `estimated` 留在原处，解析时报错
+fn take_estimated_flag(tokens: Vec<Token>) -> (Vec<Token>, bool) {
+    let mut kept = Vec::with_capacity(tokens.len());
+    let mut estimated = false;
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Word(word) if is_estimated_flag(word) => {
+                let standalone = depth == 0
+                    && !matches!(kept.last(), Some(Token::Not | Token::Or))
+                    && tokens.get(i + 1) != Some(&Token::Or);
+                if standalone {
+                    estimated = true;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        kept.push(token.clone());
+    }
+    (kept, estimated)
+}
+
+/// 题目序号的字母部分，`C1` -> `C`
+fn index_letters(index: &str) -> &str {
+    let end = index
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(index.len());
+    &index[..end]
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+
+    let flush = |word: &mut String, tokens: &mut Vec<Token>| {
+        if !word.is_empty() {
+            tokens.push(Token::Word(std::mem::take(word)));
+        }
+    };
+
+    for c in text.chars() {
+        match c {
+            '(' | '（' => {
+                flush(&mut word, &mut tokens);
+                tokens.push(Token::LParen);
+            }
+            ')' | '）' => {
+                flush(&mut word, &mut tokens);
+                tokens.push(Token::RParen);
+            }
+            '|' => {
+                flush(&mut word, &mut tokens);
+                tokens.push(Token::Or);
+            }
+            // `!` 只有出现在单词开头时才表示「非」
+            '!' | '！' if word.is_empty() => tokens.push(Token::Not),
+            c if c.is_whitespace() => flush(&mut word, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    flush(&mut word, &mut tokens);
+
+    merge_tag_words(tokens)
+}
+
+/// 把被空格拆开的多词标签（如 `data structures`）合并为一个单词
+fn merge_tag_words(tokens: Vec<Token>) -> Vec<Token> {
+    let mut merged = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let words = tokens[i..]
+            .iter()
+            .take(MAX_TAG_WORDS)
+            .map_while(|token| match token {
+                Token::Word(word) => Some(word.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let len = (2..=words.len())
+            .rev()
+            .find(|&len| is_tag(&words[..len].join(" ")));
+
+        match len {
+            Some(len) => {
+                merged.push(Token::Word(words[..len].join(" ")));
+                i += len;
+            }
+            None => {
+                merged.push(tokens[i].clone());
+                i += 1;
+            }
+        }
+    }
+    merged
+}
+
+fn token_str(token: &Token) -> &str {
+    match token {
+        Token::LParen => "(",
+        Token::RParen => ")",
+        Token::Or => "|",
+        Token::Not => "!",
+        Token::Word(word) => word,
+    }
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Filter> {
+    let mut list = vec![parse_and(tokens)?];
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        list.push(parse_and(tokens)?);
+    }
+    Ok(if list.len() == 1 {
+        list.pop().unwrap()
+    } else {
+        Filter::Or(list)
+    })
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Filter> {
+    let mut list = vec![parse_unary(tokens)?];
+    while matches!(
+        tokens.peek(),
+        Some(Token::Word(_) | Token::Not | Token::LParen)
+    ) {
+        list.push(parse_unary(tokens)?);
+    }
+    Ok(if list.len() == 1 {
+        list.pop().unwrap()
+    } else {
+        Filter::And(list)
+    })
+}
+
+fn parse_unary(tokens: &mut Tokens) -> Result<Filter> {
+    match tokens.next() {
+        Some(Token::Not) => Ok(Filter::Not(Box::new(parse_unary(tokens)?))),
+        Some(Token::LParen) => {
+            let inner = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::RParen) => Ok(inner),
+                _ => Err(anyhow!("筛选条件中的括号没有闭合")),
+            }
+        }
+        Some(Token::Word(word)) => Ok(Filter::Atom(parse_atom(&word)?)),
+        Some(token) => Err(anyhow!("筛选条件中 {} 的位置不对", token_str(&token))),
+        None => Err(anyhow!("筛选条件不完整")),
+    }
+}
+
+fn parse_atom(word: &str) -> Result<Atom> {
+    if is_estimated_flag(word) {
+        return Err(anyhow!(
+            "{word} 是对整个筛选条件生效的开关，只能单独写在最外层，不能放在括号里或与 !、| 一起使用"
+        ));
+    }
+
+    let lower = word.to_lowercase();
+
+    if let Some(range) = lower.strip_prefix("rating:") {
+        let (lo, hi) = parse_num_range(range).ok_or_else(|| anyhow!("{word} 不是合法的难度"))?;
+        return Ok(Atom::Rating(lo, hi));
+    }
+    if let Some((lo, hi)) = parse_num_range(&lower) {
+        return Ok(Atom::Rating(lo, hi));
+    }
+    if let Some(range) = lower.strip_prefix("contest:") {
+        let (lo, hi) = parse_num_range(range).ok_or_else(|| anyhow!("{word} 不是合法的比赛 id"))?;
+        return Ok(Atom::Contest(lo, hi));
+    }
+    if let Some(count) = lower
+        .strip_prefix("solved>=")
+        .or_else(|| lower.strip_prefix("solved:"))
+    {
+        let count = count
+            .parse()
+            .map_err(|_| anyhow!("{word} 不是合法的通过人数"))?;
+        return Ok(Atom::MinSolved(count));
+    }
+    if let Some(range) = lower.strip_prefix("idx:") {
+        let range = range.to_uppercase();
+        let (lo, hi) = match range.split_once('-') {
+            Some((lo, hi)) => (lo.to_string(), hi.to_string()),
+            None => (range.clone(), range.clone()),
+        };
+        let valid = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphabetic());
+        if !valid(&lo) || !valid(&hi) || lo > hi {
+            return Err(anyhow!("{word} 不是合法的题目序号范围"));
+        }
+        return Ok(Atom::Index(lo, hi));
+    }
+
+    // 显式写出的标签，用于 /cf search 等会把单词当作关键词的地方
+    if let Some(tag) = lower.strip_prefix("tag:") {
+        let tag = normalize_tag(tag);
+        check_tag(&tag)?;
+        return Ok(Atom::Tag(tag));
+    }
+
+    if let Some(name) = lower.strip_prefix("pool:") {
+        if name.is_empty() {
+            return Err(anyhow!("{word} 缺少题单名"));
+        }
+        return Ok(Atom::Pool(name.to_string()));
+    }
+    if let Some((contest_id, index)) = parse_problem_id(word) {
+        return Ok(Atom::Problem(contest_id, index));
+    }
+
+    let tag = normalize_tag(word);
+    match tag.as_str() {
+        "new" => Ok(Atom::New),
+        "not-seen" => Ok(Atom::NotSeen),
+        tag => {
+            check_tag(tag)?;
+            Ok(Atom::Tag(tag.to_string()))
+        }
+    }
+}
+
+/// 解析 `1600` 或 `1600-1900`
+pub fn parse_num_range(s: &str) -> Option<(i64, i64)> {
+    let (lo, hi) = match s.split_once('-') {
+        Some((lo, hi)) => (lo.parse().ok()?, hi.parse().ok()?),
+        None => {
+            let num = s.parse().ok()?;
+            (num, num)
+        }
+    };
+    (lo <= hi).then_some((lo, hi))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duel::tags::init_test_tags;
+
+    fn problem(contest_id: i64, index: &str, rating: Option<i64>, tags: &[&str]) -> Problem {
+        let mut problem = Problem::new(
+            contest_id,
+            index.to_string(),
+            rating,
+            tags.iter().map(|t| t.to_string()).collect(),
+        );
+        problem.solved_count = Some(1000);
+        problem
+    }
+
+    fn parse(s: &str) -> ProblemFilter {
+        init_test_tags();
+        ProblemFilter::parse(&s.split_whitespace().collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let filter = parse("(dp|greedy) !math 1600-1900");
+        assert_eq!(
+            filter.root,
+            Some(Filter::And(vec![
+                Filter::Or(vec![
+                    Filter::Atom(Atom::Tag("dp".to_string())),
+                    Filter::Atom(Atom::Tag("greedy".to_string())),
+                ]),
+                Filter::Not(Box::new(Filter::Atom(Atom::Tag("math".to_string())))),
+                Filter::Atom(Atom::Rating(1600, 1900)),
+            ]))
+        );
+
+        // 多词标签不需要下划线
+        let filter = parse("dfs and similar | data structures !dp");
+        assert_eq!(
+            filter.root,
+            Some(Filter::Or(vec![
+                Filter::Atom(Atom::Tag("dfs and similar".to_string())),
+                Filter::And(vec![
+                    Filter::Atom(Atom::Tag("data structures".to_string())),
+                    Filter::Not(Box::new(Filter::Atom(Atom::Tag("dp".to_string())))),
+                ]),
+            ]))
+        );
+
+        // `|` 的优先级低于空格
+        let filter = parse("dp 1600 | greedy");
+        assert!(matches!(filter.root, Some(Filter::Or(ref list)) if list.len() == 2));
+    }
+
+    #[test]
+    fn test_parse_atoms() {
+        init_test_tags();
+        assert_eq!(parse_atom("1500").unwrap(), Atom::Rating(1500, 1500));
+        assert_eq!(
+            parse_atom("rating:1600-1900").unwrap(),
+            Atom::Rating(1600, 1900)
+        );
+        assert_eq!(
+            parse_atom("contest:1800-1900").unwrap(),
+            Atom::Contest(1800, 1900)
+        );
+        assert_eq!(parse_atom("solved>=500").unwrap(), Atom::MinSolved(500));
+        assert_eq!(parse_atom("solved:500").unwrap(), Atom::MinSolved(500));
+        assert_eq!(
+            parse_atom("idx:a-c").unwrap(),
+            Atom::Index("A".to_string(), "C".to_string())
+        );
+        assert_eq!(
+            parse_atom("data_structures").unwrap(),
+            Atom::Tag("data structures".to_string())
+        );
+        assert_eq!(
+            parse_atom("tag:Data_structures").unwrap(),
+            Atom::Tag("data structures".to_string())
+        );
+        assert_eq!(parse_atom("not-seen").unwrap(), Atom::NotSeen);
+        assert_eq!(parse_atom("new").unwrap(), Atom::New);
+        assert_eq!(
+            parse_atom("1900d1").unwrap(),
+            Atom::Problem(1900, "D1".to_string())
+        );
+        assert_eq!(
+            parse_atom("pool:Weekly").unwrap(),
+            Atom::Pool("weekly".to_string())
+        );
+
+        assert!(parse_atom("1900-1600").is_err());
+        assert!(parse_atom("idx:C-A").is_err());
+        assert!(parse_atom("dpp").is_err());
+        assert!(parse_atom("tag:new").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        init_test_tags();
+        assert!(ProblemFilter::parse(&["(dp"]).is_err());
+        assert!(ProblemFilter::parse(&["dp)"]).is_err());
+        assert!(ProblemFilter::parse(&["dp", "|"]).is_err());
+        assert!(ProblemFilter::parse(&["!"]).is_err());
+        assert!(ProblemFilter::parse(&["!estimated", "dp"]).is_err());
+        assert!(ProblemFilter::parse(&["(estimated)", "dp"]).is_err());
+        assert!(ProblemFilter::parse(&["dp", "|", "estimated"]).is_err());
+        assert_eq!(
+            ProblemFilter::parse::<&str>(&[]).unwrap(),
+            ProblemFilter::default()
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let ctx = FilterContext::default();
+        let a = problem(1900, "A", Some(800), &["greedy", "math"]);
+        let c1 = problem(1900, "C1", Some(1700), &["dp"]);
+        let e = problem(500, "E", Some(2400), &["dp", "graphs"]);
+
+        let filter = parse("(dp|greedy) !math");
+        assert!(!filter.matches(&a, &ctx));
+        assert!(filter.matches(&c1, &ctx));
+        assert!(filter.matches(&e, &ctx));
+
+        let filter = parse("idx:A-C new");
+        assert!(filter.matches(&a, &ctx));
+        assert!(filter.matches(&c1, &ctx));
+        assert!(!filter.matches(&e, &ctx));
+
+        let filter = parse("1600-1900 | contest:1-600 solved>=2000");
+        assert!(filter.matches(&c1, &ctx));
+        assert!(!filter.matches(&e, &ctx));
+
+        let ctx = FilterContext {
+            seen: Some(HashSet::from([(1900, "A".to_string())])),
+            pools: HashMap::from([(
+                "weekly".to_string(),
+                HashSet::from([(500, "E".to_string())]),
+            )]),
+        };
+        let filter = parse("not-seen");
+        assert!(!filter.matches(&a, &ctx));
+        assert!(filter.matches(&c1, &ctx));
+
+        let filter = parse("pool:weekly | 1900A");
+        assert!(filter.matches(&a, &ctx));
+        assert!(!filter.matches(&c1, &ctx));
+        assert!(filter.matches(&e, &ctx));
+
+        // 估计难度只在开启 estimated 时参与难度比较
+        let mut unrated = problem(1900, "B", None, &["dp"]);
+        unrated.estimated_rating = Some(1200);
+        assert!(!parse("1200 dp").matches(&unrated, &ctx));
+        let filter = parse("1200 estimated dp");
+        assert!(filter.uses_estimated());
+        assert!(filter.matches(&unrated, &ctx));
+        assert!(!filter.matches(&c1, &ctx));
+    }
+
+    #[test]
+    fn test_filter_info() {
+        let filter = parse("dp !(1000|1200-1300) not-seen");
+        assert!(filter.has_rating());
+        assert!(filter.needs_seen());
+        assert_eq!(filter.min_rating(), Some(1000));
+
+        let filter = parse("dp").and(Atom::Rating(1500, 1500));
+        assert!(filter.has_rating());
+        assert!(!filter.needs_seen());
+
+        assert!(parse("1200-1900 dp").check_ratings().is_ok());
+        assert!(parse("1250 dp").check_ratings().is_err());
+        assert!(parse("600-1000").check_ratings().is_err());
+        assert!(parse("3400-4000").check_ratings().is_err());
+
+        assert_eq!(
+            parse("dp").with_default_rating().ratings(),
+            vec![(800, 3500)]
+        );
+        assert_eq!(
+            parse("1200 dp").with_default_rating().ratings(),
+            vec![(1200, 1200)]
+        );
+    }
+}
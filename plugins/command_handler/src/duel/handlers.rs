@@ -8,17 +8,24 @@ use kovi::{
 use rand::seq::IndexedRandom;
 
 use crate::{
-    duel::problem::format_problem_link,
+    duel::problem::{Problem, format_problem_link},
     sql::{
         self,
-        duel::{challenge::CommitChallengeExt, user::CommitUserExt},
+        duel::{
+            challenge::CommitChallengeExt,
+            daily::{CommitDailyExt, CommitPersonalDailyExt},
+            user::CommitUserExt,
+        },
         utils::Commit,
     },
+    subscription,
     utils::{IdOrText, user_id_or_text},
 };
 
 use super::{
     challenge::{Challenge, ChallengeStatus},
+    daily::{DailyPeriod, WEEKLY_TOPIC, get_daily_rank, get_personal_daily},
+    filter::ProblemFilter,
     user::BindingUsers,
 };
 
@@ -33,18 +40,36 @@ fn handle_error(event: &MsgEvent, e: anyhow::Error) {
 //
 
 /// 显示每日任务排行榜
-pub async fn daily_ranklist(event: &MsgEvent) {
-    match sql::duel::user::get_top_20_daily().await {
+///
+/// 用法：/duel daily ranklist [week|month|all]，默认为 all
+pub async fn daily_ranklist(event: &MsgEvent, args: &[String]) {
+    let period = match args.get(3) {
+        Some(arg) => match arg.parse::<DailyPeriod>() {
+            Ok(period) => period,
+            Err(e) => {
+                event.reply(format!("参数非法：{}", e));
+                return;
+            }
+        },
+        None => DailyPeriod::All,
+    };
+
+    match get_daily_rank(period).await {
         Ok(ranklist) => {
-            let mut result = "每日任务排行榜：(只显示前20)\n".to_string();
-            for (i, user) in ranklist.iter().enumerate() {
-                let default_str = "未绑定".to_string();
-                result.push_str(&format!(
-                    "{}. {} score: {}\n",
-                    i + 1,
-                    user.cf_id.as_ref().unwrap_or(&default_str),
-                    user.daily_score
-                ));
+            let mut result = format!("{}每日任务排行榜：(只显示前20)\n", period.name());
+            for (i, rank) in ranklist.iter().take(20).enumerate() {
+                let cf_id = rank.cf_id.as_deref().unwrap_or("未绑定");
+                if period == DailyPeriod::All {
+                    result.push_str(&format!("{}. {} score: {}\n", i + 1, cf_id, rank.score));
+                } else {
+                    result.push_str(&format!(
+                        "{}. {} score: {} solved: {}\n",
+                        i + 1,
+                        cf_id,
+                        rank.score,
+                        rank.solved
+                    ));
+                }
             }
             event.reply(result);
         }
@@ -52,6 +77,11 @@ pub async fn daily_ranklist(event: &MsgEvent) {
     }
 }
 
+/// 订阅或取消订阅每周每日任务播报
+pub async fn daily_subscribe(event: &MsgEvent, subscribe: bool) {
+    subscription::toggle(event, WEEKLY_TOPIC, subscribe, "每周每日任务播报").await;
+}
+
 /// 显示总排行榜
 pub async fn rating_ranklist(event: &MsgEvent) {
     match sql::duel::user::get_top_20_ranklist().await {
@@ -241,13 +271,14 @@ pub async fn daily_finish(event: &MsgEvent) {
     let problem = &submission.problem;
 
     // 检查是否完成了正确的题目
-    if !problem.same_problem(&daily_problem) || submission.is_accepted() {
+    if !problem.same_problem(&daily_problem) || !submission.is_accepted() {
         event.reply("未发现通过记录");
         return;
     }
 
     // 更新用户分数
-    user.daily_score += daily_problem.rating.unwrap();
+    let score = daily_problem.rating.unwrap();
+    user.daily_score += score;
     user.last_daily = now;
 
     // 提交更改
@@ -256,6 +287,8 @@ pub async fn daily_finish(event: &MsgEvent) {
             .await?
             .update_user_daily(&user)
             .await?
+            .add_daily_log(user.qq, &user.last_daily, &daily_problem, score)
+            .await?
             .commit()
             .await?;
         anyhow::Ok(())
@@ -265,14 +298,112 @@ pub async fn daily_finish(event: &MsgEvent) {
         Ok(_) => {
             event.reply(format!(
                 "你今天完成了每日任务，获得了 {} 分\n你现在的总分为 {}",
-                daily_problem.rating.unwrap(),
-                user.daily_score
+                score, user.daily_score
             ));
         }
         Err(e) => handle_error(event, anyhow::anyhow!(e)),
     }
 }
 
+/// 获取个人每日一题
+pub async fn personal_daily_problem(event: &MsgEvent) {
+    let user = match sql::duel::user::get_user(event.user_id).await {
+        Ok(user) if user.cf_id.is_some() => user,
+        _ => {
+            event.reply("你好像没有绑定 CF 账号哦");
+            return;
+        }
+    };
+
+    match get_personal_daily(&user).await {
+        Ok(daily) => {
+            let link = format_problem_link(daily.problem.contest_id, &daily.problem.index);
+            let status = match daily.score {
+                Some(score) => format!("已完成，获得了 {} 分", score),
+                None => format!("完成后可获得 {} 分", daily.calc_score()),
+            };
+            event.reply(format!(
+                "你今天的个人每日一题（按 rating {} 挑选）：\n{}\n{}",
+                daily.user_rating, link, status
+            ));
+        }
+        Err(e) => handle_error(event, e),
+    }
+}
+
+/// 完成个人每日一题
+pub async fn personal_daily_finish(event: &MsgEvent) {
+    let mut user = match sql::duel::user::get_user(event.user_id).await {
+        Ok(user) if user.cf_id.is_some() => user,
+        _ => {
+            event.reply("你好像没有绑定 CF 账号哦");
+            return;
+        }
+    };
+
+    let now = Local::now().format("%Y-%m-%d").to_string();
+    let mut daily = match sql::duel::daily::get_personal_daily(user.qq, &now).await {
+        Ok(Some(daily)) => daily,
+        Ok(None) => {
+            event.reply("你今天还没有领取个人每日一题，请先输入 /duel daily personal problem");
+            return;
+        }
+        Err(e) => {
+            event.reply(format!("获取个人每日一题失败: {}", e));
+            return;
+        }
+    };
+
+    if daily.score.is_some() {
+        event.reply("你今天已经完成了个人每日一题");
+        return;
+    }
+
+    // 获取最新提交
+    let cf_id = user.cf_id.as_ref().unwrap();
+    let submission = match super::submission::get_last_submission(cf_id).await {
+        Ok(submission) => submission,
+        Err(e) => {
+            event.reply("获取提交记录失败");
+            debug!("获取提交记录失败: {}", e);
+            return;
+        }
+    };
+
+    if !submission.problem.same_problem(&daily.problem) || !submission.is_accepted() {
+        event.reply("未发现通过记录");
+        return;
+    }
+
+    let score = daily.calc_score();
+    daily.score = Some(score);
+    user.daily_score += score;
+
+    match async {
+        Commit::start()
+            .await?
+            .finish_personal_daily(&daily)
+            .await?
+            .update_user_daily(&user)
+            .await?
+            .add_daily_log(user.qq, &now, &daily.problem, score)
+            .await?
+            .commit()
+            .await?;
+        anyhow::Ok(())
+    }
+    .await
+    {
+        Ok(_) => {
+            event.reply(format!(
+                "你完成了个人每日一题，获得了 {} 分\n你现在的总分为 {}",
+                score, user.daily_score
+            ));
+        }
+        Err(e) => handle_error(event, e),
+    }
+}
+
 //
 // 题目相关处理器
 //
@@ -290,12 +421,17 @@ pub async fn daily_problem(event: &MsgEvent) {
 
 /// 随机获取符合条件的题目
 pub async fn problem(event: &MsgEvent, args: &[String]) {
-    // 解析参数
-    let rating = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
-    let tags = if args.len() >= 3 { &args[3..] } else { &[] };
+    // 解析筛选条件
+    let filter = match ProblemFilter::parse(args.get(2..).unwrap_or_default()) {
+        Ok(filter) => filter.with_default_rating(),
+        Err(e) => {
+            handle_error(event, e);
+            return;
+        }
+    };
 
     // 获取并选择随机题目
-    let result = super::problem::get_problems_by(tags, rating, event.user_id)
+    let result = super::problem::get_problems_by(&filter, event.user_id)
         .await
         .and_then(|problems| {
             problems
@@ -305,14 +441,22 @@ pub async fn problem(event: &MsgEvent, args: &[String]) {
         });
 
     match result {
-        Ok(problem) => {
-            let link = format_problem_link(problem.contest_id, &problem.index);
-            event.reply(link);
-        }
+        Ok(problem) => event.reply(picked_problem_reply(&problem)),
         Err(e) => handle_error(event, e),
     }
 }
 
+/// 随机选出的题目链接，使用估计难度的题目会附上难度
+fn picked_problem_reply(problem: &Problem) -> String {
+    let link = format_problem_link(problem.contest_id, &problem.index);
+    match problem.rating {
+        None if problem.estimated_rating.is_some() => {
+            format!("{}\n难度：{}", link, problem.rating_text())
+        }
+        _ => link,
+    }
+}
+
 //
 // 决斗相关处理器
 //
@@ -519,24 +663,11 @@ pub async fn challenge(event: &MsgEvent, args: &[String]) {
         }
     };
 
-    // 解析题目难度
-    let rating = match args.get(3).and_then(|s| s.parse::<i64>().ok()) {
-        Some(rating) => rating,
-        None => {
-            event.reply("参数非法：需要提供题目难度");
-            return;
-        }
-    };
-
-    // 解析题目标签
-    let tags = if args.len() >= 4 {
-        args[4..].to_vec()
-    } else {
-        Vec::new()
-    };
+    // 解析题目筛选条件（难度和标签）
+    let tags = args.get(3..).unwrap_or_default().to_vec();
 
     // 创建挑战
-    match Challenge::from_args(user1, user2, rating, tags).await {
+    match Challenge::from_args(user1, user2, tags).await {
         Ok((_chall, u1, u2)) => {
             event.reply(format!(
                 "{} 向 {} 发起了挑战，请输入 /duel accept 接受挑战，或 /duel decline 拒绝挑战",
@@ -620,12 +751,17 @@ pub async fn cancel(event: &MsgEvent) {
 /// 获取随机题目
 #[allow(dead_code)]
 pub async fn random_problem(event: &MsgEvent, args: &[String]) {
-    // 解析参数
-    let rating = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
-    let tags = if args.len() >= 3 { &args[3..] } else { &[] };
+    // 解析筛选条件
+    let filter = match ProblemFilter::parse(args.get(2..).unwrap_or_default()) {
+        Ok(filter) => filter,
+        Err(e) => {
+            handle_error(event, e);
+            return;
+        }
+    };
 
     // 获取并选择随机题目
-    let result = super::problem::get_problems_by(tags, rating, event.user_id)
+    let result = super::problem::get_problems_by(&filter, event.user_id)
         .await
         .and_then(|problems| {
             problems
@@ -635,10 +771,7 @@ pub async fn random_problem(event: &MsgEvent, args: &[String]) {
         });
 
     match result {
-        Ok(problem) => {
-            let link = format_problem_link(problem.contest_id, &problem.index);
-            event.reply(link);
-        }
+        Ok(problem) => event.reply(picked_problem_reply(&problem)),
         Err(e) => handle_error(event, e),
     }
 }
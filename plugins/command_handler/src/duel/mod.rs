@@ -6,18 +6,32 @@ use utils::retry::retry;
 
 pub(crate) mod challenge;
 pub(crate) mod config;
+pub(crate) mod curation;
+pub(crate) mod daily;
+pub(crate) mod estimate;
+pub(crate) mod filter;
 pub(crate) mod handlers;
 pub(crate) mod problem;
 pub(crate) mod submission;
+pub(crate) mod tags;
 pub(crate) mod user;
 
 pub async fn init() {
+    // 先从数据库加载缓存的题库，CF 不可用时也能正常选题
+    match problem::load_problems().await {
+        Ok(count) => info!("从数据库加载了 {} 道题目", count),
+        Err(e) => error!("从数据库加载题库失败: {}", e),
+    }
+
     kovi::spawn(async move {
         // 初始化题库
         match retry(problem::refresh_problems, 3).await {
-            Ok(_) => info!("初始化题库成功"),
+            Ok(diff) => info!("初始化题库成功: {:?}", diff),
             Err(e) => {
                 error!("初始化题库失败: {}", e);
+                // 只能使用数据库中的题库，为它补上估计难度
+                let count = problem::refresh_estimates().await;
+                info!("为 {} 道题目估计了难度", count);
             }
         };
     });
@@ -25,11 +39,17 @@ pub async fn init() {
     plugin::cron("0 0 * * *", || async {
         // 每天 0 点刷新题库
         match retry(problem::refresh_problems, 3).await {
-            Ok(_) => info!("初始化题库成功"),
+            Ok(diff) => info!("刷新题库成功: {:?}", diff),
             Err(e) => {
-                error!("初始化题库失败: {}", e);
+                error!("刷新题库失败: {}", e);
             }
         };
     })
     .unwrap();
+
+    plugin::cron("0 21 * * 0", || async {
+        // 每周日 21 点播报本周每日任务排行
+        daily::announce_weekly().await;
+    })
+    .unwrap();
 }
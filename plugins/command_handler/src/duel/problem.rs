@@ -1,32 +1,47 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, LazyLock};
 
 use anyhow::{Error, Result};
-use kovi::serde_json::{self, Value};
+use kovi::chrono::Utc;
+use kovi::log::error;
+use kovi::serde_json;
 use kovi::tokio::sync::{Mutex, RwLock};
 use rand::seq::{IndexedRandom, IteratorRandom};
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
 
+use crate::CONFIG;
 use crate::duel::config::MAX_DAILY_RATING;
-use crate::duel::submission::get_recent_submissions;
+use crate::duel::curation::{get_blacklist, load_pools};
+use crate::duel::estimate::{contest_divisions, fill_estimates};
+use crate::duel::submission::get_solved_set;
 use crate::sql::duel::problem::CommitProblemExt;
 use crate::sql::utils::Commit;
 
-use super::config::TAGS;
-use crate::utils::fetch;
+use crate::duel::filter::{FilterContext, ProblemFilter};
+use crate::duel::tags::update_tags;
 
 type ProblemSet = Vec<Arc<Problem>>;
 
-const PROBLEM_URL: &str = "https://codeforces.com/api/problemset.problems";
 static PROBLEMS: LazyLock<RwLock<Arc<ProblemSet>>> =
     LazyLock::new(|| RwLock::new(Arc::new(Vec::new())));
+/// 刷新题库和重新计算估计难度时持有，避免两者互相覆盖
+static REFRESH_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
 pub struct Problem {
     #[serde(rename = "contestId")]
     pub contest_id: i64,
     pub index: String,
+    #[serde(default)]
+    pub name: String,
     pub rating: Option<i64>,
     pub tags: Vec<String>,
+    /// 通过人数，来自 problemStatistics
+    #[serde(rename = "solvedCount", default)]
+    pub solved_count: Option<i64>,
+    /// 没有官方难度时的估计值，只保存在内存中，见 [`crate::duel::estimate`]
+    #[serde(skip)]
+    pub estimated_rating: Option<i64>,
 }
 
 impl Problem {
@@ -34,234 +49,299 @@ impl Problem {
         Self {
             contest_id,
             index,
+            name: String::new(),
             rating,
             tags,
+            solved_count: None,
+            estimated_rating: None,
         }
     }
 
+    /// 题目难度，`estimated` 为 true 时没有官方难度的题目使用估计值
+    #[inline]
+    pub fn rating_with_estimate(&self, estimated: bool) -> Option<i64> {
+        self.rating.or(self.estimated_rating.filter(|_| estimated))
+    }
+
+    /// 用于展示的难度，估计值会标注出来
+    pub fn rating_text(&self) -> String {
+        match (self.rating, self.estimated_rating) {
+            (Some(rating), _) => rating.to_string(),
+            (None, Some(estimated)) => format!("~{}（估计）", estimated),
+            (None, None) => "未知".to_string(),
+        }
+    }
+
+    #[inline]
+    pub fn key(&self) -> (i64, String) {
+        (self.contest_id, self.index.clone())
+    }
+
     #[inline]
     pub fn same_problem(&self, other: &Self) -> bool {
         self.contest_id == other.contest_id && self.index == other.index
     }
 }
 
-/// 格式化题目链接
-pub fn format_problem_link(contest_id: i64, index: &str) -> String {
-    format!(
-        "题目链接：https://codeforces.com/problemset/problem/{}/{}",
-        contest_id, index
-    )
+/// 比较时忽略估计难度，它不是题库中的数据
+impl PartialEq for Problem {
+    fn eq(&self, other: &Self) -> bool {
+        self.contest_id == other.contest_id
+            && self.index == other.index
+            && self.name == other.name
+            && self.rating == other.rating
+            && self.tags == other.tags
+            && self.solved_count == other.solved_count
+    }
 }
 
-pub async fn get_problems_by(tags: &[String], rating: i64, qq: i64) -> Result<ProblemSet> {
-    if !(800..=3500).contains(&rating) || rating % 100 != 0 {
-        return Err(anyhow::anyhow!("rating 应该是 800 到 3500 之间的整数"));
+impl<'r> FromRow<'r, SqliteRow> for Problem {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let contest_id: i64 = row.try_get("contest_id")?;
+        let index: String = row.try_get("idx")?;
+        let name: String = row.try_get("name")?;
+        let rating: Option<i64> = row.try_get("rating")?;
+        let tags: String = row.try_get("tags")?;
+        let solved_count: Option<i64> = row.try_get("solved_count")?;
+
+        Ok(Self {
+            contest_id,
+            index,
+            name,
+            rating,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            solved_count,
+            estimated_rating: None,
+        })
     }
+}
 
-    let tags = tags
-        .iter()
-        .map(|tag| tag.replace("_", " "))
-        .collect::<Vec<_>>();
+/// 解析 `1900D`、`1900/D1` 形式的题目编号，返回 (比赛 id, 大写的题目序号)
+pub fn parse_problem_id(s: &str) -> Option<(i64, String)> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let contest_id = s[..split].parse().ok()?;
+    let index = s[split..].strip_prefix('/').unwrap_or(&s[split..]);
 
-    let (pos_tags, nag_tags) = {
-        let mut pos_tags = Vec::new();
-        let mut nag_tags = Vec::new();
-        for tag in tags.iter() {
-            if let Some(tag) = tag.strip_prefix('!') {
-                nag_tags.push(tag);
-            } else {
-                pos_tags.push(tag.as_str());
-            }
-        }
-        (pos_tags, nag_tags)
-    };
+    let mut chars = index.chars();
+    let valid =
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_digit());
+    valid.then(|| (contest_id, index.to_uppercase()))
+}
+
+/// 格式化题目链接
+pub fn format_problem_link(contest_id: i64, index: &str) -> String {
+    format!("题目链接：{}", problem_url(contest_id, index))
+}
+
+/// gym（包括 mashup）的比赛 id 从 100000 开始
+pub const GYM_CONTEST_ID_START: i64 = 100000;
+
+pub fn problem_url(contest_id: i64, index: &str) -> String {
+    if contest_id >= GYM_CONTEST_ID_START {
+        format!(
+            "https://codeforces.com/gym/{}/problem/{}",
+            contest_id, index
+        )
+    } else {
+        format!(
+            "https://codeforces.com/problemset/problem/{}/{}",
+            contest_id, index
+        )
+    }
+}
 
-    check_tags(&pos_tags)?;
-    check_tags(&nag_tags)?;
+pub async fn get_problems_by(filter: &ProblemFilter, qq: i64) -> Result<ProblemSet> {
+    let ctx = filter_context(filter, qq).await?;
+    let blacklist = get_blacklist().await?;
 
     let problems = get_problems().await?;
 
+    // 题单中可能有 gym 或 mashup 的题目，它们不在题库里，只知道题号
+    let known = problems
+        .iter()
+        .map(|problem| problem.key())
+        .collect::<HashSet<_>>();
+    let mut pool_problems = ctx
+        .pools
+        .values()
+        .flatten()
+        .filter(|key| !known.contains(*key))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|(contest_id, index)| Arc::new(Problem::new(*contest_id, index.clone(), None, vec![])))
+        .collect::<Vec<_>>();
+    pool_problems.sort_by_key(|problem| problem.key());
+
     let problems = problems
         .iter()
-        .filter(|problem| problem.rating == Some(rating))
-        .filter(filter_by_nag(&nag_tags, qq).await?)
-        .filter(filter_by_pos(&pos_tags, qq).await?)
+        .chain(pool_problems.iter())
+        .filter(|problem| !blacklist.contains(problem) && filter.matches(problem, &ctx))
         .cloned()
         .collect::<Vec<_>>();
 
     Ok(problems)
 }
 
-async fn filter_by_nag(tags: &[&str], qq: i64) -> Result<impl FnMut(&&Arc<Problem>) -> bool> {
-    let (new, seen, tags) = filter_help(tags, qq).await?;
+/// 准备筛选条件求值时需要的数据
+/// 只有包含 not-seen 时才会去拉取用户的提交记录
+pub async fn filter_context(filter: &ProblemFilter, qq: i64) -> Result<FilterContext> {
+    let pools = load_pools(&filter.pools()).await?;
 
-    let filter = move |problem: &&Arc<Problem>| -> bool {
-        // filter by tags
-        if !tags.is_empty()
-            && tags
-                .iter()
-                .any(|tag| problem.tags.contains(&tag.to_string()))
-        {
-            return false;
-        }
-        // filter by special tags
-        if new && problem.contest_id > 1000 {
-            return false;
-        }
-        if let Some(ref seen) = seen
-            && !seen.contains(&(problem.contest_id, problem.index.clone()))
-        {
-            return false;
-        }
-        true
+    if !filter.needs_seen() {
+        return Ok(FilterContext {
+            pools,
+            ..Default::default()
+        });
+    }
+
+    let Some(cf_id) = crate::sql::duel::user::get_user(qq).await?.cf_id else {
+        return Err(anyhow::anyhow!(
+            "你还没有绑定 CF 账号，不能使用 not-seen 标签"
+        ));
     };
 
-    Ok(filter)
+    let seen = get_solved_set(&cf_id).await.unwrap_or_default();
+
+    Ok(FilterContext {
+        seen: Some(seen),
+        pools,
+    })
 }
 
-async fn filter_by_pos(tags: &[&str], qq: i64) -> Result<impl FnMut(&&Arc<Problem>) -> bool> {
-    let (new, seen, tags) = filter_help(tags, qq).await?;
+#[derive(serde::Deserialize)]
+struct ProblemStatistics {
+    #[serde(rename = "contestId")]
+    contest_id: i64,
+    index: String,
+    #[serde(rename = "solvedCount")]
+    solved_count: i64,
+}
 
-    let filter = move |problem: &&Arc<Problem>| -> bool {
-        // filter by tags
-        if !tags.is_empty()
-            && !tags
-                .iter()
-                .all(|tag| problem.tags.contains(&tag.to_string()))
-        {
-            return false;
-        }
-        // filter by special tags
-        if new && problem.contest_id <= 1000 {
-            return false;
-        }
-        if let Some(ref seen) = seen
-            && seen.contains(&(problem.contest_id, problem.index.clone()))
-        {
-            return false;
-        }
-        true
-    };
+async fn fetch_problems() -> Result<ProblemSet, Error> {
+    let problemset = crate::codeforces::api::problemset().await?;
 
-    Ok(filter)
-}
+    let statistics = problemset
+        .problem_statistics
+        .iter()
+        .filter_map(|v| serde_json::from_value::<ProblemStatistics>(v.clone()).ok())
+        .map(|s| ((s.contest_id, s.index), s.solved_count))
+        .collect::<HashMap<_, _>>();
 
-/// 过滤掉 new 和 not-seen 标签
-/// 以及不合法的标签
-/// 返回值：
-/// - new: 是否有 new 标签
-/// - seen: 已经提交过的题目
-/// - tags: 过滤后的标签
-async fn filter_help<'a>(
-    tags: &[&'a str],
-    qq: i64,
-) -> Result<(bool, Option<HashSet<(i64, String)>>, Vec<&'a str>)> {
-    let mut new = false;
-    let mut not_seen = false;
-
-    let tags = tags
+    let problems = problemset
+        .problems
         .iter()
-        .filter(|tag| {
-            if **tag == "new" {
-                new = true;
-                false
-            } else if **tag == "not-seen" {
-                not_seen = true;
-                false
-            } else {
-                true
-            }
+        .filter_map(|v| serde_json::from_value::<Problem>(v.clone()).ok())
+        .map(|mut problem| {
+            problem.solved_count = statistics.get(&problem.key()).copied();
+            Arc::new(problem)
         })
-        .cloned()
         .collect::<Vec<_>>();
 
-    let seen = if not_seen {
-        let Some(cf_id) = crate::sql::duel::user::get_user(qq).await?.cf_id else {
-            return Err(anyhow::anyhow!(
-                "你还没有绑定 CF 账号，不能使用 not-seen 标签"
-            ));
-        };
-
-        let submissions = get_recent_submissions(&cf_id).await.unwrap_or_default();
-        let seen = submissions
-            .into_iter()
-            .filter(|submission| submission.is_accepted())
-            .map(|submission| {
-                let problem = submission.problem;
-                let contest_id = problem.contest_id;
-                let index = problem.index;
-                (contest_id, index)
-            })
-            .collect::<HashSet<_>>();
-        Some(seen)
-    } else {
-        None
-    };
+    Ok(problems)
+}
 
-    Ok((new, seen, tags))
+/// 一次刷新中题库的变化
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProblemsetDiff {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
 }
 
-fn check_tags(tags: &[&str]) -> Result<()> {
-    for tag in tags {
-        if !TAGS.contains(tag) {
-            let similar = TAGS
-                .iter()
-                .max_by_key(|&&t| (strsim::normalized_damerau_levenshtein(t, tag) * 1000.0) as i64)
-                .unwrap();
-
-            let diff = strsim::normalized_damerau_levenshtein(similar, tag);
-            if diff > 0.6 {
-                return Err(anyhow::anyhow!(
-                    "{tag} 不是一个合法的标签，你是不是想找 {similar}？"
-                ));
-            } else {
-                return Err(anyhow::anyhow!("{tag} 不是一个合法的标签"));
-            }
-        }
+/// 从数据库中加载缓存的题库
+///
+/// 估计难度需要请求比赛列表，这里不计算，由之后的 [`refresh_problems`] 或 [`refresh_estimates`] 填上
+pub async fn load_problems() -> Result<usize, Error> {
+    let problems = crate::sql::duel::problem::load_problemset()
+        .await?
+        .into_iter()
+        .map(Arc::new)
+        .collect::<Vec<_>>();
+    let count = problems.len();
+    if count > 0 {
+        update_tags(&problems);
+        *PROBLEMS.write().await = Arc::new(problems);
     }
-    Ok(())
+    Ok(count)
 }
 
-async fn fetch_problems() -> Result<ProblemSet, Error> {
-    let res = fetch(PROBLEM_URL).await?;
-
-    let body = res.json::<Value>().await?;
-    let status = body
-        .get("status")
-        .and_then(|v| v.as_str())
-        .unwrap_or_default();
-    if status != "OK" {
-        return Err(anyhow::anyhow!("Failed to fetch problems"));
-    };
+/// 从 CF 拉取题库，和数据库中的题库比较后只把变化的部分写入数据库
+pub async fn refresh_problems() -> Result<ProblemsetDiff, Error> {
+    let mut problems = fetch_problems().await?;
 
-    let problems = if let Value::Object(mut map) = body
-        && let Some(Value::Object(mut result)) = map.remove("result")
-        && let Some(Value::Array(problems)) = result.remove("problems")
-    {
-        problems
-            .into_iter()
-            .filter_map(|v| serde_json::from_value(v).ok())
-            .map(Arc::new)
-            .collect::<Vec<_>>()
-    } else {
-        return Err(anyhow::anyhow!("Failed to fetch problems"));
-    };
+    let _lock = REFRESH_LOCK.lock().await;
 
-    Ok(problems)
+    // 和数据库中的题库比较，内存中的题库在重启后第一次加载之前是空的
+    let old = crate::sql::duel::problem::load_problemset()
+        .await?
+        .into_iter()
+        .map(|problem| (problem.key(), problem))
+        .collect::<HashMap<_, _>>();
+
+    let mut diff = ProblemsetDiff::default();
+    let changed = problems
+        .iter()
+        .filter(|problem| match old.get(&problem.key()) {
+            Some(old) if old == problem.as_ref() => false,
+            Some(_) => {
+                diff.updated += 1;
+                true
+            }
+            None => {
+                diff.added += 1;
+                true
+            }
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let keys = problems
+        .iter()
+        .map(|problem| problem.key())
+        .collect::<HashSet<_>>();
+    let removed = old
+        .into_keys()
+        .filter(|key| !keys.contains(key))
+        .collect::<Vec<_>>();
+    diff.removed = removed.len();
+
+    Commit::start()
+        .await?
+        .upsert_problems(&changed)
+        .await?
+        .remove_problems(&removed)
+        .await?
+        .set_problemset_fetched_at(Utc::now())
+        .await?
+        .commit()
+        .await?;
+
+    // 估计值不写入数据库，所以在比较之后再计算
+    fill_estimates(&mut problems, &contest_divisions().await);
+    update_tags(&problems);
+    *PROBLEMS.write().await = Arc::new(problems);
+    Ok(diff)
 }
 
-pub async fn refresh_problems() -> Result<(), Error> {
-    let problems = fetch_problems().await?;
-    let problems = Arc::new(problems);
-    *PROBLEMS.write().await = problems;
-    Ok(())
+/// 为内存中的题库重新计算估计难度，用于刷新题库失败、只有数据库中的题库可用时
+pub async fn refresh_estimates() -> usize {
+    let _lock = REFRESH_LOCK.lock().await;
+    let mut problems = PROBLEMS.read().await.as_ref().clone();
+    if problems.is_empty() {
+        return 0;
+    }
+    let count = fill_estimates(&mut problems, &contest_divisions().await);
+    *PROBLEMS.write().await = Arc::new(problems);
+    count
 }
 
 pub async fn get_problems() -> Result<Arc<ProblemSet>, Error> {
     let problems = PROBLEMS.read().await;
     if problems.is_empty() {
         drop(problems);
-        refresh_problems().await?;
+        if load_problems().await? == 0 {
+            refresh_problems().await?;
+        }
         Ok(PROBLEMS.read().await.clone())
     } else {
         Ok(problems.clone())
@@ -281,12 +361,29 @@ pub async fn get_daily_problem() -> Result<Arc<Problem>, Error> {
     match crate::sql::duel::problem::get_daily_problem().await {
         Ok(problem) => Ok(Arc::new(problem)),
         Err(_) => {
+            let blacklist = get_blacklist().await?;
+            // 题单名保存时统一转成小写，配置中的名字也要转换
+            let pool = match CONFIG.get().and_then(|config| config.daily_pool.as_deref()) {
+                Some(name) => {
+                    let name = name.to_lowercase();
+                    let mut pools = load_pools(&[name.as_str()]).await.inspect_err(|e| {
+                        error!("配置的每日一题题单不可用: {}", e);
+                    })?;
+                    pools.remove(&name)
+                }
+                None => None,
+            };
+
             let problem = match get_problems()
                 .await?
                 .iter()
                 .filter(|problem| {
                     problem.rating.unwrap_or(4000) <= MAX_DAILY_RATING
                         && !problem.tags.iter().any(|tag| tag == "*special")
+                        && !blacklist.contains(problem)
+                        && pool
+                            .as_ref()
+                            .is_none_or(|pool| pool.contains(&problem.key()))
                 })
                 .choose(&mut rand::rng())
                 .cloned()
@@ -1,13 +1,29 @@
-use kovi::serde_json::{self, Value};
+use std::collections::HashSet;
+use std::sync::LazyLock;
 
-use crate::{duel::problem::Problem, utils::fetch};
+use kovi::tokio::sync::Mutex;
+
+use crate::{
+    codeforces::api::{self, CfApiError},
+    duel::problem::Problem,
+    sql::{self, duel::submission::CommitSubmissionExt, utils::Commit},
+};
+
+/// 增量同步时每页拉取的提交数
+const SYNC_PAGE_SIZE: usize = 100;
+/// 单次增量同步最多拉取的页数，超过后直接拉取全部提交
+const SYNC_MAX_PAGES: usize = 20;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
 pub struct Submission {
+    pub id: i64,
     #[serde(rename = "creationTimeSeconds")]
     pub creation_time_seconds: i64,
     pub problem: Problem,
     pub verdict: Option<String>,
+    /// 通过的测试点数，本地缓存中旧版本同步的提交没有记录
+    #[serde(rename = "passedTestCount", default)]
+    pub passed_test_count: Option<i64>,
     pub author: Author,
 }
 
@@ -19,8 +35,10 @@ pub struct Author {
 
 #[derive(thiserror::Error, Debug)]
 pub enum SubmissionError {
-    #[error("Failed to fetch response")]
-    FetchError,
+    #[error(transparent)]
+    Api(#[from] CfApiError),
+    #[error("读取本地提交记录失败: {0}")]
+    Store(anyhow::Error),
     #[error("No submission found")]
     NoSubmission,
 }
@@ -30,76 +48,252 @@ impl Submission {
         matches!(self.verdict.as_deref(), Some("OK"))
     }
 
-    #[allow(dead_code)]
+    /// 是否在第一个测试点就没有通过，CF 对这样的提交不计罚时
+    pub fn failed_first_test(&self) -> bool {
+        !self.is_accepted() && self.passed_test_count == Some(0)
+    }
+
     pub fn is_practice(&self) -> bool {
         self.author.participant_type == "PRACTICE"
     }
 }
 
+/// 用户的全部提交，按提交 id 降序
+///
+/// 绑定过的账号会先增量同步到本地再从本地读取，其他账号直接请求 API
 pub async fn get_recent_submissions(cf_id: &str) -> Result<Vec<Submission>, SubmissionError> {
-    let res = fetch(&format!(
-        "https://codeforces.com/api/user.status?handle={}",
-        cf_id
-    ))
-    .await
-    .map_err(|_| SubmissionError::FetchError)?;
-
-    let body = res
-        .json::<Value>()
+    if !sql::duel::submission::is_bound_handle(cf_id)
+        .await
+        .unwrap_or(false)
+    {
+        let submissions = api::user_status(cf_id, None, None).await?;
+        return Ok(submissions.as_ref().clone());
+    }
+
+    sync_submissions(cf_id).await?;
+    sql::duel::submission::get_submissions(cf_id)
+        .await
+        .map_err(SubmissionError::Store)
+}
+
+/// 用户通过的题目，用法同 [`get_recent_submissions`]
+pub async fn get_solved_set(cf_id: &str) -> Result<HashSet<(i64, String)>, SubmissionError> {
+    if !sql::duel::submission::is_bound_handle(cf_id)
+        .await
+        .unwrap_or(false)
+    {
+        let submissions = api::user_status(cf_id, None, None).await?;
+        return Ok(submissions
+            .iter()
+            .filter(|submission| submission.is_accepted())
+            .map(|submission| submission.problem.key())
+            .collect());
+    }
+
+    sync_submissions(cf_id).await?;
+    sql::duel::submission::get_solved_set(cf_id)
+        .await
+        .map_err(SubmissionError::Store)
+}
+
+/// 把用户在 CF 上的新提交同步到本地
+///
+/// 从最新的提交开始分页拉取，直到遇到本地已有的提交为止；
+/// 本地没有记录或者新提交太多时直接拉取全部提交
+pub async fn sync_submissions(cf_id: &str) -> Result<usize, SubmissionError> {
+    static SYNC_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    let _lock = SYNC_LOCK.lock().await;
+
+    let sync_point = sql::duel::submission::get_sync_point(cf_id)
         .await
-        .map_err(|_| SubmissionError::FetchError)?;
+        .map_err(SubmissionError::Store)?;
+
+    let new = fetch_new_submissions(sync_point, |from, count| {
+        api::user_status_uncached(cf_id, from, count)
+    })
+    .await?;
 
-    let status = body["status"].as_str().ok_or(SubmissionError::FetchError)?;
-    if status != "OK" {
-        return Err(SubmissionError::FetchError);
+    if !new.is_empty() {
+        async {
+            Commit::start()
+                .await?
+                .upsert_submissions(cf_id, &new)
+                .await?
+                .commit()
+                .await?;
+            anyhow::Ok(())
+        }
+        .await
+        .map_err(SubmissionError::Store)?;
     }
 
-    match body {
-        Value::Object(mut map) => match map.remove("result") {
-            Some(Value::Array(submissions)) => Ok(submissions
-                .into_iter()
-                .map(serde_json::from_value)
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| SubmissionError::FetchError)?),
-            _ => Err(SubmissionError::NoSubmission),
-        },
-        _ => unreachable!("Invalid response"),
+    Ok(new.len())
+}
+
+/// 拉取比同步点新的提交，`fetch` 的参数同 [`api::user_status`]
+///
+/// 从最新的提交开始分页拉取，直到某一页包含同步点或者不满一页为止；
+/// 没有同步点或者翻页超过 [`SYNC_MAX_PAGES`] 时直接拉取全部提交
+async fn fetch_new_submissions<F, Fut>(
+    sync_point: Option<i64>,
+    mut fetch: F,
+) -> Result<Vec<Submission>, CfApiError>
+where
+    F: FnMut(Option<usize>, Option<usize>) -> Fut,
+    Fut: Future<Output = Result<Vec<Submission>, CfApiError>>,
+{
+    let Some(sync_point) = sync_point else {
+        return fetch(None, None).await;
+    };
+
+    let mut new = Vec::new();
+    let mut from = 1;
+    for _ in 0..SYNC_MAX_PAGES {
+        let page = fetch(Some(from), Some(SYNC_PAGE_SIZE)).await?;
+        new.extend(page.iter().filter(|s| s.id > sync_point).cloned());
+        if page.len() < SYNC_PAGE_SIZE || page.iter().any(|s| s.id <= sync_point) {
+            return Ok(new);
+        }
+        from += SYNC_PAGE_SIZE;
     }
+    fetch(None, None).await
 }
 
 /// 得到用户最近一次提交的信息
 pub async fn get_last_submission(cf_id: &str) -> Result<Submission, SubmissionError> {
-    let res = fetch(&format!(
-        "https://codeforces.com/api/user.status?handle={}&count=1",
-        cf_id
-    ))
-    .await
-    .map_err(|_| SubmissionError::FetchError)?;
-
-    let body = res
-        .json::<Value>()
-        .await
-        .map_err(|_| SubmissionError::FetchError)?;
-
-    let status = body["status"].as_str().ok_or(SubmissionError::FetchError)?;
-    if status != "OK" {
-        return Err(SubmissionError::FetchError);
-    }
-
-    match body {
-        Value::Object(mut map) => match map.remove("result") {
-            Some(Value::Array(mut submissions)) => {
-                // 获取最近一次提交
-                assert!(submissions.len() <= 1);
-                submissions
-                    .pop()
-                    .ok_or(SubmissionError::NoSubmission)
-                    .and_then(|v| {
-                        serde_json::from_value(v).map_err(|_| SubmissionError::FetchError)
-                    })
-            }
-            _ => Err(SubmissionError::NoSubmission),
-        },
-        _ => unreachable!("Invalid response"),
+    api::last_submission(cf_id)
+        .await?
+        .ok_or(SubmissionError::NoSubmission)
+}
+
+/// 测试中构造提交，默认是 1A 的练习提交、通过、提交时间等于 id
+#[cfg(test)]
+pub struct SubmissionBuilder(Submission);
+
+#[cfg(test)]
+impl SubmissionBuilder {
+    pub fn new(id: i64) -> Self {
+        Self(Submission {
+            id,
+            creation_time_seconds: id,
+            problem: Problem::new(1, "A".to_string(), None, vec![]),
+            verdict: Some("OK".to_string()),
+            passed_test_count: None,
+            author: Author {
+                participant_type: "PRACTICE".to_string(),
+            },
+        })
+    }
+
+    pub fn problem(mut self, contest_id: i64, index: &str) -> Self {
+        self.0.problem.contest_id = contest_id;
+        self.0.problem.index = index.to_string();
+        self
+    }
+
+    pub fn rating(mut self, rating: i64) -> Self {
+        self.0.problem.rating = Some(rating);
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.0.problem.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        self
+    }
+
+    pub fn time(mut self, creation_time_seconds: i64) -> Self {
+        self.0.creation_time_seconds = creation_time_seconds;
+        self
+    }
+
+    pub fn verdict(mut self, verdict: &str) -> Self {
+        self.0.verdict = Some(verdict.to_string());
+        self
+    }
+
+    pub fn passed_tests(mut self, passed_test_count: i64) -> Self {
+        self.0.passed_test_count = Some(passed_test_count);
+        self
+    }
+
+    pub fn participant_type(mut self, participant_type: &str) -> Self {
+        self.0.author.participant_type = participant_type.to_string();
+        self
+    }
+
+    pub fn build(self) -> Submission {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// 一次请求的 `from` 和 `count`
+    type Request = (Option<usize>, Option<usize>);
+
+    /// 用 `total` 条提交（id 从 1 到 total）模拟 user.status，返回新提交的 id 和请求记录
+    fn fetch(total: i64, sync_point: Option<i64>) -> (Vec<i64>, Vec<Request>) {
+        let all = (1..=total)
+            .rev()
+            .map(|id| SubmissionBuilder::new(id).build())
+            .collect::<Vec<_>>();
+        let calls = RefCell::new(Vec::new());
+        let new = kovi::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(fetch_new_submissions(sync_point, |from, count| {
+                calls.borrow_mut().push((from, count));
+                let skip = from.map_or(0, |from| from - 1);
+                let take = count.unwrap_or(all.len());
+                let page = all.iter().skip(skip).take(take).cloned().collect();
+                async move { Ok(page) }
+            }))
+            .unwrap();
+        (new.iter().map(|s| s.id).collect(), calls.into_inner())
+    }
+
+    #[test]
+    fn test_fetch_new_submissions() {
+        // 没有同步点时拉取全部
+        let (new, calls) = fetch(5, None);
+        assert_eq!(new, vec![5, 4, 3, 2, 1]);
+        assert_eq!(calls, vec![(None, None)]);
+
+        // 同步点在第一页
+        let (new, calls) = fetch(500, Some(497));
+        assert_eq!(new, vec![500, 499, 498]);
+        assert_eq!(calls, vec![(Some(1), Some(SYNC_PAGE_SIZE))]);
+
+        // 同步点在第三页，只取比同步点新的提交
+        let (new, calls) = fetch(500, Some(250));
+        assert_eq!(new, (251..=500).rev().collect::<Vec<_>>());
+        assert_eq!(
+            calls,
+            vec![
+                (Some(1), Some(SYNC_PAGE_SIZE)),
+                (Some(101), Some(SYNC_PAGE_SIZE)),
+                (Some(201), Some(SYNC_PAGE_SIZE)),
+            ]
+        );
+
+        // 没有更新的提交
+        let (new, _) = fetch(500, Some(500));
+        assert!(new.is_empty());
+
+        // 不满一页时停止翻页
+        let (new, calls) = fetch(150, Some(0));
+        assert_eq!(new.len(), 150);
+        assert_eq!(calls.len(), 2);
+
+        // 新提交太多时改为拉取全部
+        let total = (SYNC_PAGE_SIZE * SYNC_MAX_PAGES) as i64 + 50;
+        let (new, calls) = fetch(total, Some(10));
+        assert_eq!(new.len(), total as usize);
+        assert_eq!(calls.len(), SYNC_MAX_PAGES + 1);
+        assert_eq!(calls.last(), Some(&(None, None)));
     }
 }
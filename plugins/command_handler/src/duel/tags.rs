@@ -0,0 +1,204 @@
+//! 题目标签
+//!
+//! 合法标签从题库中收集，每次刷新题库后更新；
+//! 别名表来自配置文件，解析标签时统一经过这里。
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use anyhow::{Result, anyhow};
+
+use crate::CONFIG;
+use crate::duel::problem::Problem;
+
+/// 一个标签最多由几个单词组成，用于合并被空格拆开的标签
+pub const MAX_TAG_WORDS: usize = 4;
+
+static TAGS: LazyLock<RwLock<Arc<BTreeSet<String>>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(BTreeSet::new())));
+
+/// 根据题库更新合法标签
+pub fn update_tags(problems: &[Arc<Problem>]) {
+    let tags = problems
+        .iter()
+        .flat_map(|problem| problem.tags.iter().cloned())
+        .collect::<BTreeSet<_>>();
+    *TAGS.write().unwrap() = Arc::new(tags);
+}
+
+/// 当前题库中出现过的所有标签
+pub fn valid_tags() -> Arc<BTreeSet<String>> {
+    TAGS.read().unwrap().clone()
+}
+
+/// 把用户输入的标签转换为 CF 的标签名：转小写、下划线换成空格、查别名表
+pub fn normalize_tag(word: &str) -> String {
+    let word = word.to_lowercase().replace('_', " ");
+    match CONFIG.get() {
+        Some(config) => resolve_alias(&word, &config.tag_alias),
+        None => word,
+    }
+}
+
+fn resolve_alias(word: &str, aliases: &HashMap<String, String>) -> String {
+    aliases
+        .get(word)
+        .or_else(|| aliases.get(&word.replace(' ', "_")))
+        .cloned()
+        .unwrap_or_else(|| word.to_string())
+}
+
+/// `word` 是否能被识别为一个标签（包括别名）
+pub fn is_tag(word: &str) -> bool {
+    let tag = normalize_tag(word);
+    valid_tags().contains(&tag)
+}
+
+/// 检查标签是否合法，不合法时给出最相近的标签
+/// 题库还没有加载时没法判断，一律不通过
+pub fn check_tag(tag: &str) -> Result<()> {
+    let tags = valid_tags();
+    if tags.is_empty() {
+        return Err(anyhow!(
+            "题库还没有加载完成，暂时无法识别标签 {tag}，请稍后再试"
+        ));
+    }
+    if tags.contains(tag) {
+        return Ok(());
+    }
+
+    let similar = tags
+        .iter()
+        .max_by_key(|t| (strsim::normalized_damerau_levenshtein(t, tag) * 1000.0) as i64)
+        .unwrap();
+
+    let diff = strsim::normalized_damerau_levenshtein(similar, tag);
+    if diff > 0.6 {
+        Err(anyhow!(
+            "{tag} 不是一个合法的标签，你是不是想找 {similar}？"
+        ))
+    } else {
+        Err(anyhow!("{tag} 不是一个合法的标签"))
+    }
+}
+
+/// 按标签统计题目数量，键为 (标签, rating)，没有 rating 的题目记为 None
+pub fn count_by_rating(
+    problems: &[Arc<Problem>],
+) -> BTreeMap<String, BTreeMap<Option<i64>, usize>> {
+    problems.iter().fold(BTreeMap::new(), |mut acc, problem| {
+        for tag in problem.tags.iter() {
+            *acc.entry(tag.clone())
+                .or_insert_with(BTreeMap::new)
+                .entry(problem.rating)
+                .or_insert(0) += 1;
+        }
+        acc
+    })
+}
+
+/// 默认的标签别名
+pub fn default_tag_alias() -> HashMap<String, String> {
+    [
+        ("ds", "data structures"),
+        ("数据结构", "data structures"),
+        ("动态规划", "dp"),
+        ("贪心", "greedy"),
+        ("数学", "math"),
+        ("数论", "number theory"),
+        ("nt", "number theory"),
+        ("图论", "graphs"),
+        ("树", "trees"),
+        ("字符串", "strings"),
+        ("构造", "constructive algorithms"),
+        ("cons", "constructive algorithms"),
+        ("二分", "binary search"),
+        ("bs", "binary search"),
+        ("暴力", "brute force"),
+        ("模拟", "implementation"),
+        ("impl", "implementation"),
+        ("组合数学", "combinatorics"),
+        ("博弈", "games"),
+        ("几何", "geometry"),
+        ("计算几何", "geometry"),
+        ("位运算", "bitmasks"),
+        ("排序", "sortings"),
+        ("双指针", "two pointers"),
+        ("最短路", "shortest paths"),
+        ("并查集", "dsu"),
+        ("哈希", "hashing"),
+        ("交互", "interactive"),
+        ("概率", "probabilities"),
+        ("分治", "divide and conquer"),
+        ("网络流", "flows"),
+        ("搜索", "dfs and similar"),
+        ("dfs", "dfs and similar"),
+        ("矩阵", "matrices"),
+        ("二分图匹配", "graph matchings"),
+        ("三分", "ternary search"),
+        ("后缀数组", "string suffix structures"),
+        ("中国剩余定理", "chinese remainder theorem"),
+        ("crt", "chinese remainder theorem"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+/// 测试用的标签，所有测试共用同一份，只写入一次
+#[cfg(test)]
+pub fn init_test_tags() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let problems = [
+            "greedy",
+            "math",
+            "dp",
+            "graphs",
+            "data structures",
+            "dfs and similar",
+        ]
+        .iter()
+        .map(|tag| {
+            Arc::new(Problem::new(
+                1,
+                "A".to_string(),
+                None,
+                vec![tag.to_string()],
+            ))
+        })
+        .collect::<Vec<_>>();
+        update_tags(&problems);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_alias() {
+        let aliases = default_tag_alias();
+        assert_eq!(resolve_alias("ds", &aliases), "data structures");
+        assert_eq!(resolve_alias("动态规划", &aliases), "dp");
+        assert_eq!(resolve_alias("greedy", &aliases), "greedy");
+
+        // 别名表中的键可以用下划线代替空格
+        let aliases = HashMap::from([("two_ptr".to_string(), "two pointers".to_string())]);
+        assert_eq!(resolve_alias("two ptr", &aliases), "two pointers");
+
+        // 没有加载配置时只做大小写和下划线的转换
+        assert_eq!(normalize_tag("Data_Structures"), "data structures");
+    }
+
+    #[test]
+    fn test_check_tag() {
+        init_test_tags();
+        assert!(is_tag("dp"));
+        assert!(is_tag("DFS_and_similar"));
+        assert!(!is_tag("dpp"));
+        assert!(check_tag("greedy").is_ok());
+        let err = check_tag("gredy").unwrap_err().to_string();
+        assert!(err.contains("greedy"));
+    }
+}
@@ -4,7 +4,7 @@ use duel::handlers;
 use duel::user::BindingUsers;
 use kovi::serde_json::Value;
 use kovi::utils::load_json_data;
-use kovi::{MsgEvent, PluginBuilder as plugin, tokio};
+use kovi::{MsgEvent, PluginBuilder as plugin, RuntimeBot, tokio};
 use utils::{change, mes_to_text};
 
 pub(crate) mod atcoder;
@@ -12,15 +12,18 @@ pub(crate) mod codeforces;
 pub(crate) mod config;
 pub(crate) mod duel;
 pub(crate) mod sql;
+pub(crate) mod subscription;
 pub(crate) mod utils;
 
 static PATH: OnceLock<std::path::PathBuf> = OnceLock::new();
 static CONFIG: OnceLock<config::Config> = OnceLock::new();
 static BINDING_USERS: OnceLock<BindingUsers> = OnceLock::new();
+static BOT: OnceLock<Arc<RuntimeBot>> = OnceLock::new();
 
 #[kovi::plugin]
 async fn main() {
     let bot = plugin::get_runtime_bot();
+    BOT.get_or_init(|| Arc::clone(&bot));
     let data_path = bot.get_data_path();
 
     PATH.get_or_init(|| data_path.clone());
@@ -29,6 +32,7 @@ async fn main() {
     sql::init(sql_path.to_str().unwrap()).await.unwrap();
 
     duel::init().await;
+    codeforces::init();
     BINDING_USERS.get_or_init(BindingUsers::new);
 
     let config_path = data_path.join("config.json");
@@ -55,6 +59,13 @@ async fn handle(event: Arc<MsgEvent>, command: &Value) {
 
     let (cmd, changed) = match change(&mut args, command) {
         Ok((cmd, changed)) => (cmd, changed),
+        // 单独的 /contest 由 contest 插件列出近期比赛，其他认不出的子命令在这里提示用法
+        Err(_) if args.first().is_some_and(|arg| arg == "contest") && text != "contest" => {
+            event.reply(
+                "用法：/contest 获取最近的比赛信息，/contest host|join|leave|board|cancel 管理群赛，详见 /help contest",
+            );
+            return;
+        }
         Err(_e) => {
             // event.reply(e.to_string());
             return;
@@ -77,6 +88,102 @@ async fn handle(event: Arc<MsgEvent>, command: &Value) {
         "cf_recommend" => {
             codeforces::recommend::recommend(&event, &args).await;
         }
+        "cf_group_analyze" => {
+            codeforces::group::analyze(&event).await;
+        }
+        "cf_compare" => {
+            codeforces::compare::compare(&event, &args).await;
+        }
+        "cf_predict" => {
+            codeforces::predict::predict(&event, &args).await;
+        }
+        "cf_standings" => {
+            codeforces::standings::standings(&event, &args).await;
+        }
+        "cf_upsolve" => {
+            codeforces::upsolve::upsolve(&event, &args).await;
+        }
+        "cf_plan" => {
+            codeforces::plan::plan(&event, &args).await;
+        }
+        "cf_problem" => {
+            codeforces::problemset::problem(&event, &args).await;
+        }
+        "cf_search" => {
+            codeforces::problemset::search(&event, &args).await;
+        }
+        "cf_tags" => {
+            codeforces::problemset::tags(&event, &args).await;
+        }
+        "blacklist_add" => {
+            codeforces::curation::blacklist_add(&event, &args).await;
+        }
+        "blacklist_remove" => {
+            codeforces::curation::blacklist_remove(&event, &args).await;
+        }
+        "blacklist_list" => {
+            codeforces::curation::blacklist_list(&event).await;
+        }
+        "pool_add" => {
+            codeforces::curation::pool_edit(&event, &args, true).await;
+        }
+        "pool_import" => {
+            codeforces::curation::pool_import(&event, &args).await;
+        }
+        "pool_remove" => {
+            codeforces::curation::pool_edit(&event, &args, false).await;
+        }
+        "pool_list" => {
+            codeforces::curation::pool_list(&event, &args).await;
+        }
+        "mashup_host" => {
+            codeforces::mashup::host(&event, &args).await;
+        }
+        "mashup_join" => {
+            codeforces::mashup::join(&event, true).await;
+        }
+        "mashup_leave" => {
+            codeforces::mashup::join(&event, false).await;
+        }
+        "mashup_board" => {
+            codeforces::mashup::board(&event).await;
+        }
+        "mashup_cancel" => {
+            codeforces::mashup::cancel(&event).await;
+        }
+        "virtual_create" => {
+            codeforces::virtual_contest::create(&event, &args).await;
+        }
+        "virtual_join" => {
+            codeforces::virtual_contest::join(&event, true).await;
+        }
+        "virtual_leave" => {
+            codeforces::virtual_contest::join(&event, false).await;
+        }
+        "virtual_cancel" => {
+            codeforces::virtual_contest::cancel(&event).await;
+        }
+        "virtual_status" => {
+            codeforces::virtual_contest::status(&event).await;
+        }
+        "cf_notify_subscribe" => {
+            codeforces::watch::subscribe(&event, true).await;
+        }
+        "cf_notify_unsubscribe" => {
+            codeforces::watch::subscribe(&event, false).await;
+        }
+        "cf_brag_subscribe" => {
+            codeforces::brag::subscribe(&event, true).await;
+        }
+        "cf_brag_unsubscribe" => {
+            codeforces::brag::subscribe(&event, false).await;
+        }
+        "problemset_refresh" => {
+            codeforces::problemset::refresh(&event).await;
+        }
+        "problemset_status" => {
+            codeforces::problemset::status(&event).await;
+        }
         "daily_problem" => {
             handlers::daily_problem(&event).await;
         }
@@ -114,7 +221,19 @@ async fn handle(event: Arc<MsgEvent>, command: &Value) {
             handlers::daily_finish(&event).await;
         }
         "daily_ranklist" => {
-            handlers::daily_ranklist(&event).await;
+            handlers::daily_ranklist(&event, &args).await;
+        }
+        "personal_daily_problem" => {
+            handlers::personal_daily_problem(&event).await;
+        }
+        "personal_daily_finish" => {
+            handlers::personal_daily_finish(&event).await;
+        }
+        "daily_subscribe" => {
+            handlers::daily_subscribe(&event, true).await;
+        }
+        "daily_unsubscribe" => {
+            handlers::daily_subscribe(&event, false).await;
         }
         "ranklist" => {
             handlers::ranklist(&event).await;
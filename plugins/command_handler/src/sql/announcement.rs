@@ -0,0 +1,46 @@
+use anyhow::Result;
+use kovi::chrono::Utc;
+
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+pub trait CommitAnnouncementExt {
+    async fn mark_announced(&mut self, contest_id: i64) -> Result<&mut Self>;
+}
+
+impl CommitAnnouncementExt for Commit {
+    async fn mark_announced(&mut self, contest_id: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO announced_contest (contest_id, announced_at) VALUES (?, ?)
+            "#,
+        )
+        .bind(contest_id)
+        .bind(Utc::now().timestamp())
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 是否已经播报过这场比赛的 rating 变化
+pub async fn is_announced(contest_id: i64) -> Result<bool> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT contest_id FROM announced_contest WHERE contest_id = ?
+        "#,
+    )
+    .bind(contest_id)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.is_some())
+}
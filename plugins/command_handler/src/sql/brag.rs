@@ -0,0 +1,123 @@
+use anyhow::Result;
+
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+pub trait CommitBragExt {
+    async fn set_brag_cursor(
+        &mut self,
+        handle: &str,
+        last_id: i64,
+        checked_at: i64,
+    ) -> Result<&mut Self>;
+    async fn touch_brag_cursor(
+        &mut self,
+        handle: &str,
+        since: i64,
+        checked_at: i64,
+    ) -> Result<&mut Self>;
+    async fn set_brag_round_started_at(&mut self, time: i64) -> Result<&mut Self>;
+}
+
+impl CommitBragExt for Commit {
+    async fn set_brag_cursor(
+        &mut self,
+        handle: &str,
+        last_id: i64,
+        checked_at: i64,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO brag_cursor (handle, last_id, checked_at) VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(handle.to_lowercase())
+        .bind(last_id)
+        .bind(checked_at)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    /// 只更新检查时间，`since` 之后没有检查过的记录不更新
+    async fn touch_brag_cursor(
+        &mut self,
+        handle: &str,
+        since: i64,
+        checked_at: i64,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE brag_cursor SET checked_at = ? WHERE handle = ? AND checked_at >= ?
+            "#,
+        )
+        .bind(checked_at)
+        .bind(handle.to_lowercase())
+        .bind(since)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn set_brag_round_started_at(&mut self, time: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO meta (key, value) VALUES ('brag_round_started_at', ?)
+            "#,
+        )
+        .bind(time.to_string())
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 播报到的最后一个提交，还没有播报过这个账号或者 `since` 之后没有检查过时返回 None
+pub async fn get_brag_cursor(handle: &str, since: i64) -> Result<Option<i64>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT last_id FROM brag_cursor WHERE handle = ? AND checked_at >= ?
+        "#,
+    )
+    .bind(handle.to_lowercase())
+    .bind(since)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.map(|(last_id,)| last_id))
+}
+
+/// 上一轮完整检查开始的时间，还没有检查完过一轮时返回 None
+pub async fn get_brag_round_started_at() -> Result<Option<i64>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT value FROM meta WHERE key = 'brag_round_started_at'
+        "#,
+    )
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.and_then(|(time,)| time.parse().ok()))
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+pub trait CommitCurationExt {
+    async fn add_blacklist(&mut self, rule: &str, reason: &str) -> Result<&mut Self>;
+    async fn remove_blacklist(&mut self, id: i64) -> Result<&mut Self>;
+    async fn add_to_pool(&mut self, name: &str, problems: &[(i64, String)]) -> Result<&mut Self>;
+    async fn remove_from_pool(
+        &mut self,
+        name: &str,
+        problems: &[(i64, String)],
+    ) -> Result<&mut Self>;
+    async fn remove_pool(&mut self, name: &str) -> Result<&mut Self>;
+}
+
+impl CommitCurationExt for Commit {
+    async fn add_blacklist(&mut self, rule: &str, reason: &str) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO problem_blacklist (rule, reason) VALUES (?, ?)
+            "#,
+        )
+        .bind(rule)
+        .bind(reason)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn remove_blacklist(&mut self, id: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let res = sqlx::query(
+            r#"
+            DELETE FROM problem_blacklist WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .execute(&mut **trans)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("没有编号为 {} 的黑名单规则", id));
+        }
+
+        Ok(self)
+    }
+
+    async fn add_to_pool(&mut self, name: &str, problems: &[(i64, String)]) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        for (contest_id, index) in problems {
+            let _ = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO problem_pool (name, contest_id, idx) VALUES (?, ?, ?)
+                "#,
+            )
+            .bind(name)
+            .bind(contest_id)
+            .bind(index)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+
+    async fn remove_from_pool(
+        &mut self,
+        name: &str,
+        problems: &[(i64, String)],
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        for (contest_id, index) in problems {
+            let _ = sqlx::query(
+                r#"
+                DELETE FROM problem_pool WHERE name = ? AND contest_id = ? AND idx = ?
+                "#,
+            )
+            .bind(name)
+            .bind(contest_id)
+            .bind(index)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+
+    async fn remove_pool(&mut self, name: &str) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            DELETE FROM problem_pool WHERE name = ?
+            "#,
+        )
+        .bind(name)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 所有黑名单规则：(编号, 规则, 原因)
+pub async fn get_blacklist() -> Result<Vec<(i64, String, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String, String)> = sqlx::query_as(
+        r#"
+        SELECT id, rule, reason FROM problem_blacklist ORDER BY id
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 题单中的所有题目
+pub async fn get_pool(name: &str) -> Result<HashSet<(i64, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT contest_id, idx FROM problem_pool WHERE name = ?
+        "#,
+    )
+    .bind(name)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res.into_iter().collect())
+}
+
+/// 所有题单及其题目数量
+pub async fn get_pools() -> Result<Vec<(String, i64)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(String, i64)> = sqlx::query_as(
+        r#"
+        SELECT name, COUNT(*) FROM problem_pool GROUP BY name ORDER BY name
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
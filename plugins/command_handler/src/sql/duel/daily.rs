@@ -0,0 +1,147 @@
+use anyhow::Result;
+
+use crate::duel::daily::{DailyRank, PersonalDaily};
+use crate::duel::problem::Problem;
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+pub trait CommitDailyExt {
+    async fn add_daily_log(
+        &mut self,
+        qq: i64,
+        time: &str,
+        problem: &Problem,
+        score: i64,
+    ) -> Result<&mut Self>;
+}
+
+impl CommitDailyExt for Commit {
+    async fn add_daily_log(
+        &mut self,
+        qq: i64,
+        time: &str,
+        problem: &Problem,
+        score: i64,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO daily_log (qq, time, contest_id, idx, rating, score) VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(qq)
+        .bind(time)
+        .bind(problem.contest_id)
+        .bind(&problem.index)
+        .bind(problem.rating)
+        .bind(score)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 统计 `since`（含）之后每个用户的每日任务得分，按得分降序
+pub async fn get_daily_rank_since(since: &str) -> Result<Vec<DailyRank>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, Option<String>, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT daily_log.qq, user.cf_id, SUM(daily_log.score) AS total, COUNT(*)
+        FROM daily_log LEFT JOIN user ON user.qq = daily_log.qq
+        WHERE daily_log.time >= ?
+        GROUP BY daily_log.qq
+        ORDER BY total DESC
+        "#,
+    )
+    .bind(since)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res
+        .into_iter()
+        .map(|(qq, cf_id, score, solved)| DailyRank {
+            qq,
+            cf_id,
+            score,
+            solved,
+        })
+        .collect())
+}
+
+pub trait CommitPersonalDailyExt {
+    async fn set_personal_daily(&mut self, daily: &PersonalDaily) -> Result<&mut Self>;
+    async fn finish_personal_daily(&mut self, daily: &PersonalDaily) -> Result<&mut Self>;
+}
+
+impl CommitPersonalDailyExt for Commit {
+    async fn set_personal_daily(&mut self, daily: &PersonalDaily) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO personal_daily (qq, time, contest_id, idx, rating, user_rating, score) VALUES (?, ?, ?, ?, ?, ?, NULL)
+            "#,
+        )
+        .bind(daily.qq)
+        .bind(&daily.time)
+        .bind(daily.problem.contest_id)
+        .bind(&daily.problem.index)
+        .bind(daily.problem.rating)
+        .bind(daily.user_rating)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn finish_personal_daily(&mut self, daily: &PersonalDaily) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE personal_daily SET score = ? WHERE qq = ? AND time = ?
+            "#,
+        )
+        .bind(daily.score)
+        .bind(daily.qq)
+        .bind(&daily.time)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+pub async fn get_personal_daily(qq: i64, time: &str) -> Result<Option<PersonalDaily>> {
+    let sql = POOL.get().unwrap();
+
+    let res = sqlx::query_as::<_, (i64, String, Option<i64>, i64, Option<i64>)>(
+        r#"
+        SELECT contest_id, idx, rating, user_rating, score FROM personal_daily WHERE qq = ? AND time = ?
+        "#,
+    )
+    .bind(qq)
+    .bind(time)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.map(|res| PersonalDaily {
+        qq,
+        time: time.to_string(),
+        problem: Problem::new(res.0, res.1, res.2, vec![]),
+        user_rating: res.3,
+        score: res.4,
+    }))
+}
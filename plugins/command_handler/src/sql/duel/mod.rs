@@ -1,3 +1,6 @@
 pub(crate) mod challenge;
+pub(crate) mod curation;
+pub(crate) mod daily;
 pub(crate) mod problem;
+pub(crate) mod submission;
 pub(crate) mod user;
@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use anyhow::Result;
-use kovi::chrono::Local;
+use kovi::chrono::{DateTime, Local, Utc};
+use kovi::serde_json;
 
 use crate::duel::problem::Problem;
 use crate::sql::POOL;
@@ -7,6 +10,9 @@ use crate::sql::utils::Commit;
 
 pub trait CommitProblemExt {
     async fn set_daily_problem(&mut self, problem: &Problem) -> Result<&mut Self>;
+    async fn upsert_problems(&mut self, problems: &[Arc<Problem>]) -> Result<&mut Self>;
+    async fn remove_problems(&mut self, keys: &[(i64, String)]) -> Result<&mut Self>;
+    async fn set_problemset_fetched_at(&mut self, time: DateTime<Utc>) -> Result<&mut Self>;
 }
 
 impl CommitProblemExt for Commit {
@@ -32,6 +38,70 @@ impl CommitProblemExt for Commit {
 
         Ok(self)
     }
+
+    async fn upsert_problems(&mut self, problems: &[Arc<Problem>]) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        for problem in problems {
+            let _ = sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO problem (contest_id, idx, name, rating, tags, solved_count) VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(problem.contest_id)
+            .bind(&problem.index)
+            .bind(&problem.name)
+            .bind(problem.rating)
+            .bind(serde_json::to_string(&problem.tags).unwrap())
+            .bind(problem.solved_count)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+
+    async fn remove_problems(&mut self, keys: &[(i64, String)]) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        for (contest_id, index) in keys {
+            let _ = sqlx::query(
+                r#"
+                DELETE FROM problem WHERE contest_id = ? AND idx = ?
+                "#,
+            )
+            .bind(contest_id)
+            .bind(index)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+
+    async fn set_problemset_fetched_at(&mut self, time: DateTime<Utc>) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO meta (key, value) VALUES ('problemset_fetched_at', ?)
+            "#,
+        )
+        .bind(time.to_rfc3339())
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
 }
 
 pub async fn get_daily_problem() -> Result<Problem> {
@@ -51,3 +121,38 @@ pub async fn get_daily_problem() -> Result<Problem> {
 
     Ok(problem)
 }
+
+/// 读取缓存在数据库中的题库
+pub async fn load_problemset() -> Result<Vec<Problem>> {
+    let sql = POOL.get().unwrap();
+
+    let problems: Vec<Problem> = sqlx::query_as(
+        r#"
+        SELECT contest_id, idx, name, rating, tags, solved_count FROM problem
+        ORDER BY contest_id DESC, idx ASC
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(problems)
+}
+
+/// 题库上一次从 CF 拉取的时间
+pub async fn get_problemset_fetched_at() -> Result<Option<DateTime<Utc>>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT value FROM meta WHERE key = 'problemset_fetched_at'
+        "#,
+    )
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.and_then(|(time,)| {
+        DateTime::parse_from_rfc3339(&time)
+            .ok()
+            .map(|time| time.to_utc())
+    }))
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use kovi::serde_json;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::duel::problem::Problem;
+use crate::duel::submission::{Author, Submission};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for Submission {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let tags: String = row.try_get("tags")?;
+        let mut problem = Problem::new(
+            row.try_get("contest_id")?,
+            row.try_get("idx")?,
+            row.try_get("rating")?,
+            serde_json::from_str(&tags).unwrap_or_default(),
+        );
+        problem.name = row.try_get("name")?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            creation_time_seconds: row.try_get("creation_time")?,
+            problem,
+            verdict: row.try_get("verdict")?,
+            passed_test_count: row.try_get("passed_test_count")?,
+            author: Author {
+                participant_type: row.try_get("participant_type")?,
+            },
+        })
+    }
+}
+
+pub trait CommitSubmissionExt {
+    async fn upsert_submissions(
+        &mut self,
+        handle: &str,
+        submissions: &[Submission],
+    ) -> Result<&mut Self>;
+}
+
+impl CommitSubmissionExt for Commit {
+    async fn upsert_submissions(
+        &mut self,
+        handle: &str,
+        submissions: &[Submission],
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        for submission in submissions {
+            let problem = &submission.problem;
+            let _ = sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO submission (id, handle, creation_time, contest_id, idx, name, rating, tags, verdict, passed_test_count, participant_type) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(submission.id)
+            .bind(handle)
+            .bind(submission.creation_time_seconds)
+            .bind(problem.contest_id)
+            .bind(&problem.index)
+            .bind(&problem.name)
+            .bind(problem.rating)
+            .bind(serde_json::to_string(&problem.tags)?)
+            .bind(&submission.verdict)
+            .bind(submission.passed_test_count)
+            .bind(&submission.author.participant_type)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+}
+
+/// 用户在本地的全部提交，按提交 id 降序
+pub async fn get_submissions(handle: &str) -> Result<Vec<Submission>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<Submission> = sqlx::query_as(
+        r#"
+        SELECT * FROM submission WHERE handle = ? COLLATE NOCASE ORDER BY id DESC
+        "#,
+    )
+    .bind(handle)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 用户在本地的 id 大于 `after` 的提交，按提交 id 升序
+pub async fn get_submissions_after(handle: &str, after: i64) -> Result<Vec<Submission>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<Submission> = sqlx::query_as(
+        r#"
+        SELECT * FROM submission WHERE handle = ? COLLATE NOCASE AND id > ? ORDER BY id ASC
+        "#,
+    )
+    .bind(handle)
+    .bind(after)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 增量同步的起点：比这个 id 新的提交都需要重新拉取
+///
+/// 还在评测中的提交结果可能会变化，所以取最早一个未出结果的提交之前的 id；
+/// 本地没有任何提交时返回 None
+pub async fn get_sync_point(handle: &str) -> Result<Option<i64>> {
+    let sql = POOL.get().unwrap();
+
+    let res: (Option<i64>, Option<i64>) = sqlx::query_as(
+        r#"
+        SELECT
+            MAX(id),
+            MIN(CASE WHEN verdict IS NULL OR verdict = 'TESTING' THEN id END)
+        FROM submission WHERE handle = ? COLLATE NOCASE
+        "#,
+    )
+    .bind(handle)
+    .fetch_one(sql)
+    .await?;
+
+    Ok(match res {
+        (_, Some(pending)) => Some(pending - 1),
+        (latest, None) => latest,
+    })
+}
+
+/// 用户通过的题目
+pub async fn get_solved_set(handle: &str) -> Result<HashSet<(i64, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT contest_id, idx FROM submission WHERE handle = ? COLLATE NOCASE AND verdict = 'OK'
+        "#,
+    )
+    .bind(handle)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res.into_iter().collect())
+}
+
+/// 用户 id 不超过 `until` 的提交中通过的题目
+pub async fn get_solved_set_until(handle: &str, until: i64) -> Result<HashSet<(i64, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT contest_id, idx FROM submission WHERE handle = ? COLLATE NOCASE AND verdict = 'OK' AND id <= ?
+        "#,
+    )
+    .bind(handle)
+    .bind(until)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res.into_iter().collect())
+}
+
+/// 用户 id 不超过 `until` 的提交中赛时或者虚拟参加过的比赛
+pub async fn get_participated_until(handle: &str, until: i64) -> Result<HashSet<i64>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT contest_id FROM submission WHERE handle = ? COLLATE NOCASE AND participant_type IN ('CONTESTANT', 'OUT_OF_COMPETITION', 'VIRTUAL') AND id <= ?
+        "#,
+    )
+    .bind(handle)
+    .bind(until)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res.into_iter().map(|(contest_id,)| contest_id).collect())
+}
+
+/// 本地记录中交过这道题的账号，以及是否通过
+pub async fn get_problem_attempts(contest_id: i64, index: &str) -> Result<Vec<(String, bool)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(String, bool)> = sqlx::query_as(
+        r#"
+        SELECT handle, MAX(COALESCE(verdict, '') = 'OK') FROM submission WHERE contest_id = ? AND idx = ? GROUP BY handle COLLATE NOCASE
+        "#,
+    )
+    .bind(contest_id)
+    .bind(index)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 是否有用户绑定了这个 CF 账号
+pub async fn is_bound_handle(handle: &str) -> Result<bool> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT qq FROM user WHERE cf_id = ? COLLATE NOCASE LIMIT 1
+        "#,
+    )
+    .bind(handle)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.is_some())
+}
@@ -47,6 +47,21 @@ pub async fn get_top_20_ranklist() -> Result<Vec<User>> {
     Ok(users)
 }
 
+/// 所有绑定了 CF 账号的用户
+pub async fn get_bound_users() -> Result<Vec<User>> {
+    let sql = POOL.get().unwrap();
+
+    let users: Vec<User> = sqlx::query_as(
+        r#"
+        SELECT * FROM user WHERE cf_id IS NOT NULL
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(users)
+}
+
 pub trait CommitUserExt {
     async fn update_user_cf_id(&mut self, user: &User) -> Result<&mut Self>;
     async fn update_user_daily(&mut self, user: &User) -> Result<&mut Self>;
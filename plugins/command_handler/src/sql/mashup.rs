@@ -0,0 +1,170 @@
+use anyhow::Result;
+use kovi::{chrono::DateTime, serde_json};
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::codeforces::{mashup::Mashup, virtual_contest::VirtualStatus};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for Mashup {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let problems: String = row.try_get("problems")?;
+        let start_time: i64 = row.try_get("start_time")?;
+        let status: String = row.try_get("status")?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            group_id: row.try_get("group_id")?,
+            creator: row.try_get("creator")?,
+            problems: serde_json::from_str(&problems).unwrap_or_default(),
+            filter: row.try_get("filter")?,
+            problem_count: row.try_get::<i64, _>("problem_count")? as usize,
+            start_time: DateTime::from_timestamp(start_time, 0).unwrap_or_default(),
+            duration_seconds: row.try_get("duration")?,
+            status: VirtualStatus::parse(&status).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "status".to_string(),
+                source: format!("unknown status {}", status).into(),
+            })?,
+        })
+    }
+}
+
+pub trait CommitMashupExt {
+    async fn add_mashup(&mut self, mashup: &Mashup) -> Result<&mut Self>;
+    async fn update_mashup(&mut self, mashup: &Mashup) -> Result<&mut Self>;
+    async fn join_mashup(&mut self, mashup_id: i64, qq: i64, cf_id: &str) -> Result<&mut Self>;
+    async fn leave_mashup(&mut self, mashup_id: i64, qq: i64) -> Result<&mut Self>;
+}
+
+impl CommitMashupExt for Commit {
+    async fn add_mashup(&mut self, mashup: &Mashup) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO mashup (group_id, creator, problems, filter, problem_count, start_time, duration, status) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(mashup.group_id)
+        .bind(mashup.creator)
+        .bind(serde_json::to_string(&mashup.problems)?)
+        .bind(&mashup.filter)
+        .bind(mashup.problem_count as i64)
+        .bind(mashup.start_time.timestamp())
+        .bind(mashup.duration_seconds)
+        .bind(mashup.status.as_str())
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn update_mashup(&mut self, mashup: &Mashup) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE mashup SET problems = ?, status = ? WHERE id = ?
+            "#,
+        )
+        .bind(serde_json::to_string(&mashup.problems)?)
+        .bind(mashup.status.as_str())
+        .bind(mashup.id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn join_mashup(&mut self, mashup_id: i64, qq: i64, cf_id: &str) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO mashup_participant (mashup_id, qq, cf_id) VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(mashup_id)
+        .bind(qq)
+        .bind(cf_id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn leave_mashup(&mut self, mashup_id: i64, qq: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            DELETE FROM mashup_participant WHERE mashup_id = ? AND qq = ?
+            "#,
+        )
+        .bind(mashup_id)
+        .bind(qq)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 所有还没开始或者正在进行的群赛
+pub async fn get_active_mashups() -> Result<Vec<Mashup>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<Mashup> = sqlx::query_as(
+        r#"
+        SELECT * FROM mashup WHERE status IN ('pending', 'running')
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 群里还没开始或者正在进行的群赛，同一个群最多只有一场
+pub async fn get_active_mashup(group_id: i64) -> Result<Option<Mashup>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<Mashup> = sqlx::query_as(
+        r#"
+        SELECT * FROM mashup WHERE group_id = ? AND status IN ('pending', 'running') LIMIT 1
+        "#,
+    )
+    .bind(group_id)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 群赛的参赛者 (qq, cf_id)
+pub async fn get_mashup_participants(mashup_id: i64) -> Result<Vec<(i64, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT qq, cf_id FROM mashup_participant WHERE mashup_id = ?
+        "#,
+    )
+    .bind(mashup_id)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
@@ -3,8 +3,16 @@ use std::sync::OnceLock;
 use anyhow::Result;
 use kovi::log::debug;
 
+pub(crate) mod announcement;
+pub(crate) mod brag;
 pub(crate) mod duel;
+pub(crate) mod mashup;
+pub(crate) mod plan;
+pub(crate) mod recommendation;
+pub(crate) mod subscription;
+pub(crate) mod upsolve;
 pub(crate) mod utils;
+pub(crate) mod virtual_contest;
 
 static POOL: OnceLock<sqlx::SqlitePool> = OnceLock::new();
 static PATH: OnceLock<String> = OnceLock::new();
@@ -44,6 +52,194 @@ pub async fn init(path: &str) -> Result<()> {
     .execute(sql)
     .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_log
+        (qq INTEGER, time TEXT, contest_id INTEGER, idx TEXT, rating INTEGER, score INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS personal_daily
+        (qq INTEGER, time TEXT, contest_id INTEGER, idx TEXT, rating INTEGER, user_rating INTEGER, score INTEGER, PRIMARY KEY (qq, time))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS problem
+        (contest_id INTEGER, idx TEXT, name TEXT, rating INTEGER, tags TEXT, solved_count INTEGER, PRIMARY KEY (contest_id, idx))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS submission
+        (id INTEGER PRIMARY KEY, handle TEXT, creation_time INTEGER, contest_id INTEGER, idx TEXT, name TEXT, rating INTEGER, tags TEXT, verdict TEXT, passed_test_count INTEGER, participant_type TEXT)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    // 旧版本建的表没有 passed_test_count，列已经存在时会失败，忽略即可
+    let _ = sqlx::query(
+        r#"
+        ALTER TABLE submission ADD COLUMN passed_test_count INTEGER
+        "#,
+    )
+    .execute(sql)
+    .await;
+
+    sqlx::query(
+        r#"
+        CREATE INDEX IF NOT EXISTS submission_handle ON submission (handle COLLATE NOCASE, id)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS meta
+        (key TEXT PRIMARY KEY, value TEXT)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS problem_blacklist
+        (id INTEGER PRIMARY KEY AUTOINCREMENT, rule TEXT, reason TEXT)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS problem_pool
+        (name TEXT, contest_id INTEGER, idx TEXT, PRIMARY KEY (name, contest_id, idx))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS subscription
+        (group_id INTEGER, topic TEXT, PRIMARY KEY (group_id, topic))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS announced_contest
+        (contest_id INTEGER PRIMARY KEY, announced_at INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mashup
+        (id INTEGER PRIMARY KEY AUTOINCREMENT, group_id INTEGER, creator INTEGER, problems TEXT, filter TEXT, problem_count INTEGER, start_time INTEGER, duration INTEGER, status TEXT)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mashup_participant
+        (mashup_id INTEGER, qq INTEGER, cf_id TEXT, PRIMARY KEY (mashup_id, qq))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS virtual_contest
+        (id INTEGER PRIMARY KEY AUTOINCREMENT, group_id INTEGER, creator INTEGER, contest_id INTEGER, filter TEXT, start_time INTEGER, duration INTEGER, status TEXT)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS virtual_participant
+        (virtual_id INTEGER, qq INTEGER, cf_id TEXT, PRIMARY KEY (virtual_id, qq))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS upsolve
+        (qq INTEGER, cf_id TEXT, contest_id INTEGER, idx TEXT, name TEXT, reason TEXT, added_at INTEGER, solved_at INTEGER, PRIMARY KEY (qq, contest_id, idx))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS upsolve_contest
+        (contest_id INTEGER PRIMARY KEY, tracked_at INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS recommendation
+        (id INTEGER PRIMARY KEY AUTOINCREMENT, qq INTEGER, cf_id TEXT, contest_id INTEGER, idx TEXT, problem_rating INTEGER, user_rating INTEGER, difficulty TEXT, params TEXT, recommended_at INTEGER, solved_at INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS training_plan
+        (id INTEGER PRIMARY KEY AUTOINCREMENT, qq INTEGER, cf_id TEXT, start_time INTEGER, weeks INTEGER, start_rating INTEGER, target_rating INTEGER, focus TEXT, status TEXT, rebalanced_week INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS training_plan_item
+        (plan_id INTEGER, week INTEGER, contest_id INTEGER, idx TEXT, rating INTEGER, solved_at INTEGER, PRIMARY KEY (plan_id, contest_id, idx))
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS brag_cursor
+        (handle TEXT PRIMARY KEY, last_id INTEGER, checked_at INTEGER)
+        "#,
+    )
+    .execute(sql)
+    .await?;
+
     Ok(())
 }
 
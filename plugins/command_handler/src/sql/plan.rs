@@ -0,0 +1,220 @@
+use anyhow::Result;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::codeforces::plan::{PlanItem, PlanStatus, TrainingPlan};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for TrainingPlan {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let status: String = row.try_get("status")?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            qq: row.try_get("qq")?,
+            cf_id: row.try_get("cf_id")?,
+            start_time: row.try_get("start_time")?,
+            weeks: row.try_get("weeks")?,
+            start_rating: row.try_get("start_rating")?,
+            target_rating: row.try_get("target_rating")?,
+            focus: row.try_get("focus")?,
+            status: PlanStatus::parse(&status).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "status".to_string(),
+                source: format!("unknown status {}", status).into(),
+            })?,
+            rebalanced_week: row.try_get("rebalanced_week")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for PlanItem {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        Ok(Self {
+            week: row.try_get("week")?,
+            contest_id: row.try_get("contest_id")?,
+            index: row.try_get("idx")?,
+            rating: row.try_get("rating")?,
+            solved_at: row.try_get("solved_at")?,
+        })
+    }
+}
+
+pub trait CommitPlanExt {
+    async fn add_plan(&mut self, plan: &TrainingPlan, items: &[PlanItem]) -> Result<&mut Self>;
+    async fn update_plan(&mut self, plan: &TrainingPlan) -> Result<&mut Self>;
+    async fn mark_plan_item_solved(
+        &mut self,
+        plan_id: i64,
+        item: &PlanItem,
+        solved_at: i64,
+    ) -> Result<&mut Self>;
+    /// 用 `items` 替换某一周还没完成的题目，`items` 中已经在计划里的题目只修改所在的周
+    async fn replace_plan_week(
+        &mut self,
+        plan_id: i64,
+        week: i64,
+        items: &[PlanItem],
+    ) -> Result<&mut Self>;
+}
+
+impl CommitPlanExt for Commit {
+    async fn add_plan(&mut self, plan: &TrainingPlan, items: &[PlanItem]) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let res = sqlx::query(
+            r#"
+            INSERT INTO training_plan (qq, cf_id, start_time, weeks, start_rating, target_rating, focus, status, rebalanced_week) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(plan.qq)
+        .bind(&plan.cf_id)
+        .bind(plan.start_time)
+        .bind(plan.weeks)
+        .bind(plan.start_rating)
+        .bind(plan.target_rating)
+        .bind(&plan.focus)
+        .bind(plan.status.as_str())
+        .bind(plan.rebalanced_week)
+        .execute(&mut **trans)
+        .await?;
+
+        let plan_id = res.last_insert_rowid();
+        for item in items {
+            let _ = sqlx::query(
+                r#"
+                INSERT INTO training_plan_item (plan_id, week, contest_id, idx, rating) VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(plan_id)
+            .bind(item.week)
+            .bind(item.contest_id)
+            .bind(&item.index)
+            .bind(item.rating)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+
+    async fn update_plan(&mut self, plan: &TrainingPlan) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE training_plan SET status = ?, rebalanced_week = ? WHERE id = ?
+            "#,
+        )
+        .bind(plan.status.as_str())
+        .bind(plan.rebalanced_week)
+        .bind(plan.id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn mark_plan_item_solved(
+        &mut self,
+        plan_id: i64,
+        item: &PlanItem,
+        solved_at: i64,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE training_plan_item SET solved_at = ? WHERE plan_id = ? AND contest_id = ? AND idx = ?
+            "#,
+        )
+        .bind(solved_at)
+        .bind(plan_id)
+        .bind(item.contest_id)
+        .bind(&item.index)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn replace_plan_week(
+        &mut self,
+        plan_id: i64,
+        week: i64,
+        items: &[PlanItem],
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            DELETE FROM training_plan_item WHERE plan_id = ? AND week = ? AND solved_at IS NULL
+            "#,
+        )
+        .bind(plan_id)
+        .bind(week)
+        .execute(&mut **trans)
+        .await?;
+
+        for item in items {
+            let _ = sqlx::query(
+                r#"
+                INSERT INTO training_plan_item (plan_id, week, contest_id, idx, rating) VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (plan_id, contest_id, idx) DO UPDATE SET week = excluded.week
+                "#,
+            )
+            .bind(plan_id)
+            .bind(week)
+            .bind(item.contest_id)
+            .bind(&item.index)
+            .bind(item.rating)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        Ok(self)
+    }
+}
+
+/// 用户进行中的训练计划，同一个用户最多只有一个
+pub async fn get_active_plan(qq: i64) -> Result<Option<TrainingPlan>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<TrainingPlan> = sqlx::query_as(
+        r#"
+        SELECT * FROM training_plan WHERE qq = ? AND status = 'active' LIMIT 1
+        "#,
+    )
+    .bind(qq)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 计划中的所有题目，按周和难度排序
+pub async fn get_plan_items(plan_id: i64) -> Result<Vec<PlanItem>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<PlanItem> = sqlx::query_as(
+        r#"
+        SELECT * FROM training_plan_item WHERE plan_id = ? ORDER BY week, rating, contest_id, idx
+        "#,
+    )
+    .bind(plan_id)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
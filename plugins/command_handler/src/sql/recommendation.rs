@@ -0,0 +1,96 @@
+use anyhow::Result;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::codeforces::recommend::{RecommendDifficulty, Recommendation};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for Recommendation {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let difficulty: Option<String> = row.try_get("difficulty")?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            qq: row.try_get("qq")?,
+            cf_id: row.try_get("cf_id")?,
+            contest_id: row.try_get("contest_id")?,
+            index: row.try_get("idx")?,
+            problem_rating: row.try_get("problem_rating")?,
+            user_rating: row.try_get("user_rating")?,
+            difficulty: difficulty
+                .as_deref()
+                .and_then(RecommendDifficulty::from_str),
+            params: row.try_get("params")?,
+            recommended_at: row.try_get("recommended_at")?,
+            solved_at: row.try_get("solved_at")?,
+        })
+    }
+}
+
+pub trait CommitRecommendationExt {
+    async fn add_recommendation(&mut self, recommendation: &Recommendation) -> Result<&mut Self>;
+    async fn mark_recommendation_solved(&mut self, id: i64, solved_at: i64) -> Result<&mut Self>;
+}
+
+impl CommitRecommendationExt for Commit {
+    async fn add_recommendation(&mut self, recommendation: &Recommendation) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO recommendation (qq, cf_id, contest_id, idx, problem_rating, user_rating, difficulty, params, recommended_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(recommendation.qq)
+        .bind(&recommendation.cf_id)
+        .bind(recommendation.contest_id)
+        .bind(&recommendation.index)
+        .bind(recommendation.problem_rating)
+        .bind(recommendation.user_rating)
+        .bind(recommendation.difficulty.as_ref().map(|d| d.as_str()))
+        .bind(&recommendation.params)
+        .bind(recommendation.recommended_at)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn mark_recommendation_solved(&mut self, id: i64, solved_at: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE recommendation SET solved_at = ? WHERE id = ?
+            "#,
+        )
+        .bind(solved_at)
+        .bind(id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 用户的推荐记录，最近的在前
+pub async fn get_recommendations(qq: i64) -> Result<Vec<Recommendation>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<Recommendation> = sqlx::query_as(
+        r#"
+        SELECT * FROM recommendation WHERE qq = ? ORDER BY recommended_at DESC, id DESC
+        "#,
+    )
+    .bind(qq)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+pub trait CommitSubscriptionExt {
+    async fn subscribe(&mut self, group_id: i64, topic: &str) -> Result<&mut Self>;
+    async fn unsubscribe(&mut self, group_id: i64, topic: &str) -> Result<&mut Self>;
+}
+
+impl CommitSubscriptionExt for Commit {
+    async fn subscribe(&mut self, group_id: i64, topic: &str) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO subscription (group_id, topic) VALUES (?, ?)
+            "#,
+        )
+        .bind(group_id)
+        .bind(topic)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn unsubscribe(&mut self, group_id: i64, topic: &str) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            DELETE FROM subscription WHERE group_id = ? AND topic = ?
+            "#,
+        )
+        .bind(group_id)
+        .bind(topic)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 获取订阅了 `topic` 的所有群
+pub async fn get_subscribed_groups(topic: &str) -> Result<Vec<i64>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT group_id FROM subscription WHERE topic = ?
+        "#,
+    )
+    .bind(topic)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res.into_iter().map(|(group_id,)| group_id).collect())
+}
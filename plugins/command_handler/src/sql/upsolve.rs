@@ -0,0 +1,146 @@
+use anyhow::Result;
+use kovi::chrono::Utc;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::codeforces::upsolve::{UpsolveItem, UpsolveReason};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for UpsolveItem {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let reason: String = row.try_get("reason")?;
+
+        Ok(Self {
+            qq: row.try_get("qq")?,
+            cf_id: row.try_get("cf_id")?,
+            contest_id: row.try_get("contest_id")?,
+            index: row.try_get("idx")?,
+            name: row.try_get("name")?,
+            reason: UpsolveReason::parse(&reason).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "reason".to_string(),
+                source: format!("unknown reason {}", reason).into(),
+            })?,
+        })
+    }
+}
+
+pub trait CommitUpsolveExt {
+    /// 记录一场比赛的补题，同时标记这场比赛已经处理过
+    async fn add_upsolve_items(
+        &mut self,
+        contest_id: i64,
+        items: &[UpsolveItem],
+    ) -> Result<&mut Self>;
+    async fn mark_upsolved(&mut self, item: &UpsolveItem) -> Result<&mut Self>;
+}
+
+impl CommitUpsolveExt for Commit {
+    async fn add_upsolve_items(
+        &mut self,
+        contest_id: i64,
+        items: &[UpsolveItem],
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let now = Utc::now().timestamp();
+        for item in items {
+            let _ = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO upsolve (qq, cf_id, contest_id, idx, name, reason, added_at) VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(item.qq)
+            .bind(&item.cf_id)
+            .bind(item.contest_id)
+            .bind(&item.index)
+            .bind(&item.name)
+            .bind(item.reason.as_str())
+            .bind(now)
+            .execute(&mut **trans)
+            .await?;
+        }
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO upsolve_contest (contest_id, tracked_at) VALUES (?, ?)
+            "#,
+        )
+        .bind(contest_id)
+        .bind(now)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn mark_upsolved(&mut self, item: &UpsolveItem) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE upsolve SET solved_at = ? WHERE qq = ? AND contest_id = ? AND idx = ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .bind(item.qq)
+        .bind(item.contest_id)
+        .bind(&item.index)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 是否已经记录过这场比赛的补题
+pub async fn is_tracked(contest_id: i64) -> Result<bool> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT contest_id FROM upsolve_contest WHERE contest_id = ?
+        "#,
+    )
+    .bind(contest_id)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res.is_some())
+}
+
+/// 用户还没补的题，按比赛和题号排序
+pub async fn get_pending(qq: i64) -> Result<Vec<UpsolveItem>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<UpsolveItem> = sqlx::query_as(
+        r#"
+        SELECT * FROM upsolve WHERE qq = ? AND solved_at IS NULL ORDER BY contest_id, idx
+        "#,
+    )
+    .bind(qq)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 所有人还没补的题
+pub async fn get_all_pending() -> Result<Vec<UpsolveItem>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<UpsolveItem> = sqlx::query_as(
+        r#"
+        SELECT * FROM upsolve WHERE solved_at IS NULL ORDER BY qq, contest_id, idx
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
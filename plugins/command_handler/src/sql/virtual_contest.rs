@@ -0,0 +1,198 @@
+use anyhow::Result;
+use kovi::chrono::DateTime;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+
+use crate::codeforces::virtual_contest::{VirtualContest, VirtualStatus};
+use crate::sql::POOL;
+use crate::sql::utils::Commit;
+
+impl<'r> FromRow<'r, SqliteRow> for VirtualContest {
+    fn from_row(row: &'r SqliteRow) -> std::result::Result<Self, sqlx::Error> {
+        let start_time: i64 = row.try_get("start_time")?;
+        let status: String = row.try_get("status")?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            group_id: row.try_get("group_id")?,
+            creator: row.try_get("creator")?,
+            contest_id: row.try_get("contest_id")?,
+            filter: row.try_get("filter")?,
+            start_time: DateTime::from_timestamp(start_time, 0).unwrap_or_default(),
+            duration_seconds: row.try_get("duration")?,
+            status: VirtualStatus::parse(&status).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "status".to_string(),
+                source: format!("unknown status {}", status).into(),
+            })?,
+        })
+    }
+}
+
+pub trait CommitVirtualContestExt {
+    /// 创建虚拟赛，创建者自动报名
+    async fn add_virtual_contest(
+        &mut self,
+        contest: &VirtualContest,
+        creator_cf_id: &str,
+    ) -> Result<&mut Self>;
+    async fn update_virtual_contest(&mut self, contest: &VirtualContest) -> Result<&mut Self>;
+    async fn join_virtual_contest(
+        &mut self,
+        virtual_id: i64,
+        qq: i64,
+        cf_id: &str,
+    ) -> Result<&mut Self>;
+    async fn leave_virtual_contest(&mut self, virtual_id: i64, qq: i64) -> Result<&mut Self>;
+}
+
+impl CommitVirtualContestExt for Commit {
+    async fn add_virtual_contest(
+        &mut self,
+        contest: &VirtualContest,
+        creator_cf_id: &str,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let res = sqlx::query(
+            r#"
+            INSERT INTO virtual_contest (group_id, creator, contest_id, filter, start_time, duration, status) VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(contest.group_id)
+        .bind(contest.creator)
+        .bind(contest.contest_id)
+        .bind(&contest.filter)
+        .bind(contest.start_time.timestamp())
+        .bind(contest.duration_seconds)
+        .bind(contest.status.as_str())
+        .execute(&mut **trans)
+        .await?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT INTO virtual_participant (virtual_id, qq, cf_id) VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(res.last_insert_rowid())
+        .bind(contest.creator)
+        .bind(creator_cf_id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn update_virtual_contest(&mut self, contest: &VirtualContest) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            UPDATE virtual_contest SET contest_id = ?, duration = ?, status = ? WHERE id = ?
+            "#,
+        )
+        .bind(contest.contest_id)
+        .bind(contest.duration_seconds)
+        .bind(contest.status.as_str())
+        .bind(contest.id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn join_virtual_contest(
+        &mut self,
+        virtual_id: i64,
+        qq: i64,
+        cf_id: &str,
+    ) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO virtual_participant (virtual_id, qq, cf_id) VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(virtual_id)
+        .bind(qq)
+        .bind(cf_id)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+
+    async fn leave_virtual_contest(&mut self, virtual_id: i64, qq: i64) -> Result<&mut Self> {
+        let trans = self
+            .tx
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not started"))?;
+
+        let _ = sqlx::query(
+            r#"
+            DELETE FROM virtual_participant WHERE virtual_id = ? AND qq = ?
+            "#,
+        )
+        .bind(virtual_id)
+        .bind(qq)
+        .execute(&mut **trans)
+        .await?;
+
+        Ok(self)
+    }
+}
+
+/// 所有还没开始或者正在进行的虚拟赛
+pub async fn get_active_virtual_contests() -> Result<Vec<VirtualContest>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<VirtualContest> = sqlx::query_as(
+        r#"
+        SELECT * FROM virtual_contest WHERE status IN ('pending', 'running')
+        "#,
+    )
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 群里还没开始或者正在进行的虚拟赛，同一个群最多只有一场
+pub async fn get_active_virtual_contest(group_id: i64) -> Result<Option<VirtualContest>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Option<VirtualContest> = sqlx::query_as(
+        r#"
+        SELECT * FROM virtual_contest WHERE group_id = ? AND status IN ('pending', 'running') LIMIT 1
+        "#,
+    )
+    .bind(group_id)
+    .fetch_optional(sql)
+    .await?;
+
+    Ok(res)
+}
+
+/// 虚拟赛的参赛者 (qq, cf_id)
+pub async fn get_virtual_participants(virtual_id: i64) -> Result<Vec<(i64, String)>> {
+    let sql = POOL.get().unwrap();
+
+    let res: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT qq, cf_id FROM virtual_participant WHERE virtual_id = ?
+        "#,
+    )
+    .bind(virtual_id)
+    .fetch_all(sql)
+    .await?;
+
+    Ok(res)
+}
@@ -0,0 +1,39 @@
+//! 群订阅的开关，各种定时播报共用
+
+use kovi::MsgEvent;
+
+use crate::{
+    sql::{subscription::CommitSubscriptionExt, utils::Commit},
+    utils::is_group_admin,
+};
+
+/// 本群订阅或取消订阅 `topic`（群管理员），`label` 为回复中播报的名字
+pub async fn toggle(event: &MsgEvent, topic: &str, subscribe: bool, label: &str) {
+    let Some(group_id) = event.group_id else {
+        event.reply("请在群聊中使用该指令");
+        return;
+    };
+
+    if !is_group_admin(event) {
+        event.reply("只有群管理员可以修改订阅");
+        return;
+    }
+
+    let result = async {
+        let mut commit = Commit::start().await?;
+        if subscribe {
+            commit.subscribe(group_id, topic).await?;
+        } else {
+            commit.unsubscribe(group_id, topic).await?;
+        }
+        commit.commit().await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(_) if subscribe => event.reply(format!("已订阅{}", label)),
+        Ok(_) => event.reply(format!("已取消订阅{}", label)),
+        Err(e) => event.reply(format!("修改订阅失败: {}", e)),
+    }
+}
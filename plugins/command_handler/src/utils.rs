@@ -1,8 +1,12 @@
-use kovi::Message;
+use std::collections::HashSet;
+
+use kovi::{Message, MsgEvent};
 
 use anyhow::{Error, Result};
 use kovi::serde_json::Value;
 
+use crate::BOT;
+
 pub enum IdOrText<'a> {
     Text(&'a str),
     At(i64),
@@ -26,6 +30,38 @@ pub fn mes_to_text(msg: &Message) -> String {
         .collect::<String>()
 }
 
+/// 是否为 bot 管理员
+pub fn is_bot_admin(user_id: i64) -> bool {
+    BOT.get()
+        .and_then(|bot| bot.get_all_admin().ok())
+        .is_some_and(|admins| admins.contains(&user_id))
+}
+
+/// 是否为 bot 管理员，或者当前群的群主/管理员
+pub fn is_group_admin(event: &MsgEvent) -> bool {
+    is_bot_admin(event.user_id)
+        || matches!(event.sender.role.as_deref(), Some("owner") | Some("admin"))
+}
+
+/// 获取群成员的 qq 号
+pub(crate) async fn group_member_ids(group_id: i64) -> Result<HashSet<i64>> {
+    let bot = BOT.get().unwrap();
+    let res = bot
+        .get_group_member_list(group_id)
+        .await
+        .map_err(|e| anyhow::anyhow!("获取群成员列表失败: {}", e.data))?;
+
+    let members = res
+        .data
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Invalid response"))?
+        .iter()
+        .filter_map(|member| member["user_id"].as_i64())
+        .collect();
+
+    Ok(members)
+}
+
 // 解析指令并替换
 pub(crate) fn change(args: &mut [String], commands: &Value) -> Result<(String, bool)> {
     let mut changed = false;
@@ -70,46 +106,20 @@ pub(crate) fn change(args: &mut [String], commands: &Value) -> Result<(String, b
     Ok((command, changed))
 }
 
-pub(crate) async fn fetch(url: &str) -> Result<reqwest::Response> {
-    fetch_cf_api(async move { reqwest::get(url).await.map_err(Into::into) }).await
-}
-
 pub(crate) async fn fetch_cf_api<F: Future>(future: F) -> F::Output {
     utils::api_limit::limit_api_call("codeforces", std::time::Duration::from_secs(2), 1, future)
         .await
 }
 
-pub(crate) async fn get_user_rating(cf_id: &str) -> Result<i64> {
-    let res = fetch(&format!(
-        "https://codeforces.com/api/user.info?handles={}",
-        cf_id
-    ))
-    .await
-    .map_err(|_| anyhow::anyhow!("Failed to fetch user info"))?;
-
-    let body: Value = res
-        .json()
-        .await
-        .map_err(|_| anyhow::anyhow!("Failed to parse response"))?;
-
-    let status = body["status"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("Invalid response"))?;
-    if status != "OK" {
-        return Err(anyhow::anyhow!("API returned error"));
-    }
-
-    let users = body["result"]
-        .as_array()
-        .ok_or_else(|| anyhow::anyhow!("No user found"))?;
-    if users.is_empty() {
-        return Err(anyhow::anyhow!("User not found"));
-    }
-
-    let user = &users[0];
-    let rating = user["rating"]
-        .as_i64()
-        .ok_or_else(|| anyhow::anyhow!("User has no rating"))?;
+/// ELO 模型下 rating 为 `rating` 的一方胜过 rating 为 `opponent` 的一方的期望
+pub(crate) fn elo_win_probability(rating: i64, opponent: i64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) as f64 / 400.0))
+}
 
-    Ok(rating)
+pub(crate) async fn get_user_rating(cf_id: &str) -> Result<i64> {
+    let users = crate::codeforces::api::user_info(&[cf_id]).await?;
+    users
+        .first()
+        .and_then(|user| user.rating)
+        .ok_or_else(|| anyhow::anyhow!("User has no rating"))
 }
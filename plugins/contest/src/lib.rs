@@ -34,7 +34,8 @@ async fn main() {
             return;
         };
 
-        if !text.starts_with("/contest") {
+        // /contest host 等子命令由 command_handler 处理
+        if text.trim() != "/contest" {
             return;
         }
 
@@ -27,7 +27,13 @@ pub static HELP: LazyLock<Value> = LazyLock::new(|| {
             "/duel statics: 查询历史统计",
             "/duel problem rating：随机一道分数为 rating 的题目"
         ],
-        "contest": "/contest，获取最近的比赛信息",
+        "contest": [
+            "/contest，获取最近的比赛信息",
+            "/contest host <HH:MM|now> <时长(分钟)> <题号...|筛选条件 [-c 题目数]>: 创建群赛（群管理员），例如 /contest host 20:00 120 1600-2000 dp -c 5，筛选条件的写法和 /duel challenge 中一致，按条件选题时开始时才选题，不会选到参赛者通过的题",
+            "/contest join / leave: 报名或退出群赛，比赛开始后也可以报名",
+            "/contest board: 查看群赛的 ICPC 赛制榜单，深绿色为一血",
+            "/contest cancel: 取消群赛（群管理员）"
+        ],
         "cf": [
         ],
         "chat": "/chat [content], 和 ai 聊天",
@@ -38,17 +44,52 @@ pub static HELP: LazyLock<Value> = LazyLock::new(|| {
 });
 
 pub const CF_HELP: &[&str] = &[
-    "/cf rating [@p, cf_id]: 查询用户的 CF rating",
+    "/cf rating [@p, cf_id ...]: 查询用户的 CF rating 曲线，多个用户画在同一张图上",
+    "/cf rating --group: 画出本群所有绑定用户的 rating 曲线，每 10 人一张图",
     "/cf analyze [@p, cf_id]: 查询用户的 CF 做题情况",
+    "/cf group analyze: 汇总本群绑定成员最近半年的提交，按标签统计通过题数和通过率，和群 rating 水平附近的题库比较找出练得少和没人练的标签，并从中挑几道群里没人做过的题作为训练题",
+    "/cf compare [@p, cf_id] [@p, cf_id]...: 对比 2 到 4 个用户的 rating、各难度和标签的做题数、共同参加的比赛排名以及只有自己通过的题目",
+    "/cf predict <比赛 id>: 按 CF 的 rating 算法根据当前榜单预测本群绑定用户的 rating 变化，比赛中和刚结束时都可以使用，rating 正式更新后显示官方结果",
+    "/cf standings <比赛 id> [--unofficial/-u]: 查看本群绑定用户（私聊时为所有绑定用户）在比赛中的排名、每题通过时间、hack 和 rating 变化，加上 -u 后包括打星和虚拟参赛",
+    "/cf upsolve [@p]: 查看比赛后需要补的题（赛时没过的题和最后通过的题的下一题），通过后自动勾掉",
+    "/cf upsolve subscribe / unsubscribe: 开关本群的每周补题提醒（群管理员）",
     r"/cf recommend: 智能推荐题目
 用法：/cf recommend [难度] [选项]
 难度可选 easy, medium(moderate), hard(difficult) 或具体 rating（800-3500 的 100 的倍数）
 选项有 --exclude-solved/-e（排除已解决题目），--count/-c N（推荐 N 个题目，默认为1，最多10个）
 
+其余参数会作为题目筛选条件，用法和 /duel challenge 中一致，例如 (dp|greedy) idx:A-C
+按 rating 估计每道题的通过概率，选出接近目标概率（easy 80%，medium 50%，hard 20%）的题目，并偏向最近通过率低的标签，每道题会给出推荐理由
+
 默认参数是/cf recommend moderate -c 1
 例如：
 /cf recommend easy 1200 -c 3 -r 1200 -e
-推荐3个简单且未解决的rating为1200的题目",
+推荐3个简单且未解决的rating为1200的题目
+最近 30 天推荐过的题目不会再推荐，会根据之前推荐的题目 7 天内的通过率自动调整难度",
+    "/cf recommend history: 查看最近的推荐记录、推荐后多久通过以及各难度的实际通过率",
+    r"/cf plan: 训练计划
+/cf plan create [周数] [目标 rating] [重点标签...]: 生成按周安排的训练计划，默认 4 周、目标为当前 rating + 200（没有 rating 时按 1000 算），每周 5 道题，难度逐周升高；重点标签的写法和 /duel challenge 的筛选条件一致
+/cf plan: 查看完成度、逾期的题目和本周、下周的题目，根据提交自动更新进度，落后或领先时会重新安排下周的题目
+/cf plan cancel: 取消训练计划",
+    "/cf problem <题号> [--tags/-t]: 查看题目的名称、难度、通过人数和链接，标签默认隐藏，加上 -t 显示；同时列出本群绑定用户（私聊时为所有绑定用户）中通过或尝试过这道题的人",
+    "/cf search <关键词...> [筛选条件...] [--exclude-solved/-e] [--page/-p 页码]: 按题目名模糊搜索题库，拼写有误也能找到，可以加上难度、标签等筛选条件（写法和 /duel challenge 一致，但标签要写成 tag:dp，单独的单词都按题目名搜索），-e 排除自己通过的题目，每页 10 道",
+    "/cf tags [rating]: 查询各标签的题目数量和标签别名，可以指定 rating 或 rating 范围",
+    r"/cf virtual: 群内虚拟赛
+/cf virtual create [比赛 id | 条件...] [HH:MM]: 创建虚拟赛，不指定时间时 10 分钟后开始。条件有 div1 div2 div3 div4 edu global，year:2020-2023（比赛年份），rating:1600 或 rating:1400-1800（题目平均难度），开始时会从符合条件且所有参赛者都没有提交过的比赛中随机挑选
+/cf virtual join / leave: 报名或退出
+/cf virtual status: 查看报名情况，比赛中查看当前榜单
+/cf virtual cancel: 取消虚拟赛（创建者或群管理员）
+比赛时间内对这场比赛的提交都会计入成绩，结束后公布榜单和相当于正式比赛的名次",
+    "/cf notify subscribe: 在群内订阅 CF 比赛的 rating 变化播报，rating 更新后会列出本群绑定用户的排名和 rating 变化\n/cf notify unsubscribe: 取消订阅（需要群管理员）",
+    "/cf brag subscribe: 在群内订阅做题喜报，群友通过比自己 rating 高很多的题（默认高 300，可在配置中修改）、赛后补完整场比赛、通过题数达到里程碑时播报\n/cf brag unsubscribe: 取消订阅（需要群管理员）",
+    "/cf problemset status: 查询本地题库的题目数量和上次更新时间\n/cf problemset refresh: 立即从 CF 刷新题库（仅管理员）",
+    "/cf blacklist list: 查看题目黑名单，命中黑名单的题目不会被随机选中（单挑、每日一题、/duel problem、/cf recommend）
+/cf blacklist add <筛选条件> [# 原因]: 添加黑名单规则，筛选条件的写法和 /duel challenge 中一致，例如 /cf blacklist add interactive # 交互题（仅管理员）
+/cf blacklist remove <编号>: 删除黑名单规则（仅管理员）",
+    "/cf pool list [题单名]: 查看所有题单，或者某个题单中的题目，题单可以在筛选条件中用 pool:题单名 引用
+/cf pool add <题单名> <题号>...: 向题单中添加题目，例如 /cf pool add 入门 4A 1900D（仅管理员）
+/cf pool import <题单名> <比赛 id>: 把一场比赛的全部题目加入题单，支持 gym 和 mashup，私有比赛需要配置 cf_api_key（仅管理员）
+/cf pool remove <题单名> [题号]...: 从题单中移除题目，不带题号时删除整个题单（仅管理员）",
 ];
 
 pub static DUEL_HELP: LazyLock<Value> = LazyLock::new(|| {
@@ -64,19 +105,25 @@ pub static DUEL_HELP: LazyLock<Value> = LazyLock::new(|| {
             "data": {
                 "text": "duel challenge @p rating [tags]：挑战用户p，题目将随机选取一道分数为 rating，标签为 [tags] 的题目\n\
                         \n\
-                        标签的用法为：输入 CF 题目中包含的标签，多个标签用空格隔开，本身包含空格的标签请将空格用下划线 \"_\" 替换。可以在标签前加上 \"!\" ，表示要求不包含该标签。\n\
+                        标签的用法为：输入 CF 题目中包含的标签，多个标签用空格隔开，本身包含空格的标签可以直接输入，也可以将空格用下划线 \"_\" 替换，还可以使用中文别名（如 数据结构、贪心，完整列表见 /cf tags）。可以在标签前加上 \"!\" ，表示要求不包含该标签。\n\
                         \n\
                         例如：\n\
                         /duel challenge @EternalAlexander 2400 geometry !data_structures\n\
                         输入以上指令，将挑战用户 EternalAlexander，题目将随机选取一道 rating 为 2400，标签包含 geometry，且不包含 data structures 的题目。\n\
                         \n\
-                        另外支持两个 CF 中不包含的标签。new 将筛选比赛 id >= 1000 的题目，not-seen 将筛选自己没有提交过的题目。"
+                        另外支持两个 CF 中不包含的标签。new 将筛选比赛 id >= 1000 的题目，not-seen 将筛选自己没有提交过的题目。\n\
+                        \n\
+                        更复杂的筛选：用 \"|\" 表示或，用括号分组；rating 可以写成范围 1600-1900；\
+                        contest:1800-1900 筛选比赛 id，solved:1000 要求至少 1000 人通过，idx:A-C 筛选题目序号。\n\
+                        pool:题单名 只从管理员整理的题单中选题，此时可以不写难度；直接写题号（如 1900D）表示指定题目。\n\
+                        没有官方难度的题目默认不会按难度选中，加上 estimated 后会使用根据通过人数估计的难度（显示为 ~1600）。\n\
+                        例如：/duel problem 1600-1900 (dp|greedy) !math idx:A-C"
             }
         },
         {
             "type": "text",
             "data": {
-                "text": "/duel problem rating [tags]：随机一道分数为 rating ，标签为 [tags] 的题目。tag 的用法和 /duel challenge 中一致"
+                "text": "/duel problem rating [tags]：随机一道分数为 rating ，标签为 [tags] 的题目。筛选条件的用法和 /duel challenge 中一致"
             }
         },
         {
@@ -84,7 +131,9 @@ pub static DUEL_HELP: LazyLock<Value> = LazyLock::new(|| {
             "data": {
                 "text": "/duel daily problem 访问今天的每日挑战题目\n\
                         通过每日挑战题目可以得到相应的积分\n\
-                        /duel daily ranklist 可以查询总积分排行"
+                        /duel daily personal problem 领取按你的 CF rating 和薄弱标签挑选的个人每日一题，/duel daily personal finish 完成后领取积分，得分随题目相对你 rating 的难度变化，最多 1200 分\n\
+                        /duel daily ranklist [week|month|all] 可以查询本周、本月或总积分排行，默认为总排行\n\
+                        /duel daily subscribe 在群内订阅每周日晚的每日任务播报，/duel daily unsubscribe 取消订阅（需要群管理员）"
             }
         },
         {
@@ -3,15 +3,18 @@
 //! 提供一个全局的、可跨 crate 使用的 API 访问管理器。
 //!
 //! # 示例
-//! ```rust
+//! 限速器依赖 kovi 的任务管理，只能在插件中运行
+//! ```rust,no_run
 //! use utils::api_limit::limit_api_call;
 //! use std::time::Duration;
 //!
+//! # async fn example() {
 //! // 手动限制
 //! let result = limit_api_call("MY_API", Duration::from_secs(1), 5, async {
 //!     // API 调用
 //!     "data"
 //! }).await;
+//! # }
 //! ```
 
 use kovi::tokio::{
@@ -79,8 +82,10 @@ async fn global_registry() -> &'static LimiterRegistry {
 /// * `f`: 需要被限制速率的异步操作。
 ///
 /// # 示例
-/// ```
+/// ```no_run
 /// use std::time::Duration;
+/// use kovi::tokio;
+/// use utils::api_limit::limit_api_call;
 ///
 /// async fn my_api_call() -> &'static str {
 ///     // 模拟网络请求
@@ -88,6 +93,7 @@ async fn global_registry() -> &'static LimiterRegistry {
 ///     "Data received"
 /// }
 ///
+/// # async fn example() {
 /// // 限制 "MY_API" 每秒最多调用 5 次
 /// let result = limit_api_call(
 ///     "MY_API",
@@ -97,6 +103,7 @@ async fn global_registry() -> &'static LimiterRegistry {
 /// ).await;
 ///
 /// assert_eq!(result, "Data received");
+/// # }
 /// ```
 pub async fn limit_api_call<F, R>(
     api_identifier: &'static str,